@@ -0,0 +1,50 @@
+//! Benchmarking cho pallet tokens
+
+use super::*;
+use crate::Pallet as Tokens;
+use frame_benchmarking::{account, benchmarks, whitelisted_caller};
+use frame_support::{sp_std::vec, traits::EnsureOrigin};
+use frame_system::RawOrigin;
+
+const SEED: u32 = 0;
+
+// owner issue token, trả về token_hash
+fn issue_token<T: Config>(owner: &T::AccountId) -> T::Hash {
+	let index = Tokens::<T>::owned_token_total(owner.clone());
+	Tokens::<T>::do_issue(owner.clone(), b"TOKEN".to_vec(), u32::MAX.into()).unwrap();
+	Tokens::<T>::owned_token_index(owner.clone(), index).unwrap()
+}
+
+benchmarks! {
+	// symbol dài nhất có thể
+	issue {
+		let caller: T::AccountId = whitelisted_caller();
+		let symbol = vec![b'T'; T::MaxSymbolLength::get() as usize];
+		let total_supply: Balance<T> = u32::MAX.into();
+	}: _(RawOrigin::Signed(caller.clone()), symbol, total_supply)
+	verify {
+		let token_hash = Tokens::<T>::owned_token_index(caller.clone(), 0).unwrap();
+		assert_eq!(Tokens::<T>::balance_of(caller, token_hash), total_supply);
+	}
+
+	transfer {
+		let caller: T::AccountId = whitelisted_caller();
+		let to: T::AccountId = account("to", 0, SEED);
+		let token_hash = issue_token::<T>(&caller);
+		let amount: Balance<T> = 1_000u32.into();
+	}: _(RawOrigin::Signed(caller), to.clone(), token_hash, amount)
+	verify {
+		assert_eq!(Tokens::<T>::balance_of(to, token_hash), amount);
+	}
+
+	force_transfer {
+		let from: T::AccountId = account("from", 0, SEED);
+		let to: T::AccountId = account("to", 0, SEED);
+		let token_hash = issue_token::<T>(&from);
+		let amount: Balance<T> = 1_000u32.into();
+		let origin = T::AdminOrigin::successful_origin();
+	}: _<T::RuntimeOrigin>(origin, from, to.clone(), token_hash, amount)
+	verify {
+		assert_eq!(Tokens::<T>::balance_of(to, token_hash), amount);
+	}
+}
@@ -1,6 +1,12 @@
 #![cfg_attr(not(feature = "std"), no_std)]
 
+pub mod migrations;
+pub mod weights;
 pub use pallet::*;
+pub use weights::WeightInfo;
+
+#[cfg(feature = "runtime-benchmarks")]
+mod benchmarking;
 
 #[frame_support::pallet]
 pub mod pallet {
@@ -11,25 +17,30 @@ pub mod pallet {
 			ArithmeticError,
 		},
 		sp_std::vec::Vec,
-		traits::{Currency, Randomness},
+		traits::{Currency, EnsureOrigin, Randomness},
 		Blake2_128Concat,
 	};
 	use frame_system::pallet_prelude::*;
 	use scale_info::TypeInfo;
+	use crate::weights::WeightInfo;
+
+	// version của storage, tăng lên khi thay đổi cấu trúc storage và cần migration
+	pub const STORAGE_VERSION: StorageVersion = StorageVersion::new(1);
 
 	#[pallet::pallet]
 	#[pallet::generate_store(pub(super) trait Store)]
-	#[pallet::without_storage_info]
+	#[pallet::storage_version(STORAGE_VERSION)]
 	pub struct Pallet<T>(_);
 
 	pub type Balance<T> = <<T as Config>::Currency as Currency<<T as frame_system::Config>::AccountId>>::Balance; // Khai báo balance type
 
-	#[derive(Clone, Encode, Decode, PartialEq, RuntimeDebug, TypeInfo)]
+	#[derive(Clone, Encode, Decode, PartialEq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
 	#[scale_info(skip_type_params(T))]
+	#[codec(mel_bound())]
 	// Thông tin của 1 token gồm: hash, symbol và tổng supply
 	pub struct Token<T: Config> {
 		pub hash: T::Hash,
-		pub symbol: Vec<u8>,
+		pub symbol: BoundedVec<u8, T::MaxSymbolLength>,
 		pub total_supply: Balance<T>,
 	}
 
@@ -51,6 +62,9 @@ pub mod pallet {
 		type RuntimeEvent: From<Event<Self>> + IsType<<Self as frame_system::Config>::RuntimeEvent>;
 		type Currency: Currency<Self::AccountId>; // dùng để định dạng type Balance
 		type TokenRandom: Randomness<Self::Hash, Self::BlockNumber>; // dùng để tạo random
+		type AdminOrigin: EnsureOrigin<Self::RuntimeOrigin>; // origin của governance, dùng cho các call đặc quyền
+		type MaxSymbolLength: Get<u32>; // độ dài tối đa của symbol (số byte)
+		type WeightInfo: WeightInfo; // weight của các extrinsic, sinh từ benchmarking.rs
 	}
 
 	#[pallet::storage]
@@ -100,6 +114,7 @@ pub mod pallet {
 		Transferred { from: T::AccountId, to: T::AccountId, token_hash: T::Hash, amount: Balance<T> },  // when user transfer token success
 		Freezed { owner: T::AccountId, token_hash: T::Hash, amount: Balance<T> },						// when user make an order
 		Unfreezed { owner: T::AccountId, token_hash: T::Hash, amount: Balance<T> },						// when user cancel an order
+		ForceTransferred { from: T::AccountId, to: T::AccountId, token_hash: T::Hash, amount: Balance<T> },	// when admin force transfer token
 	}
 
 	#[pallet::error]
@@ -109,33 +124,55 @@ pub mod pallet {
 		BalanceNotEnough,           // The balance is not enough
 		AmountOverFlow,             // Amount overflow
 		SenderHaveNoToken,          // Sender does not have token
+		SymbolTooLong,              // Symbol is longer than MaxSymbolLength
+	}
+
+	#[pallet::hooks]
+	impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
+		// check bất biến của storage, chạy bằng try-runtime trên snapshot của chain
+		#[cfg(feature = "try-runtime")]
+		fn try_state(_n: T::BlockNumber) -> Result<(), &'static str> {
+			Self::do_try_state()
+		}
 	}
 
 	#[pallet::call]
 	impl<T: Config> Pallet<T> {
-		#[pallet::weight(100)]  // fn issue a token 
+		#[pallet::weight(T::WeightInfo::issue())]  // fn issue a token 
 		pub fn issue( origin: OriginFor<T>, symbol: Vec<u8>, total_supply: Balance<T> ) -> DispatchResult {
 			let sender = ensure_signed(origin)?;
 			Self::do_issue(sender, symbol, total_supply)
 		}
 
-		#[pallet::weight(100)] // fn transfer a token 
+		#[pallet::weight(T::WeightInfo::transfer())] // fn transfer a token 
 		pub fn transfer( origin: OriginFor<T>, to: T::AccountId, token_hash: T::Hash, amount: Balance<T> ) -> DispatchResult {
 			let from = ensure_signed(origin)?;
 			ensure!(from != to, <Error<T>>::MustNotTransferYourself);
 			Self::do_transfer(from, to, token_hash, amount)
 		}
+
+		#[pallet::weight(T::WeightInfo::force_transfer())] // fn admin transfer a token from any account
+		pub fn force_transfer( origin: OriginFor<T>, from: T::AccountId, to: T::AccountId, token_hash: T::Hash, amount: Balance<T> ) -> DispatchResult {
+			T::AdminOrigin::ensure_origin(origin)?;
+			ensure!(from != to, <Error<T>>::MustNotTransferYourself);
+			Self::do_transfer(from.clone(), to.clone(), token_hash, amount)?;
+			Self::deposit_event(Event::ForceTransferred { from, to, token_hash, amount });
+			Ok(())
+		}
 	}
 
 	// Helper fn
 	impl<T: Config> Pallet<T> {
 		// fn when issue a token
 		pub fn do_issue( sender: T::AccountId, symbol: Vec<u8>, total_supply: Balance<T> ) -> DispatchResult {
+			// symbol phải nằm trong giới hạn MaxSymbolLength
+			let symbol: BoundedVec<u8, T::MaxSymbolLength> = symbol.try_into().map_err(|_| <Error<T>>::SymbolTooLong)?;
+
 			// create a token struct
 			let nonce = Self::nonce();
 			let random = T::TokenRandom::random(&symbol).0;
 			let token_hash = (random, sender.clone(), nonce).using_encoded(T::Hashing::hash);
-			let token = Token::<T> { hash: token_hash.clone(), symbol: symbol.clone(), total_supply };
+			let token = Token::<T> { hash: token_hash.clone(), symbol, total_supply };
 
 			// update nonce
 			let new_nonce = nonce.checked_add(1).ok_or(ArithmeticError::Overflow)?;
@@ -264,5 +301,43 @@ pub mod pallet {
 				OptionBalance::FreezedBalance => <FreezedBalanceOf<T>>::insert(owner, token_hash, balance),
 			};
 		}
+
+		/* fn check bất biến của storage:
+			+ Balance = FreeBalance + FreezedBalance với mọi account, mọi token
+			+ tổng Balance của mọi account = total_supply với mọi token
+		*/
+		#[cfg(feature = "try-runtime")]
+		pub fn do_try_state() -> Result<(), &'static str> {
+			use frame_support::{sp_runtime::traits::{CheckedAdd, Zero}, sp_std::collections::btree_map::BTreeMap};
+
+			let mut supplies: BTreeMap<T::Hash, Balance<T>> = BTreeMap::new();
+
+			for (owner, token_hash, balance) in <BalanceOf<T>>::iter() {
+				ensure!(Self::tokens(token_hash).is_some(), "Balance of a token that does not exist");
+
+				let free_balance = Self::free_balance_of(owner.clone(), token_hash);
+				let freezed_balance = Self::freezed_balance_of(owner, token_hash);
+				ensure!(free_balance.checked_add(&freezed_balance) == Some(balance), "Balance is not equal to free balance + freezed balance");
+
+				let supply = supplies.entry(token_hash).or_insert_with(Zero::zero);
+				*supply = supply.checked_add(&balance).ok_or("Total balance of a token overflows")?;
+			}
+
+			// account có free/freezed mà không có balance => cả 2 phải bằng 0
+			for (owner, token_hash) in <FreeBalanceOf<T>>::iter_keys().chain(<FreezedBalanceOf<T>>::iter_keys()) {
+				if !<BalanceOf<T>>::contains_key(owner.clone(), token_hash) {
+					let free_balance = Self::free_balance_of(owner.clone(), token_hash);
+					let freezed_balance = Self::freezed_balance_of(owner, token_hash);
+					ensure!(free_balance.is_zero() && freezed_balance.is_zero(), "Free or freezed balance without balance");
+				}
+			}
+
+			for (token_hash, token) in <Tokens<T>>::iter() {
+				let supply = supplies.get(&token_hash).cloned().unwrap_or_else(Zero::zero);
+				ensure!(supply == token.total_supply, "Total balance is not equal to total supply");
+			}
+
+			Ok(())
+		}
 	}
 }
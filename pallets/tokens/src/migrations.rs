@@ -0,0 +1,56 @@
+use crate::{Balance, Config, Pallet, Tokens, Token};
+use codec::{Decode, Encode};
+use frame_support::{
+	pallet_prelude::*,
+	sp_std::{marker::PhantomData, vec::Vec},
+	traits::{GetStorageVersion, OnRuntimeUpgrade, StorageVersion},
+};
+
+/* v0 => v1: symbol của token chuyển từ Vec<u8> sang BoundedVec<u8, MaxSymbolLength>
+	Encode của Vec và BoundedVec giống nhau nên chỉ cần decode lại, symbol dài hơn MaxSymbolLength thì bị cắt bớt
+*/
+pub mod v1 {
+	use super::*;
+
+	#[derive(Encode, Decode)]
+	struct OldToken<T: Config> {
+		hash: T::Hash,
+		symbol: Vec<u8>,
+		total_supply: Balance<T>,
+	}
+
+	// khai báo trong ds Migrations của runtime (Executive) để chạy khi nâng spec_version
+	pub struct MigrateToV1<T>(PhantomData<T>);
+
+	impl<T: Config> OnRuntimeUpgrade for MigrateToV1<T> {
+		fn on_runtime_upgrade() -> Weight {
+			migrate::<T>()
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn post_upgrade(_state: Vec<u8>) -> Result<(), &'static str> {
+			ensure!(StorageVersion::get::<Pallet<T>>() == crate::STORAGE_VERSION, "Storage version was not updated");
+			Pallet::<T>::do_try_state()
+		}
+	}
+
+	pub fn migrate<T: Config>() -> Weight {
+		let onchain_version = Pallet::<T>::on_chain_storage_version();
+		if onchain_version >= 1 {
+			return T::DbWeight::get().reads(1)
+		}
+
+		let mut translated: u64 = 0;
+		<Tokens<T>>::translate::<OldToken<T>, _>(|_, old| {
+			translated += 1;
+			let mut symbol = old.symbol;
+			symbol.truncate(T::MaxSymbolLength::get() as usize);
+			let symbol = BoundedVec::try_from(symbol).ok()?;
+			Some(Token { hash: old.hash, symbol, total_supply: old.total_supply })
+		});
+
+		StorageVersion::new(1).put::<Pallet<T>>();
+
+		T::DbWeight::get().reads_writes(1 + translated, 1 + translated)
+	}
+}
@@ -0,0 +1,72 @@
+//! Weights for pallet_tokens
+//!
+//! CHƯA CÓ KẾT QUẢ BENCHMARK: các weight dưới đây được viết tay theo số lần đọc/ghi storage của từng extrinsic,
+//! không phải output của `benchmark pallet`
+//! => chạy `scripts/benchmark.sh` trên máy chuẩn để sinh lại toàn bộ file này từ `benchmarking.rs` trước khi lên production
+
+#![cfg_attr(rustfmt, rustfmt_skip)]
+#![allow(unused_parens)]
+#![allow(unused_imports)]
+
+use frame_support::{traits::Get, weights::{Weight, constants::RocksDbWeight}};
+use frame_support::sp_std::marker::PhantomData;
+
+/// Weight functions needed for pallet_tokens.
+pub trait WeightInfo {
+	fn issue() -> Weight;
+	fn transfer() -> Weight;
+	fn force_transfer() -> Weight;
+}
+
+/// Weights viết tay cho pallet_tokens, chưa đo trên máy chuẩn.
+pub struct SubstrateWeight<T>(PhantomData<T>);
+impl<T: frame_system::Config> WeightInfo for SubstrateWeight<T> {
+	// ước lượng: Tokens::Nonce đọc 1, ghi 1
+	// ước lượng: RandomnessCollectiveFlip::RandomMaterial đọc 1, ghi 0
+	// ước lượng: Tokens::OwnedTokensTotal đọc 1, ghi 1
+	// ước lượng: Tokens::Tokens đọc 0, ghi 1
+	// ước lượng: Tokens::Owners đọc 0, ghi 1
+	// ước lượng: Tokens::BalanceOf đọc 0, ghi 1
+	// ước lượng: Tokens::FreeBalanceOf đọc 0, ghi 1
+	// ước lượng: Tokens::OwnedTokensIndex đọc 0, ghi 1
+	fn issue() -> Weight {
+		Weight::from_ref_time(28_000_000 as u64)
+			.saturating_add(T::DbWeight::get().reads(3 as u64))
+			.saturating_add(T::DbWeight::get().writes(7 as u64))
+	}
+	// ước lượng: Tokens::Tokens đọc 1, ghi 0
+	// ước lượng: Tokens::FreeBalanceOf đọc 2, ghi 2
+	// ước lượng: Tokens::BalanceOf đọc 2, ghi 2
+	fn transfer() -> Weight {
+		Weight::from_ref_time(31_000_000 as u64)
+			.saturating_add(T::DbWeight::get().reads(5 as u64))
+			.saturating_add(T::DbWeight::get().writes(4 as u64))
+	}
+	// ước lượng: Tokens::Tokens đọc 1, ghi 0
+	// ước lượng: Tokens::FreeBalanceOf đọc 2, ghi 2
+	// ước lượng: Tokens::BalanceOf đọc 2, ghi 2
+	fn force_transfer() -> Weight {
+		Weight::from_ref_time(32_000_000 as u64)
+			.saturating_add(T::DbWeight::get().reads(5 as u64))
+			.saturating_add(T::DbWeight::get().writes(4 as u64))
+	}
+}
+
+// For backwards compatibility and tests
+impl WeightInfo for () {
+	fn issue() -> Weight {
+		Weight::from_ref_time(28_000_000 as u64)
+			.saturating_add(RocksDbWeight::get().reads(3 as u64))
+			.saturating_add(RocksDbWeight::get().writes(7 as u64))
+	}
+	fn transfer() -> Weight {
+		Weight::from_ref_time(31_000_000 as u64)
+			.saturating_add(RocksDbWeight::get().reads(5 as u64))
+			.saturating_add(RocksDbWeight::get().writes(4 as u64))
+	}
+	fn force_transfer() -> Weight {
+		Weight::from_ref_time(32_000_000 as u64)
+			.saturating_add(RocksDbWeight::get().reads(5 as u64))
+			.saturating_add(RocksDbWeight::get().writes(4 as u64))
+	}
+}
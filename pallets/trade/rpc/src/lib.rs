@@ -0,0 +1,116 @@
+//! RPC interface for the trade pallet.
+
+use std::{marker::PhantomData, sync::Arc};
+
+use codec::Codec;
+use jsonrpsee::{
+	core::RpcResult,
+	proc_macros::rpc,
+	types::error::{CallError, ErrorObject},
+};
+use pallet_trade::runtime_api::{Candle, OrderBook};
+use sp_api::ProvideRuntimeApi;
+use sp_blockchain::HeaderBackend;
+use sp_runtime::{generic::BlockId, traits::Block as BlockT};
+
+pub use pallet_trade::runtime_api::TradeApi as TradeRuntimeApi;
+
+#[rpc(server)]
+pub trait TradeApi<BlockHash, Hash, BlockNumber, Price, Balance> {
+	/// Aggregated bid and ask levels of a trade pair, best price first.
+	#[method(name = "trade_getOrderBook")]
+	fn get_order_book(
+		&self,
+		tp_hash: Hash,
+		depth: u32,
+		at: Option<BlockHash>,
+	) -> RpcResult<OrderBook<Price, Balance>>;
+
+	/// Latest OHLCV candles of a trade pair at the given interval (in blocks), oldest first.
+	#[method(name = "trade_getCandles")]
+	fn get_candles(
+		&self,
+		tp_hash: Hash,
+		interval: u32,
+		limit: u32,
+		at: Option<BlockHash>,
+	) -> RpcResult<Vec<Candle<BlockNumber, Price, Balance>>>;
+}
+
+/// Provides RPC methods to query the order books of the trade pallet.
+pub struct Trade<C, Block> {
+	client: Arc<C>,
+	_marker: PhantomData<Block>,
+}
+
+impl<C, Block> Trade<C, Block> {
+	/// Creates a new instance of the Trade RPC helper.
+	pub fn new(client: Arc<C>) -> Self {
+		Self { client, _marker: Default::default() }
+	}
+}
+
+/// Error type of this RPC api.
+pub enum Error {
+	/// The call to runtime failed.
+	RuntimeError,
+}
+
+impl From<Error> for i32 {
+	fn from(e: Error) -> i32 {
+		match e {
+			Error::RuntimeError => 1,
+		}
+	}
+}
+
+impl<C, Block, Hash, BlockNumber, Price, Balance>
+	TradeApiServer<<Block as BlockT>::Hash, Hash, BlockNumber, Price, Balance> for Trade<C, Block>
+where
+	Block: BlockT,
+	C: ProvideRuntimeApi<Block> + HeaderBackend<Block> + Send + Sync + 'static,
+	C::Api: TradeRuntimeApi<Block, Hash, BlockNumber, Price, Balance>,
+	Hash: Codec,
+	BlockNumber: Codec,
+	Price: Codec,
+	Balance: Codec,
+{
+	fn get_order_book(
+		&self,
+		tp_hash: Hash,
+		depth: u32,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> RpcResult<OrderBook<Price, Balance>> {
+		let api = self.client.runtime_api();
+		let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+
+		api.order_book(&at, tp_hash, depth).map_err(|e| {
+			CallError::Custom(ErrorObject::owned(
+				Error::RuntimeError.into(),
+				"Unable to query order book.",
+				Some(e.to_string()),
+			))
+			.into()
+		})
+	}
+
+	fn get_candles(
+		&self,
+		tp_hash: Hash,
+		interval: u32,
+		limit: u32,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> RpcResult<Vec<Candle<BlockNumber, Price, Balance>>> {
+		let api = self.client.runtime_api();
+		let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+
+		api.candles(&at, tp_hash, interval, limit).map_err(|e| {
+			CallError::Custom(ErrorObject::owned(
+				Error::RuntimeError.into(),
+				"Unable to query candles.",
+				Some(e.to_string()),
+			))
+			.into()
+		})
+	}
+}
@@ -0,0 +1,240 @@
+//! Benchmarking cho pallet trade
+
+use super::*;
+use crate::Pallet as Trade;
+use frame_benchmarking::{account, benchmarks, impl_benchmark_test_suite, whitelisted_caller};
+use frame_support::{
+    sp_runtime::traits::Bounded,
+    traits::{Currency, EnsureOrigin},
+};
+use frame_system::RawOrigin;
+use pallet_tokens::Balance;
+
+const SEED: u32 = 0;
+// lượng quote của mỗi maker order
+const MAKER_AMOUNT: u32 = 100;
+
+// giá theo PriceFactor, ví dụ price(2) => 1 quote = 2 base
+fn price<T: Config>(units: u32) -> T::Price {
+    T::Price::from(units as u128 * T::PriceFactor::get())
+}
+
+// owner issue token, trả về token_hash
+fn issue_token<T: Config>(owner: &T::AccountId, symbol: &[u8]) -> T::Hash {
+    let index = pallet_tokens::Pallet::<T>::owned_token_total(owner.clone());
+    pallet_tokens::Pallet::<T>::do_issue(owner.clone(), symbol.to_vec(), u32::MAX.into()).unwrap();
+    pallet_tokens::Pallet::<T>::owned_token_index(owner.clone(), index).unwrap()
+}
+
+// owner có đủ native token để cọc khi tạo cặp
+fn fund_deposit<T: Config>(owner: &T::AccountId) {
+    let balance = <T::DepositCurrency as Currency<T::AccountId>>::Balance::max_value() / 2u32.into();
+    T::DepositCurrency::make_free_balance_be(owner, balance);
+}
+
+// owner issue BUSD, BTC rồi tạo cặp BUSD/BTC => trả về (base, quote)
+fn setup_trade_pair<T: Config>(owner: &T::AccountId) -> (T::Hash, T::Hash) {
+    fund_deposit::<T>(owner);
+    let base = issue_token::<T>(owner, b"BUSD");
+    let quote = issue_token::<T>(owner, b"BTC");
+    Trade::<T>::create_trade_pair(RawOrigin::Signed(owner.clone()).into(), base, quote).unwrap();
+    (base, quote)
+}
+
+// chuyển token từ owner rồi đặt lệnh limit GTC, trả về order_hash
+fn place_limit_order<T: Config>(owner: &T::AccountId, who: &T::AccountId, base: T::Hash, quote: T::Hash, otype: OrderType, price: T::Price, sell_amount: Balance<T>) -> T::Hash {
+    let sell_hash = match otype {
+        OrderType::Buy => base,
+        OrderType::Sell => quote,
+    };
+    pallet_tokens::Pallet::<T>::do_transfer(owner.clone(), who.clone(), sell_hash, sell_amount).unwrap();
+    Trade::<T>::create_order(RawOrigin::Signed(who.clone()).into(), base, quote, OrderOpt::Limit, otype, price, sell_amount, TimeInForce::GoodTilCanceled, false, None, None).unwrap();
+    last_order_hash::<T>(who)
+}
+
+// đặt stop order limit Buy tại trigger_price, trả về stop_order_hash
+fn place_stop_order<T: Config>(who: &T::AccountId, base: T::Hash, quote: T::Hash, trigger_price: T::Price) -> T::Hash {
+    Trade::<T>::create_stop_order(RawOrigin::Signed(who.clone()).into(), base, quote, OrderOpt::Limit, OrderType::Buy, trigger_price, price::<T>(10), (MAKER_AMOUNT * 10).into()).unwrap();
+    let tp_hash = Trade::<T>::trade_pair_hash_by_base_quote((base, quote)).unwrap();
+    *<TPStopOrders<T>>::get((tp_hash, OrderType::Buy), trigger_price).last().unwrap()
+}
+
+// order được tạo gần nhất của account
+fn last_order_hash<T: Config>(who: &T::AccountId) -> T::Hash {
+    let index = Trade::<T>::owned_orders_index(who.clone());
+    Trade::<T>::owned_orders(who.clone(), index - 1).unwrap()
+}
+
+benchmarks! {
+    create_trade_pair {
+        let caller: T::AccountId = whitelisted_caller();
+        fund_deposit::<T>(&caller);
+        let base = issue_token::<T>(&caller, b"BUSD");
+        let quote = issue_token::<T>(&caller, b"BTC");
+    }: _(RawOrigin::Signed(caller), base, quote)
+    verify {
+        assert!(Trade::<T>::trade_pair_hash_by_base_quote((base, quote)).is_some());
+    }
+
+    /* taker Buy khớp hết f maker order Sell nằm trên l mức giá
+        mỗi maker là 1 account riêng để không chạm OpenedOrdersArrayCap và self-trade prevention
+        mỗi mức giá chứa tối đa MaxOrdersPerPriceLevel order => f lớn thì số mức giá thực tế có thể lớn hơn l
+    */
+    create_order {
+        let l in 1 .. T::MaxMarketOrderFills::get();
+        let f in 1 .. T::MaxMarketOrderFills::get();
+
+        let owner: T::AccountId = account("owner", 0, SEED);
+        let (base, quote) = setup_trade_pair::<T>(&owner);
+
+        let per_level = T::MaxOrdersPerPriceLevel::get();
+        let levels = l.min(f).max((f + per_level - 1) / per_level);
+        for i in 0 .. f {
+            let maker: T::AccountId = account("maker", i, SEED);
+            place_limit_order::<T>(&owner, &maker, base, quote, OrderType::Sell, price::<T>(10 + i % levels), MAKER_AMOUNT.into());
+        }
+
+        // taker đặt giá bằng mức giá cao nhất, sell_amount đủ mua hết lượng của các maker tại giá đó
+        let caller: T::AccountId = whitelisted_caller();
+        let worst_price = 10 + levels - 1;
+        let sell_amount: Balance<T> = (f * MAKER_AMOUNT * worst_price).into();
+        pallet_tokens::Pallet::<T>::do_transfer(owner, caller.clone(), base, sell_amount)?;
+    }: _(RawOrigin::Signed(caller.clone()), base, quote, OrderOpt::Limit, OrderType::Buy, price::<T>(worst_price), sell_amount, TimeInForce::GoodTilCanceled, false, None, None)
+    verify {
+        let order = Trade::<T>::orders(last_order_hash::<T>(&caller)).unwrap();
+        assert_eq!(order.status, OrderStatus::Filled);
+    }
+
+    // order bị hủy nằm cuối mức giá đã đủ MaxOrdersPerPriceLevel order
+    cancel_limit_order {
+        let owner: T::AccountId = account("owner", 0, SEED);
+        let (base, quote) = setup_trade_pair::<T>(&owner);
+
+        let sell_amount: Balance<T> = (MAKER_AMOUNT * 10).into();
+        for i in 1 .. T::MaxOrdersPerPriceLevel::get() {
+            let maker: T::AccountId = account("maker", i, SEED);
+            place_limit_order::<T>(&owner, &maker, base, quote, OrderType::Buy, price::<T>(10), sell_amount);
+        }
+
+        let caller: T::AccountId = whitelisted_caller();
+        let order_hash = place_limit_order::<T>(&owner, &caller, base, quote, OrderType::Buy, price::<T>(10), sell_amount);
+    }: _(RawOrigin::Signed(caller), order_hash)
+    verify {
+        assert_eq!(Trade::<T>::orders(order_hash).unwrap().status, OrderStatus::Canceled);
+    }
+
+    update_trade_pair_rules {
+        let owner: T::AccountId = account("owner", 0, SEED);
+        let (base, quote) = setup_trade_pair::<T>(&owner);
+        let tp_hash = Trade::<T>::trade_pair_hash_by_base_quote((base, quote)).unwrap();
+        let origin = T::AdminOrigin::successful_origin();
+    }: _<T::RuntimeOrigin>(origin, tp_hash, price::<T>(1), 10u32.into(), 100u32.into())
+    verify {
+        assert_eq!(Trade::<T>::trade_pairs(tp_hash).unwrap().min_notional, 100u32.into());
+    }
+
+    // delist là trường hợp nặng nhất: đưa vào hàng đợi sweep và hoàn tiền cọc
+    set_trade_pair_status {
+        let owner: T::AccountId = account("owner", 0, SEED);
+        let (base, quote) = setup_trade_pair::<T>(&owner);
+        let tp_hash = Trade::<T>::trade_pair_hash_by_base_quote((base, quote)).unwrap();
+        let origin = T::AdminOrigin::successful_origin();
+    }: _<T::RuntimeOrigin>(origin, tp_hash, TradePairStatus::Delisted)
+    verify {
+        assert_eq!(Trade::<T>::trade_pairs(tp_hash).unwrap().status, TradePairStatus::Delisted);
+    }
+
+    set_self_trade_prevention {
+        let caller: T::AccountId = whitelisted_caller();
+    }: _(RawOrigin::Signed(caller.clone()), SelfTradePrevention::CancelBoth)
+    verify {
+        assert_eq!(Trade::<T>::self_trade_prevention(caller), Some(SelfTradePrevention::CancelBoth));
+    }
+
+    // phía Buy đã có MaxStopTriggerPrices - 1 trigger_price, stop order mới chèn vào đầu ds trigger_price
+    create_stop_order {
+        let owner: T::AccountId = account("owner", 0, SEED);
+        let (base, quote) = setup_trade_pair::<T>(&owner);
+        let tp_hash = Trade::<T>::trade_pair_hash_by_base_quote((base, quote)).unwrap();
+        for i in 2 ..= T::MaxStopTriggerPrices::get() {
+            place_stop_order::<T>(&owner, base, quote, price::<T>(i));
+        }
+
+        let caller: T::AccountId = whitelisted_caller();
+        let sell_amount: Balance<T> = (MAKER_AMOUNT * 10).into();
+        pallet_tokens::Pallet::<T>::do_transfer(owner, caller.clone(), base, sell_amount)?;
+    }: _(RawOrigin::Signed(caller), base, quote, OrderOpt::Limit, OrderType::Buy, price::<T>(1), price::<T>(10), sell_amount)
+    verify {
+        assert_eq!(<TPStopTriggerPrices<T>>::get((tp_hash, OrderType::Buy)).len() as u32, T::MaxStopTriggerPrices::get());
+    }
+
+    // stop order bị hủy nằm cuối trigger_price đã đủ MaxStopOrdersPerPrice stop order
+    cancel_stop_order {
+        let owner: T::AccountId = account("owner", 0, SEED);
+        let (base, quote) = setup_trade_pair::<T>(&owner);
+        for _ in 1 .. T::MaxStopOrdersPerPrice::get() {
+            place_stop_order::<T>(&owner, base, quote, price::<T>(1));
+        }
+
+        let caller: T::AccountId = whitelisted_caller();
+        pallet_tokens::Pallet::<T>::do_transfer(owner, caller.clone(), base, (MAKER_AMOUNT * 10).into())?;
+        let stop_order_hash = place_stop_order::<T>(&caller, base, quote, price::<T>(1));
+    }: _(RawOrigin::Signed(caller), stop_order_hash)
+    verify {
+        assert_eq!(Trade::<T>::stop_orders(stop_order_hash).unwrap().status, StopOrderStatus::Canceled);
+    }
+
+    // on_initialize hủy 1 order hết hạn, weight của hook = weight này * số order bị hủy
+    expire_order {
+        let owner: T::AccountId = account("owner", 0, SEED);
+        let (base, quote) = setup_trade_pair::<T>(&owner);
+
+        let caller: T::AccountId = whitelisted_caller();
+        let sell_amount: Balance<T> = (MAKER_AMOUNT * 10).into();
+        pallet_tokens::Pallet::<T>::do_transfer(owner, caller.clone(), base, sell_amount)?;
+        let expires_at = frame_system::Pallet::<T>::block_number() + 1u32.into();
+        Trade::<T>::create_order(RawOrigin::Signed(caller.clone()).into(), base, quote, OrderOpt::Limit, OrderType::Buy, price::<T>(10), sell_amount, TimeInForce::GoodTilCanceled, false, Some(expires_at), None)?;
+        let order_hash = last_order_hash::<T>(&caller);
+    }: {
+        Trade::<T>::expire_orders(expires_at);
+    }
+    verify {
+        assert_eq!(Trade::<T>::orders(order_hash).unwrap().status, OrderStatus::Canceled);
+    }
+
+    // on_initialize hủy 1 order của cặp đã delist, weight của hook = weight này * số order bị hủy
+    sweep_delisted_order {
+        let owner: T::AccountId = account("owner", 0, SEED);
+        let (base, quote) = setup_trade_pair::<T>(&owner);
+        let tp_hash = Trade::<T>::trade_pair_hash_by_base_quote((base, quote)).unwrap();
+
+        let caller: T::AccountId = whitelisted_caller();
+        let order_hash = place_limit_order::<T>(&owner, &caller, base, quote, OrderType::Buy, price::<T>(10), (MAKER_AMOUNT * 10).into());
+        Trade::<T>::set_trade_pair_status(T::AdminOrigin::successful_origin(), tp_hash, TradePairStatus::Delisted)?;
+    }: {
+        Trade::<T>::sweep_delisted_trade_pairs();
+    }
+    verify {
+        assert_eq!(Trade::<T>::orders(order_hash).unwrap().status, OrderStatus::Canceled);
+    }
+
+    // cập nhật nến ở 1 interval, trường hợp xấu nhất: tạo nến mới khi đã đủ CandleRetention nến => xóa nến cũ nhất
+    // weight của mỗi order khớp = weight này * số interval trong CandleIntervals
+    update_candle {
+        let owner: T::AccountId = account("owner", 0, SEED);
+        let (base, quote) = setup_trade_pair::<T>(&owner);
+        let tp_hash = Trade::<T>::trade_pair_hash_by_base_quote((base, quote)).unwrap();
+
+        let retention = T::CandleRetention::get();
+        for block in 0..retention {
+            Trade::<T>::update_candle(tp_hash, 1, block.into(), price::<T>(1), MAKER_AMOUNT.into(), MAKER_AMOUNT.into());
+        }
+    }: {
+        Trade::<T>::update_candle(tp_hash, 1, retention.into(), price::<T>(2), MAKER_AMOUNT.into(), MAKER_AMOUNT.into());
+    }
+    verify {
+        assert_eq!(Trade::<T>::candles(tp_hash, 1, 1).first().map(|candle| candle.open), Some(price::<T>(2)));
+    }
+}
+
+impl_benchmark_test_suite!(Trade, crate::mock::new_test_ext(), crate::mock::Test);
@@ -0,0 +1,154 @@
+/* Property-based test: sinh ngẫu nhiên 1 chuỗi thao tác (đặt lệnh limit/market, hủy lệnh, đổi chế độ self-trade, sang block mới)
+    trên mock runtime và check các bất biến sau mỗi thao tác:
+    + với mỗi token: tổng BalanceOf của mọi account = total_supply
+    + với mỗi account: BalanceOf = FreeBalanceOf + FreezedBalanceOf
+    + với mỗi PriceItem trên sổ lệnh: buy_amount/sell_amount = tổng remained_buy_amount/remained_sell_amount của các order tại mức giá đó
+*/
+use crate::{mock::*, LinkedItemList, MarketOrderAmount, OrderOpt, OrderType, SelfTradePrevention, TimeInForce};
+use proptest::prelude::*;
+use sp_core::H256;
+use sp_runtime::traits::Dispatchable;
+
+#[derive(Clone, Debug)]
+enum Action {
+    Limit { who: AccountId, otype: OrderType, half_price: u128, quantity: u128 },
+    Market { who: AccountId, otype: OrderType, amount: MarketOrderAmount<Balance> },
+    Cancel { who: AccountId, index: u64 },
+    SetSelfTradePrevention { who: AccountId, mode: SelfTradePrevention },
+    NextBlock,
+}
+
+fn account() -> impl Strategy<Value = AccountId> {
+    prop_oneof![Just(ALICE), Just(BOB), Just(CHARLIE)]
+}
+
+fn order_type() -> impl Strategy<Value = OrderType> {
+    prop_oneof![Just(OrderType::Buy), Just(OrderType::Sell)]
+}
+
+fn stp_mode() -> impl Strategy<Value = SelfTradePrevention> {
+    prop_oneof![
+        Just(SelfTradePrevention::Allow),
+        Just(SelfTradePrevention::CancelNewest),
+        Just(SelfTradePrevention::CancelOldest),
+        Just(SelfTradePrevention::CancelBoth),
+        Just(SelfTradePrevention::DecrementAndCancel),
+    ]
+}
+
+fn action() -> impl Strategy<Value = Action> {
+    prop_oneof![
+        6 => (account(), order_type(), 1u128..=12, 1u128..=50)
+            .prop_map(|(who, otype, half_price, quantity)| Action::Limit { who, otype, half_price, quantity }),
+        2 => (account(), order_type(), 1u128..=200, any::<bool>())
+            .prop_map(|(who, otype, amount, quote)| Action::Market {
+                who,
+                otype,
+                amount: if quote { MarketOrderAmount::Quote(amount) } else { MarketOrderAmount::Base(amount) },
+            }),
+        2 => (account(), any::<u64>()).prop_map(|(who, index)| Action::Cancel { who, index }),
+        1 => (account(), stp_mode()).prop_map(|(who, mode)| Action::SetSelfTradePrevention { who, mode }),
+        1 => Just(Action::NextBlock),
+    ]
+}
+
+/* Giá theo nửa đơn vị (0.5, 1, 1.5, ...) và lượng BTC luôn chẵn => lượng đối ứng luôn chia hết ở giá đặt,
+    phần lẻ chỉ xuất hiện khi khớp ở giá của maker => đi qua các nhánh làm tròn của calculate_ex_amount
+    Lỗi của extrinsic được bỏ qua, dispatch qua RuntimeCall để storage được rollback khi lỗi giống như on-chain
+*/
+fn apply(action: Action, busd: H256, btc: H256) {
+    let call = match action {
+        Action::Limit { who, otype, half_price, quantity } => {
+            let price = half_price * price(1) / 2;
+            let sell_amount = match otype {
+                OrderType::Buy => quantity * half_price,
+                OrderType::Sell => quantity * 2,
+            };
+            let call = crate::Call::create_order {
+                base: busd,
+                quote: btc,
+                oopt: OrderOpt::Limit,
+                otype,
+                price,
+                sell_amount,
+                tif: TimeInForce::GoodTilCanceled,
+                post_only: false,
+                expires_at: None,
+                max_slippage: None,
+            };
+            (who, call)
+        },
+        Action::Market { who, otype, amount } => {
+            (who, crate::Call::create_market_order { base: busd, quote: btc, otype, amount, max_slippage: None })
+        },
+        Action::Cancel { who, index } => {
+            let total = TradeModule::owned_orders_index(who);
+            if total == 0 {
+                return
+            }
+            let order_hash = TradeModule::owned_orders(who, index % total).unwrap();
+            (who, crate::Call::cancel_limit_order { order_hash })
+        },
+        Action::SetSelfTradePrevention { who, mode } => (who, crate::Call::set_self_trade_prevention { mode }),
+        Action::NextBlock => {
+            run_to_block(System::block_number() + 1);
+            return
+        },
+    };
+
+    let (who, call) = call;
+    let _ = RuntimeCall::TradeModule(call).dispatch(RuntimeOrigin::signed(who));
+}
+
+fn assert_invariants(tp_hash: H256) {
+    // tổng số dư của mỗi token không đổi
+    for (token_hash, token) in pallet_tokens::Tokens::<Test>::iter() {
+        let total: Balance = pallet_tokens::BalanceOf::<Test>::iter()
+            .filter(|(_, hash, _)| *hash == token_hash)
+            .map(|(_, _, balance)| balance)
+            .sum();
+        assert_eq!(total, token.total_supply, "total balance of token {:?}", token_hash);
+    }
+
+    // balance = free + freezed, duyệt cả 3 storage để không bỏ sót account chỉ có free/freezed
+    let accounts = pallet_tokens::BalanceOf::<Test>::iter_keys()
+        .chain(pallet_tokens::FreeBalanceOf::<Test>::iter_keys())
+        .chain(pallet_tokens::FreezedBalanceOf::<Test>::iter_keys());
+    for (who, token_hash) in accounts {
+        let (balance, free, freezed) = (Tokens::balance_of(who, token_hash), Tokens::free_balance_of(who, token_hash), Tokens::freezed_balance_of(who, token_hash));
+        assert_eq!(balance, free + freezed, "balances of account {} token {:?}", who, token_hash);
+    }
+
+    // PriceItem giữ đúng tổng lượng còn lại của các order tại mức giá đó, 3 node cố định luôn trống
+    for (price, item) in LinkedItemList::<Test>::iter_prefix(tp_hash) {
+        let (sell_amount, buy_amount) = item.orders.iter().fold((0, 0), |(sell, buy), order_hash| {
+            let order = TradeModule::orders(order_hash).unwrap();
+            (sell + order.remained_sell_amount, buy + order.remained_buy_amount)
+        });
+        assert_eq!((item.sell_amount, item.buy_amount), (sell_amount, buy_amount), "price item at {:?}", price);
+
+        if price.is_none() || price == Some(Price::MIN) || price == Some(Price::MAX) {
+            assert!(item.orders.is_empty(), "sentinel item at {:?}", price);
+        }
+    }
+
+    // các check của try-runtime cũng phải pass sau mọi thao tác
+    assert_eq!(TradeModule::do_try_state(), Ok(()));
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(64))]
+
+    #[test]
+    fn random_order_flow_keeps_invariants(actions in prop::collection::vec(action(), 1..80)) {
+        new_test_ext().execute_with(|| {
+            let (busd, btc, tp_hash) = setup_trade_pair();
+            assert_invariants(tp_hash);
+
+            for action in actions {
+                apply(action, busd, btc);
+                assert_invariants(tp_hash);
+            }
+        });
+    }
+}
@@ -1,17 +1,35 @@
 #![cfg_attr(not(feature = "std"), no_std)]
 
 mod linked_price_list;
+pub mod migrations;
+mod rolling_window;
+pub mod runtime_api;
+pub mod weights;
 pub use pallet::*;
+pub use weights::WeightInfo;
+
+#[cfg(feature = "runtime-benchmarks")]
+mod benchmarking;
+
+#[cfg(test)]
+mod mock;
+
+#[cfg(test)]
+mod tests;
+
+#[cfg(test)]
+mod invariant_tests;
 
 #[frame_support::pallet]
 pub mod pallet {
     use frame_support::{
         pallet_prelude::*,
         sp_runtime::{
-            traits::{Hash, Bounded, AtLeast32Bit, Zero, CheckedSub},
-            ArithmeticError
+            traits::{Hash, Bounded, AtLeast32Bit, Zero, One, CheckedSub},
+            ArithmeticError, Permill
         },
-        traits::{Randomness},
+        traits::{Randomness, EnsureOrigin, Currency, ReservableCurrency},
+        storage::with_storage_layer,
         sp_std::{
             fmt::Debug,
             convert::{TryFrom, TryInto},
@@ -23,24 +41,54 @@ pub mod pallet {
     use frame_system::pallet_prelude::*;
     use sp_core::U256;
     use crate::linked_price_list::{PriceItem, PriceList};
+    use crate::rolling_window;
+    use crate::runtime_api::{Candle, OrderBook, OrderBookLevel};
+    use crate::weights::WeightInfo;
+
+    // version của storage, tăng lên khi thay đổi cấu trúc storage và cần migration
+    pub const STORAGE_VERSION: StorageVersion = StorageVersion::new(1);
 
     #[pallet::pallet]
 	#[pallet::generate_store(pub(super) trait Store)]
-	#[pallet::without_storage_info]
+	#[pallet::storage_version(STORAGE_VERSION)]
 	pub struct Pallet<T>(_);
 
 	#[pallet::config]
     pub trait Config: frame_system::Config + pallet_tokens::Config + Debug{
 		type RuntimeEvent: From<Event<Self>> + IsType<<Self as frame_system::Config>::RuntimeEvent>; 
-        type Price: Parameter + Default + Member + Bounded + AtLeast32Bit + Copy + From<u128> + Into<u128>;
+        type Price: Parameter + Default + Member + Bounded + AtLeast32Bit + Copy + From<u128> + Into<u128> + MaxEncodedLen;
         type TradeRandom: Randomness<Self::Hash, Self::BlockNumber>;
         type PriceFactor: Get<u128>;                // 100_000_000
         type BlocksPerDay: Get<u32>;                // 6 * 60 * 24
-        type OpenedOrdersArrayCap: Get<u8>;         // 20
-        type ClosedOrdersArrayCap: Get<u8>;         // 100
+        type OpenedOrdersArrayCap: Get<u32>;        // 100, số order đang mở tối đa của 1 account trong 1 cặp
+        type ClosedOrdersArrayCap: Get<u32>;        // 100, số order đã đóng gần nhất được giữ lại cho 1 account trong 1 cặp
+        type MaxOrdersPerPriceLevel: Get<u32>;      // 200, số order tối đa tại 1 mức giá trên sổ lệnh
+        type MakerFee: Get<Permill>;                // phí của maker, tính trên lượng token maker nhận về
+        type TakerFee: Get<Permill>;                // phí của taker, tính trên lượng token taker nhận về
+        type FeeCollector: Get<Self::AccountId>;    // account nhận phí giao dịch
+        type MaxExpiringOrdersPerBlock: Get<u32>;   // 100
+        type MaxStopOrdersPerBlock: Get<u32>;       // 50
+        type MaxCancelAllOrders: Get<u32>;          // 50
+        type MaxBatchOrders: Get<u32>;              // 20
+        type MaxDelistOrdersPerBlock: Get<u32>;     // 50
+        type AdminOrigin: EnsureOrigin<Self::RuntimeOrigin>;    // origin của governance, dùng cho các call đặc quyền
+        type DepositCurrency: ReservableCurrency<Self::AccountId>;  // native currency dùng để reserve tiền cọc khi tạo cặp
+        type TradePairDeposit: Get<DepositBalance<Self>>;           // tiền cọc khi tạo cặp, hoàn lại khi cặp bị delist
+        type OwnerOnlyTradePairCreation: Get<bool>;                 // true => chỉ owner của base/quote được tạo cặp (không cần cọc)
+        type MaxMarketOrderFills: Get<u32>;                         // 50, số maker order tối đa 1 order (market, limit, amend) được khớp
+        type MaxCandleIntervals: Get<u32>;                          // 4, số interval tối đa của CandleIntervals
+        type CandleIntervals: Get<BoundedVec<u32, Self::MaxCandleIntervals>>;   // các interval của nến tính bằng block, ví dụ 1m/1h/1d
+        type CandleRetention: Get<u32>;                             // số nến tối đa giữ lại cho mỗi cặp, mỗi interval
+        type PriceObservationPeriod: Get<u32>;                      // khoảng cách tối thiểu (block) giữa 2 lần lưu observation của TWAP
+        type MaxPriceObservations: Get<u32>;                        // số observation tối đa giữ lại cho mỗi cặp
+        type MaxStopOrdersPerPrice: Get<u32>;                       // số stop order tối đa chờ tại 1 trigger_price của mỗi cặp, mỗi phía
+        type MaxStopTriggerPrices: Get<u32>;                        // số trigger_price khác nhau tối đa của mỗi cặp, mỗi phía
+        type WeightInfo: WeightInfo;                                // weight của các extrinsic, sinh từ benchmarking.rs
 	}
 
     type Balance<T> = pallet_tokens::Balance<T>;
+    type CandleOf<T> = Candle<<T as frame_system::Config>::BlockNumber, <T as Config>::Price, Balance<T>>;
+    type DepositBalance<T> = <<T as Config>::DepositCurrency as Currency<<T as frame_system::Config>::AccountId>>::Balance;
 
      /* struct TradePair để quản lý các cặp trade_pair
      Ở trong program này thì cặp trade pair sẽ theo hướng stable_coin/coin_cần_mua
@@ -49,22 +97,38 @@ pub mod pallet {
         => Khi đặt lệnh BUY => là mình đang mua BTC
         => Khi đặt lệnh SELL => là mình đang bán BTC
     */ 
-    #[derive(Encode, Decode, Clone, PartialEq, Eq, TypeInfo, RuntimeDebug)]
+    #[derive(Encode, Decode, Clone, PartialEq, Eq, TypeInfo, RuntimeDebug, MaxEncodedLen)]
 	#[scale_info(skip_type_params(T))]
+	#[codec(mel_bound())]
     pub struct TradePair<T: Config> {
-        hash: T::Hash,                              // trade_pair_hash
-        base: T::Hash,                              // base_token_hash       
-        quote: T::Hash,                             // quote_token_hash
+        pub hash: T::Hash,                              // trade_pair_hash
+        pub base: T::Hash,                              // base_token_hash       
+        pub quote: T::Hash,                             // quote_token_hash
+
+        pub latest_matched_price: Option<T::Price>,     // giá khớp lệnh gần nhất
+
+        pub one_day_trade_volume: Balance<T>,           // tổng volume 24h
+        pub one_day_highest_price: Option<T::Price>,    // giá cao nhất 24h
+        pub one_day_lowest_price: Option<T::Price>,     // giá thấp nhất 24h
 
-        latest_matched_price: Option<T::Price>,     // giá khớp lệnh gần nhất
+        pub tick_size: T::Price,                        // bước giá, giá đặt phải chia hết cho tick_size
+        pub lot_size: Balance<T>,                       // bước khối lượng quote_token (coin cần mua), phải chia hết cho lot_size
+        pub min_notional: Balance<T>,                   // giá trị tối thiểu của 1 order, tính theo base_token
 
-        one_day_trade_volume: Balance<T>,           // tổng volume 24h
-        one_day_highest_price: Option<T::Price>,    // giá cao nhất 24h
-        one_day_lowest_price: Option<T::Price>,     // giá thấp nhất 24h
+        pub status: TradePairStatus,                    // trạng thái của cặp
+    }
+
+    // Trạng thái của 1 cặp trade_pair
+    #[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, TypeInfo, RuntimeDebug, MaxEncodedLen)]
+    pub enum TradePairStatus {
+        Active,         // giao dịch bình thường
+        CancelOnly,     // chỉ được hủy order, không đặt order mới và không khớp lệnh
+        Paused,         // dừng toàn bộ, kể cả hủy order (dùng khi có sự cố)
+        Delisted        // ngừng giao dịch vĩnh viễn, các order còn trên sổ bị hủy dần trong on_initialize
     }
 
     // Loại order gồm: Buy hoặc Sell
-    #[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, TypeInfo, RuntimeDebug)]
+    #[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, TypeInfo, RuntimeDebug, MaxEncodedLen)]
     pub enum OrderType {
         Buy,
         Sell
@@ -80,14 +144,22 @@ pub mod pallet {
         }
     }
 
-    #[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, TypeInfo, RuntimeDebug)]
+    #[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, TypeInfo, RuntimeDebug, MaxEncodedLen)]
     pub enum OrderOpt {
         Limit,
         Market
     }
 
+    // Hiệu lực của lệnh limit sau khi khớp lệnh xong mà vẫn còn dư
+    #[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, TypeInfo, RuntimeDebug, MaxEncodedLen)]
+    pub enum TimeInForce {
+        GoodTilCanceled,        // phần còn dư nằm trên sổ lệnh đến khi bị hủy
+        ImmediateOrCancel,      // khớp được bao nhiêu thì khớp, phần còn dư bị hủy và unfreeze
+        FillOrKill              // phải khớp hết ngay, nếu không thì cả lệnh thất bại
+    }
+
     // Theo dõi trạng thái của order
-    #[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, TypeInfo, RuntimeDebug)]
+    #[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, TypeInfo, RuntimeDebug, MaxEncodedLen)]
     pub enum OrderStatus {
         Created,                // Khởi tạo
         PartialFilled,          // Khớp 1 phần
@@ -96,8 +168,9 @@ pub mod pallet {
     }
 
     // struct Order để quản lý các lệnh Limit
-    #[derive(Encode, Decode, Clone, PartialEq, Eq, TypeInfo, RuntimeDebug)]
+    #[derive(Encode, Decode, Clone, PartialEq, Eq, TypeInfo, RuntimeDebug, MaxEncodedLen)]
 	#[scale_info(skip_type_params(T))]
+	#[codec(mel_bound())]
     pub struct Order<T: Config>{
         pub hash: T::Hash,                      // order_hash
         pub base: T::Hash,                      // base_token_hash
@@ -112,17 +185,19 @@ pub mod pallet {
 
         pub oopt: OrderOpt,                     // Limit / Market
         pub otype: OrderType,                   // Buy / Sell
+        pub tif: TimeInForce,                   // GTC / IOC / FOK
+        pub expires_at: Option<T::BlockNumber>, // block mà lệnh tự động bị hủy, None => không hết hạn
         pub status: OrderStatus                 // trạng thái lệnh
     }
     impl<T: Config> Order<T> {
-        fn new(base: T::Hash, quote: T::Hash, owner: T::AccountId, price: T::Price, sell_amount:Balance<T>, buy_amount: Balance<T>, oopt: OrderOpt, otype: OrderType) -> Self {
+        fn new(base: T::Hash, quote: T::Hash, owner: T::AccountId, price: T::Price, sell_amount:Balance<T>, buy_amount: Balance<T>, oopt: OrderOpt, otype: OrderType, tif: TimeInForce, expires_at: Option<T::BlockNumber>) -> Self {
             // create order_hash
             let nonce = <Nonce<T>>::get();
             let random = T::TradeRandom::random_seed().0;
-            let hash = (base, quote, owner.clone(), price, sell_amount, buy_amount, oopt, otype, 
+            let hash = (base, quote, owner.clone(), price, sell_amount, buy_amount, oopt, otype, tif, expires_at,
                                              random, nonce, frame_system::Pallet::<T>::block_number()).using_encoded(T::Hashing::hash);
             
-            Order { hash, base, quote, owner, price, sell_amount, buy_amount, remained_sell_amount: sell_amount, remained_buy_amount: buy_amount, oopt, otype, status: OrderStatus::Created }
+            Order { hash, base, quote, owner, price, sell_amount, buy_amount, remained_sell_amount: sell_amount, remained_buy_amount: buy_amount, oopt, otype, tif, expires_at, status: OrderStatus::Created }
         }
 
         // check trạng thái order đã finish => đã Filled hoặc Canceled
@@ -133,24 +208,27 @@ pub mod pallet {
     }
 
     // struct Trade để quản lý các giao dịch khớp lệnh
-    #[derive(Encode, Decode, Clone, PartialEq, Eq, TypeInfo, RuntimeDebug)]
+    #[derive(Encode, Decode, Clone, PartialEq, Eq, TypeInfo, RuntimeDebug, MaxEncodedLen)]
 	#[scale_info(skip_type_params(T))]
+	#[codec(mel_bound())]
     pub struct Trade<T: Config> {
-        hash: T::Hash,                  // trade_hash
-        base: T::Hash,                  // base_token_hash
-        quote: T::Hash,                 // quote_token_hash
-
-        buyer: T::AccountId,            // người bán => người sở hữu base_token
-        seller: T::AccountId,           // người mua => người sở hữu quote_token
-        maker: T::AccountId,            // lệnh được đặt trước
-        taker: T::AccountId,            // lệnh đặt sau mà khớp lệnh vói maker
-        otype: OrderType,               // taker order's type
-        price: T::Price,                // maker order's price
-        base_amount: Balance<T>,        // số lượng base_token giao dịch
-        quote_amount: Balance<T>        // số lượng quote_token giao dịch
+        pub hash: T::Hash,                  // trade_hash
+        pub base: T::Hash,                  // base_token_hash
+        pub quote: T::Hash,                 // quote_token_hash
+
+        pub buyer: T::AccountId,            // người bán => người sở hữu base_token
+        pub seller: T::AccountId,           // người mua => người sở hữu quote_token
+        pub maker: T::AccountId,            // lệnh được đặt trước
+        pub taker: T::AccountId,            // lệnh đặt sau mà khớp lệnh vói maker
+        pub otype: OrderType,               // taker order's type
+        pub price: T::Price,                // maker order's price
+        pub base_amount: Balance<T>,        // số lượng base_token giao dịch
+        pub quote_amount: Balance<T>,       // số lượng quote_token giao dịch
+        pub maker_fee: Balance<T>,          // phí maker trả, tính bằng token maker nhận về
+        pub taker_fee: Balance<T>           // phí taker trả, tính bằng token taker nhận về
     }
     impl<T: Config> Trade<T> {
-        fn new(base: T::Hash, quote: T::Hash, maker_order: &Order<T>, taker_order: &Order<T>, base_amount: Balance<T>, quote_amount: Balance<T>) -> Self {
+        fn new(base: T::Hash, quote: T::Hash, maker_order: &Order<T>, taker_order: &Order<T>, base_amount: Balance<T>, quote_amount: Balance<T>, maker_fee: Balance<T>, taker_fee: Balance<T>) -> Self {
             // create trade hash
             let nonce = <Nonce<T>>::get();
             let random = T::TradeRandom::random_seed().0;
@@ -172,11 +250,124 @@ pub mod pallet {
                 seller = taker_order.owner.clone();
             }
 
-            Trade { hash, base, quote, buyer, seller, maker: maker_order.owner.clone(), taker: taker_order.owner.clone(), otype: taker_order.otype, price: maker_order.price, base_amount, quote_amount }
+            Trade { hash, base, quote, buyer, seller, maker: maker_order.owner.clone(), taker: taker_order.owner.clone(), otype: taker_order.otype, price: maker_order.price, base_amount, quote_amount, maker_fee, taker_fee }
+        }
+    }
+
+    // Cách xử lý khi lệnh mới (taker) khớp với lệnh đang nằm trên sổ (maker) của cùng 1 account
+    #[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, TypeInfo, RuntimeDebug, MaxEncodedLen)]
+    pub enum SelfTradePrevention {
+        Allow,                  // cho phép khớp như bình thường (mặc định)
+        CancelNewest,           // hủy phần còn dư của taker
+        CancelOldest,           // hủy maker, taker tiếp tục khớp với các lệnh khác
+        CancelBoth,             // hủy cả taker và maker
+        DecrementAndCancel      // giảm cả 2 lệnh đi phần chồng nhau (không tạo trade), lệnh nào về 0 thì bị hủy
+    }
+
+    // 1 phần tử trong batch_orders: tạo lệnh limit hoặc hủy lệnh
+    #[derive(Encode, Decode, Clone, PartialEq, Eq, TypeInfo, RuntimeDebug)]
+	#[scale_info(skip_type_params(T))]
+    pub enum OrderSpec<T: Config> {
+        Create {
+            base: T::Hash,
+            quote: T::Hash,
+            otype: OrderType,
+            price: T::Price,
+            sell_amount: Balance<T>,
+            tif: TimeInForce,
+            post_only: bool,
+            expires_at: Option<T::BlockNumber>
+        },
+        Cancel {
+            order_hash: T::Hash
+        }
+    }
+
+    /* Lượng của market order, giả sử cặp BUSD/BTC
+        + Quote(n): mua/bán đúng n BTC, lượng BUSD tính theo các lần khớp thực tế
+        + Base(n): Buy => chi tối đa n BUSD, Sell => bán lượng BTC đủ để nhận về tối đa n BUSD
+    */
+    #[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, TypeInfo, RuntimeDebug, MaxEncodedLen)]
+    pub enum MarketOrderAmount<Balance> {
+        Quote(Balance),
+        Base(Balance)
+    }
+
+    // Cách xử lý lỗi trong batch_orders
+    #[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, TypeInfo, RuntimeDebug, MaxEncodedLen)]
+    pub enum BatchMode {
+        AllOrNothing,           // 1 phần tử lỗi => cả batch thất bại
+        BestEffort              // phần tử lỗi bị bỏ qua (rollback riêng phần tử đó), các phần tử khác vẫn thực hiện
+    }
+
+    // Số liệu của 1 lần khớp lệnh, dùng để tính weight thực tế của extrinsic đặt lệnh
+    #[derive(Clone, Copy, Default, PartialEq, Eq, RuntimeDebug)]
+    pub struct MatchStats {
+        pub levels: u32,        // số mức giá đã khớp
+        pub fills: u32          // số maker order đã khớp
+    }
+
+    // Trạng thái của stop order
+    #[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, TypeInfo, RuntimeDebug, MaxEncodedLen)]
+    pub enum StopOrderStatus {
+        Pending,                // Đang chờ giá chạm trigger_price
+        Triggered,              // Giá đã chạm, đang chờ kích hoạt trong hàng đợi
+        Activated,              // Đã chuyển thành order market/limit
+        Failed,                 // Kích hoạt thất bại (cặp không Active, không có thanh khoản, ...)
+        Canceled                // Hủy
+    }
+
+    /* struct StopOrder để quản lý các lệnh điều kiện (stop-loss / stop-limit)
+        Lệnh này nằm ngoài sổ lệnh cho đến khi latest_matched_price của cặp tp chạm trigger_price:
+        => Stop Buy  => kích hoạt khi giá khớp gần nhất >= trigger_price
+        => Stop Sell => kích hoạt khi giá khớp gần nhất <= trigger_price
+        Khi kích hoạt, oopt = Market => tạo lệnh market, oopt = Limit => tạo lệnh limit tại price
+        sell_amount luôn là lượng token bán (Buy => base_token, Sell => quote_token) và bị freeze từ lúc tạo đến khi kích hoạt/hủy
+    */
+    #[derive(Encode, Decode, Clone, PartialEq, Eq, TypeInfo, RuntimeDebug, MaxEncodedLen)]
+	#[scale_info(skip_type_params(T))]
+	#[codec(mel_bound())]
+    pub struct StopOrder<T: Config> {
+        pub hash: T::Hash,                      // stop_order_hash
+        pub base: T::Hash,                      // base_token_hash
+        pub quote: T::Hash,                     // quote_token_hash
+
+        pub owner: T::AccountId,                // người tạo lệnh
+        pub trigger_price: T::Price,            // giá kích hoạt
+        pub price: T::Price,                    // giá đặt khi kích hoạt (chỉ dùng cho Limit)
+        pub sell_amount: Balance<T>,            // lượng bán khi kích hoạt, bị freeze khi tạo lệnh
+
+        pub oopt: OrderOpt,                     // Limit => stop-limit / Market => stop-loss
+        pub otype: OrderType,                   // Buy / Sell
+        pub status: StopOrderStatus             // trạng thái lệnh
+    }
+    impl<T: Config> StopOrder<T> {
+        // check giá khớp gần nhất đã chạm trigger_price chưa
+        fn is_triggered_by(otype: OrderType, trigger_price: T::Price, last_price: T::Price) -> bool {
+            match otype {
+                OrderType::Buy => last_price >= trigger_price,
+                OrderType::Sell => last_price <= trigger_price
+            }
+        }
+
+        // token bị freeze: Buy => base_token, Sell => quote_token
+        fn sell_hash(&self) -> T::Hash {
+            match self.otype {
+                OrderType::Buy => self.base,
+                OrderType::Sell => self.quote,
+            }
+        }
+
+        // lượng của market order khi kích hoạt: Buy => chi tối đa sell_amount base_token, Sell => bán đúng sell_amount quote_token
+        fn market_amount(&self) -> MarketOrderAmount<Balance<T>> {
+            match self.otype {
+                OrderType::Buy => MarketOrderAmount::Base(self.sell_amount),
+                OrderType::Sell => MarketOrderAmount::Quote(self.sell_amount),
+            }
         }
     }
 
-    type OrderItem<T> = PriceItem<<T as frame_system::Config>::Hash, <T as Config>::Price, Balance<T>>;
+    type OrderItem<T> = PriceItem<<T as frame_system::Config>::Hash, <T as Config>::Price, Balance<T>, <T as Config>::MaxOrdersPerPriceLevel>;
     type OrderList<T> = PriceList<T, LinkedItemList<T>, <T as frame_system::Config>::Hash, <T as Config>::Price, Balance<T>>;
 
     #[pallet::storage]
@@ -269,13 +460,18 @@ pub mod pallet {
 
     #[pallet::storage]
     #[pallet::getter(fn owned_tp_opened_orders)]
-    // (account_id, tp_hash) => orders
-    pub type OwnedTPOpenedOrders<T: Config> = StorageMap<_, Blake2_128Concat, (T::AccountId, T::Hash), Vec<T::Hash>>;
+    // (account_id, tp_hash) => orders, mới trước cũ sau, tối đa OpenedOrdersArrayCap phần tử
+    pub type OwnedTPOpenedOrders<T: Config> = StorageMap<_, Blake2_128Concat, (T::AccountId, T::Hash), BoundedVec<T::Hash, T::OpenedOrdersArrayCap>>;
+
+    #[pallet::storage]
+    #[pallet::getter(fn owned_opened_trade_pair)]
+    // account_id => tp_hash => (), các cặp mà account còn order mở, dùng để duyệt OwnedTPOpenedOrders theo account
+    pub type OwnedOpenedTradePairs<T: Config> = StorageDoubleMap<_, Blake2_128Concat, T::AccountId, Blake2_128Concat, T::Hash, ()>;
 
     #[pallet::storage]
     #[pallet::getter(fn owned_tp_closed_orders)]
-    // (aacount_id, tp_hash) => orders
-    pub type OwnedTPClosedOrders<T: Config> = StorageMap<_, Blake2_128Concat, (T::AccountId, T::Hash), Vec<T::Hash>>;
+    // (aacount_id, tp_hash) => orders, mới trước cũ sau, đầy thì bỏ order cũ nhất
+    pub type OwnedTPClosedOrders<T: Config> = StorageMap<_, Blake2_128Concat, (T::AccountId, T::Hash), BoundedVec<T::Hash, T::ClosedOrdersArrayCap>>;
 
     #[pallet::storage]
     #[pallet::getter(fn trade_pair_owned_trades)]
@@ -287,15 +483,100 @@ pub mod pallet {
     // tp_hash => index
     pub type TradePairOwnedTradesIndex<T: Config> = StorageMap<_, Blake2_128Concat, T::Hash, u64, ValueQuery>;
 
+    #[pallet::storage]
+    #[pallet::getter(fn orders_by_expiry)]
+    // block_number => ds order_hash hết hạn tại block đó
+    pub type OrdersByExpiry<T: Config> = StorageMap<_, Blake2_128Concat, T::BlockNumber, BoundedVec<T::Hash, T::MaxExpiringOrdersPerBlock>, ValueQuery>;
+
+    #[pallet::storage]
+    #[pallet::getter(fn trade_pair_deposits)]
+    // tp_hash => (người tạo, tiền cọc đã reserve)
+    pub type TradePairDeposits<T: Config> = StorageMap<_, Blake2_128Concat, T::Hash, (T::AccountId, DepositBalance<T>)>;
+
+    #[pallet::storage]
+    #[pallet::getter(fn price_cumulative)]
+    // tp_hash => (tổng cộng dồn price * số block, block cập nhật gần nhất, giá khớp gần nhất), cộng dồn theo kiểu wrapping
+    pub type PriceCumulatives<T: Config> = StorageMap<_, Blake2_128Concat, T::Hash, (u128, T::BlockNumber, T::Price)>;
+
+    #[pallet::storage]
+    #[pallet::getter(fn price_observations)]
+    // tp_hash => ds (block, giá trị cộng dồn tại block đó), cũ trước mới sau, tối đa MaxPriceObservations phần tử
+    pub type PriceObservations<T: Config> = StorageMap<_, Blake2_128Concat, T::Hash, BoundedVec<(T::BlockNumber, u128), T::MaxPriceObservations>, ValueQuery>;
+
+    #[pallet::storage]
+    #[pallet::getter(fn candles_of)]
+    // (tp_hash, interval) => block bắt đầu của nến => nến
+    pub type Candles<T: Config> = StorageDoubleMap<_, Blake2_128Concat, (T::Hash, u32), Twox64Concat, T::BlockNumber, CandleOf<T>>;
+
+    #[pallet::storage]
+    #[pallet::getter(fn candle_starts)]
+    // (tp_hash, interval) => ds block bắt đầu của các nến còn giữ lại, cũ trước mới sau, tối đa CandleRetention phần tử
+    pub type CandleStarts<T: Config> = StorageMap<_, Blake2_128Concat, (T::Hash, u32), BoundedVec<T::BlockNumber, T::CandleRetention>, ValueQuery>;
+
+    #[pallet::storage]
+    #[pallet::getter(fn delisting_trade_pairs)]
+    // tp_hash => () với các cặp đã delist nhưng vẫn còn order trên sổ lệnh, on_initialize sẽ hủy dần theo MaxDelistOrdersPerBlock
+    pub type DelistingTradePairs<T: Config> = StorageMap<_, Blake2_128Concat, T::Hash, ()>;
+
+    #[pallet::storage]
+    #[pallet::getter(fn self_trade_prevention)]
+    // account_id => chế độ self-trade prevention, chưa set thì là Allow
+    pub type SelfTradePreventionOf<T: Config> = StorageMap<_, Blake2_128Concat, T::AccountId, SelfTradePrevention>;
+
+    #[pallet::storage]
+    #[pallet::getter(fn stop_orders)]
+    // stop_order_hash => StopOrder
+    pub type StopOrders<T: Config> = StorageMap<_, Blake2_128Concat, T::Hash, StopOrder<T>>;
+
+    #[pallet::storage]
+    #[pallet::getter(fn trade_pair_stop_orders)]
+    // ((tp_hash, otype), trigger_price) => ds stop_order_hash đang chờ tại trigger_price, tối đa MaxStopOrdersPerPrice phần tử
+    pub type TPStopOrders<T: Config> = StorageDoubleMap<_, Blake2_128Concat, (T::Hash, OrderType), Blake2_128Concat, T::Price, BoundedVec<T::Hash, T::MaxStopOrdersPerPrice>, ValueQuery>;
+
+    #[pallet::storage]
+    #[pallet::getter(fn trade_pair_stop_trigger_prices)]
+    // (tp_hash, otype) => ds trigger_price đang có stop order chờ, sắp xếp tăng dần, tối đa MaxStopTriggerPrices phần tử
+    pub type TPStopTriggerPrices<T: Config> = StorageMap<_, Blake2_128Concat, (T::Hash, OrderType), BoundedVec<T::Price, T::MaxStopTriggerPrices>, ValueQuery>;
+
+    #[pallet::storage]
+    #[pallet::getter(fn stop_check_pairs)]
+    // tp_hash => () với các cặp vừa có giao dịch và có stop order đang chờ => cần check trigger ở block tiếp theo
+    pub type StopCheckPairs<T: Config> = StorageMap<_, Blake2_128Concat, T::Hash, ()>;
+
+    #[pallet::storage]
+    #[pallet::getter(fn triggered_stop_orders)]
+    // hàng đợi các stop order đã chạm trigger, chờ kích hoạt, mỗi block trigger và kích hoạt tối đa MaxStopOrdersPerBlock phần tử
+    pub type TriggeredStopOrders<T: Config> = StorageValue<_, BoundedVec<T::Hash, T::MaxStopOrdersPerBlock>, ValueQuery>;
+
     #[pallet::storage]
     #[pallet::getter(fn trade_pair_trade_data_bucket)]
     // (tp_hash, blocknumber) => (sum_of_trade_volume, highest_price, lowest_price)
     pub type TPTradeDataBucket<T: Config> = StorageMap<_, Blake2_128Concat, (T::Hash, T::BlockNumber), (Balance<T>, Option<T::Price>, Option<T::Price>)>;
 
     #[pallet::storage]
-    #[pallet::getter(fn trade_pair_trade_price_bucket)]
-    // tp_hash => (Vec<highest_price>, Vec<lowest_price>)
-    pub type TPTradePriceBucket<T: Config> = StorageMap<_, Blake2_128Concat, T::Hash, (Vec<Option<T::Price>>, Vec<Option<T::Price>>)>;
+    #[pallet::getter(fn trade_pair_high_prices)]
+    // tp_hash => deque (block bắt đầu bucket, giá cao nhất của phần bucket còn trong 24h) giá giảm dần => phần tử đầu là giá cao nhất 24h
+    pub type TPHighPrices<T: Config> = StorageMap<_, Blake2_128Concat, T::Hash, BoundedVec<(T::BlockNumber, T::Price), rolling_window::MaxBuckets>, ValueQuery>;
+
+    #[pallet::storage]
+    #[pallet::getter(fn trade_pair_low_prices)]
+    // tp_hash => deque (block bắt đầu bucket, giá thấp nhất của phần bucket còn trong 24h) giá tăng dần => phần tử đầu là giá thấp nhất 24h
+    pub type TPLowPrices<T: Config> = StorageMap<_, Blake2_128Concat, T::Hash, BoundedVec<(T::BlockNumber, T::Price), rolling_window::MaxBuckets>, ValueQuery>;
+
+    #[pallet::storage]
+    #[pallet::getter(fn trade_pair_bucket_high_prices)]
+    // (tp_hash, block bắt đầu bucket) => deque (blocknumber, price) giá giảm dần của các block trong bucket còn trong 24h
+    pub type TPBucketHighPrices<T: Config> = StorageDoubleMap<_, Blake2_128Concat, T::Hash, Blake2_128Concat, T::BlockNumber, BoundedVec<(T::BlockNumber, T::Price), rolling_window::BucketLen<T::BlocksPerDay>>, ValueQuery>;
+
+    #[pallet::storage]
+    #[pallet::getter(fn trade_pair_bucket_low_prices)]
+    // (tp_hash, block bắt đầu bucket) => deque (blocknumber, price) giá tăng dần của các block trong bucket còn trong 24h
+    pub type TPBucketLowPrices<T: Config> = StorageDoubleMap<_, Blake2_128Concat, T::Hash, Blake2_128Concat, T::BlockNumber, BoundedVec<(T::BlockNumber, T::Price), rolling_window::BucketLen<T::BlocksPerDay>>, ValueQuery>;
+
+    #[pallet::storage]
+    #[pallet::getter(fn one_day_stats_expiry)]
+    // (blocknumber, tp_hash) => () với các cặp có trade ở (blocknumber - BlocksPerDay), cần trừ ra khỏi thống kê 24h tại blocknumber
+    pub type OneDayStatsExpiry<T: Config> = StorageDoubleMap<_, Blake2_128Concat, T::BlockNumber, Blake2_128Concat, T::Hash, ()>;
 
     trait AddTrade{
         type A;
@@ -304,7 +585,7 @@ pub mod pallet {
         fn add_trade(_param1: Self::A, _param2: Self::B, _param3: Self::C) -> DispatchResult;
     }    
     trait AddOrder<T: Config> {
-        fn add_order(_param1: T::AccountId, _param2: T::Hash, _param3: T::Hash);
+        fn add_order(_param1: T::AccountId, _param2: T::Hash, _param3: T::Hash) -> DispatchResult;
     } 
     trait RemoveOrder<T: Config> {
         fn remove_order(_param1: T::AccountId, _param2: T::Hash, _param3: T::Hash);
@@ -362,67 +643,50 @@ pub mod pallet {
     
     // Khi có một order được khởi tạo thì add nó vào opened_order
     impl<T: Config> AddOrder<T> for OwnedTPOpenedOrders<T> {
-        fn add_order(account_id: T::AccountId, tp_hash: T::Hash, order_hash: T::Hash){
-    
-            let mut orders;
+        fn add_order(account_id: T::AccountId, tp_hash: T::Hash, order_hash: T::Hash) -> DispatchResult {
             // lấy ds orders ra nếu chưa có thì khởi tạo
-            if let Some(ts) = Self::get((account_id.clone(), tp_hash)) {
-                orders = ts;
-            } else {
-                orders = Vec::<T::Hash>::new();
-            }
-    
-            // check order này đã tồn tại chưa, có thì bỏ qua còn chưa thì add vào ds
-            match orders.iter().position(|&x| x == order_hash) {
-                Some(_) => return,
-                None => { // chèn vào đầu vec, nếu len của ds = opened_cap thì mình xóa phần tử cuối
-                    orders.insert(0, order_hash);
-                    if orders.len() == T::OpenedOrdersArrayCap::get() as usize {
-                        orders.pop();
-                    }
-                    // update lại ds orders
-                    <OwnedTPOpenedOrders<T>>::insert((account_id, tp_hash), orders);
-                }
+            let mut orders = Self::get((account_id.clone(), tp_hash)).unwrap_or_default();
+
+            // check order này đã tồn tại chưa, có thì bỏ qua còn chưa thì chèn vào đầu ds
+            if orders.contains(&order_hash) {
+                return Ok(())
             }
+            // ds đã đủ OpenedOrdersArrayCap order đang mở => không được đặt thêm order trong cặp này
+            orders.try_insert(0, order_hash).map_err(|_| <Error<T>>::TooManyOpenOrders)?;
+
+            // update lại ds orders
+            <OwnedTPOpenedOrders<T>>::insert((account_id.clone(), tp_hash), orders);
+            <OwnedOpenedTradePairs<T>>::insert(account_id, tp_hash, ());
+            Ok(())
         }
     }
     
     // Khi một order được hoàn tất hoặc cancel thì mình xóa khỏi opened_order và thêm vào closed_order
     impl<T: Config> RemoveOrder<T> for OwnedTPOpenedOrders<T> {
         fn remove_order(account_id: T::AccountId, tp_hash: T::Hash, order_hash: T::Hash) {
-    
-            let mut orders;
-            if let Some(ts) = Self::get((account_id.clone(), tp_hash)) {
-                orders = ts;
-            } else {
-                orders = Vec::<T::Hash>::new();
-            }
-    
+            let mut orders = Self::get((account_id.clone(), tp_hash)).unwrap_or_default();
             orders.retain(|&x| x != order_hash);
+            if orders.is_empty() {
+                <OwnedOpenedTradePairs<T>>::remove(account_id.clone(), tp_hash);
+            }
             <OwnedTPOpenedOrders<T>>::insert((account_id, tp_hash), orders);
         }
     }
     impl<T: Config> AddOrder<T> for OwnedTPClosedOrders<T> {
-        fn add_order(account_id: T::AccountId, tp_hash: T::Hash, order_hash: T::Hash){
-    
-            let mut orders;
-            if let Some(ts) = Self::get((account_id.clone(), tp_hash)) {
-                orders = ts;
-            } else {
-                orders = Vec::<T::Hash>::new();
+        fn add_order(account_id: T::AccountId, tp_hash: T::Hash, order_hash: T::Hash) -> DispatchResult {
+            let mut orders = Self::get((account_id.clone(), tp_hash)).unwrap_or_default();
+
+            if orders.contains(&order_hash) {
+                return Ok(())
             }
-    
-            match orders.iter().position(|&x| x == order_hash) {
-                Some(_) => return,
-                None => {
-                    orders.insert(0, order_hash);
-                    if orders.len() == T::ClosedOrdersArrayCap::get() as usize {
-                        orders.pop();
-                    }
-    
-                    <OwnedTPClosedOrders<T>>::insert((account_id, tp_hash), orders);
-                }
+            // ds closed chỉ là lịch sử => đầy thì bỏ order cũ nhất ở cuối ds rồi chèn order mới vào đầu
+            if orders.len() >= T::ClosedOrdersArrayCap::get() as usize && !orders.is_empty() {
+                orders.remove(orders.len() - 1);
+            }
+            if orders.try_insert(0, order_hash).is_ok() {
+                <OwnedTPClosedOrders<T>>::insert((account_id, tp_hash), orders);
             }
+            Ok(())
         }
     }
 
@@ -435,6 +699,28 @@ pub mod pallet {
             hash: T::Hash,                  
             trade_pair: TradePair<T>            
         },
+        // emit khi rule của tradepair được cập nhật => (tp_hash, tick_size, lot_size, min_notional)
+        TradePairRulesUpdated {
+            hash: T::Hash,
+            tick_size: T::Price,
+            lot_size: Balance<T>,
+            min_notional: Balance<T>
+        },
+        // emit khi tiền cọc của tradepair được hoàn lại => (người tạo, tp_hash, tiền cọc)
+        TradePairDepositRefunded {
+            owner: T::AccountId,
+            hash: T::Hash,
+            deposit: DepositBalance<T>
+        },
+        // emit khi trạng thái của tradepair thay đổi => (tp_hash, trạng thái mới)
+        TradePairStatusChanged {
+            hash: T::Hash,
+            status: TradePairStatus
+        },
+        // emit khi cặp đã delist được hủy hết order trên sổ lệnh => (tp_hash)
+        TradePairDelistSwept {
+            hash: T::Hash
+        },
         // emit khi order được tạo => (người tạo, base_token, quote_token, order_hash, limit_order)
         OrderCreated {
             owner: T::AccountId,
@@ -443,19 +729,102 @@ pub mod pallet {
             order_hash: T::Hash,
             limit_order: Order<T>
         },
-        // emit khi trade được tạo => (người tạo, base_token, quote_token, trade_hash, trade)
+        // emit khi trade được tạo => (người tạo, base_token, quote_token, trade_hash, trade, phí maker, phí taker)
         TradeCreated {
             owner: T::AccountId,
             base_token: T::Hash,
             quote_token: T::Hash,
             trade_hash: T::Hash,
-            trade: Trade<T>
+            trade: Trade<T>,
+            maker_fee: Balance<T>,
+            taker_fee: Balance<T>
+        },
+        // emit khi market order khớp xong => (người tạo, base_token, quote_token, order_hash, loại lệnh, tổng base, tổng quote, giá trung bình, lượng chưa khớp)
+        MarketOrderExecuted {
+            owner: T::AccountId,
+            base_token: T::Hash,
+            quote_token: T::Hash,
+            order_hash: T::Hash,
+            otype: OrderType,
+            base_amount: Balance<T>,
+            quote_amount: Balance<T>,
+            avg_price: Option<T::Price>,
+            unfilled_amount: Balance<T>
         },
         // emit khi order cancel => (người cancel, order_hash)
         OrderCanceled {
             owner: T::AccountId,
             order_hash: T::Hash
         },
+        // emit khi admin hủy order => (người tạo order, order_hash)
+        OrderForceCanceled {
+            owner: T::AccountId,
+            order_hash: T::Hash
+        },
+        // emit khi cancel_all_orders xong => (người hủy, tp_hash nếu có, số order đã hủy)
+        AllOrdersCanceled {
+            owner: T::AccountId,
+            tp_hash: Option<T::Hash>,
+            count: u32
+        },
+        // emit khi batch_orders xong => (người gọi, kết quả từng phần tử theo thứ tự)
+        BatchOrdersExecuted {
+            owner: T::AccountId,
+            results: Vec<DispatchResult>
+        },
+        // emit khi account đổi chế độ self-trade prevention => (account, chế độ mới)
+        SelfTradePreventionSet {
+            owner: T::AccountId,
+            mode: SelfTradePrevention
+        },
+        // emit khi taker khớp với maker của cùng account và bị chặn => (account, taker, maker, chế độ, ds order bị hủy)
+        SelfTradePrevented {
+            owner: T::AccountId,
+            taker_order_hash: T::Hash,
+            maker_order_hash: T::Hash,
+            mode: SelfTradePrevention,
+            canceled: Vec<T::Hash>
+        },
+        // emit khi order được sửa giá/số lượng => (người sửa, order_hash, giá mới, lượng bán còn lại mới)
+        OrderAmended {
+            owner: T::AccountId,
+            order_hash: T::Hash,
+            price: T::Price,
+            amount: Balance<T>
+        },
+        // emit khi order hết hạn và bị hủy tự động trong hook => (người tạo, order_hash)
+        OrderExpired {
+            owner: T::AccountId,
+            order_hash: T::Hash
+        },
+        // emit khi stop order được tạo => (người tạo, stop_order_hash, stop_order)
+        StopOrderCreated {
+            owner: T::AccountId,
+            stop_order_hash: T::Hash,
+            stop_order: StopOrder<T>
+        },
+        // emit khi giá khớp gần nhất chạm trigger_price => (người tạo, stop_order_hash, giá khớp gần nhất)
+        StopOrderTriggered {
+            owner: T::AccountId,
+            stop_order_hash: T::Hash,
+            last_price: T::Price
+        },
+        // emit khi stop order được chuyển thành order market/limit => (người tạo, stop_order_hash)
+        StopOrderActivated {
+            owner: T::AccountId,
+            stop_order_hash: T::Hash
+        },
+        // emit khi kích hoạt stop order thất bại => (người tạo, stop_order_hash, lỗi)
+        StopOrderActivationFailed {
+            owner: T::AccountId,
+            stop_order_hash: T::Hash,
+            error: DispatchError
+        },
+        // emit khi stop order bị hủy => (người hủy, stop_order_hash)
+        StopOrderCanceled {
+            owner: T::AccountId,
+            stop_order_hash: T::Hash
+        },
     }
 
 	#[pallet::error]
@@ -498,26 +867,94 @@ pub mod pallet {
         CanOnlyCancelOwnOrder,
         /// can only cancel not finished order
         CanOnlyCancelNotFinishedOrder, 
+        /// Fill-or-kill order could not be filled completely
+        OrderNotFullyFilled,
+        /// Post-only order would take liquidity from the book
+        PostOnlyWouldCross,
+        /// Post-only is only allowed for good-til-canceled limit orders
+        InvalidPostOnlyOrder,
+        /// Expiry block must be in the future
+        InvalidExpiry,
+        /// Too many orders already expire at the given block
+        TooManyExpiringOrders,
+        /// Can only amend own order
+        CanOnlyAmendOwnOrder,
+        /// Can only amend not finished order
+        CanOnlyAmendNotFinishedOrder,
+        /// Reducing the order at the same price would raise its remaining buy amount
+        AmendIncreasesBuyAmount,
+        /// No matching stop order
+        NoMatchingStopOrder,
+        /// Can only cancel own stop order
+        CanOnlyCancelOwnStopOrder,
+        /// Stop order was already activated, failed or canceled
+        StopOrderNotCancelable,
+        /// Price is not a multiple of the trade pair tick size
+        PriceNotMultipleOfTickSize,
+        /// Amount is not a multiple of the trade pair lot size
+        AmountNotMultipleOfLotSize,
+        /// Order value is below the trade pair minimum notional
+        BelowMinNotional,
+        /// Tick size and lot size must be greater than zero
+        InvalidTradePairRules,
+        /// Trade pair does not accept new orders or matching
+        TradePairNotActive,
+        /// Trade pair is paused, orders can not be canceled
+        TradePairPaused,
+        /// Trade pair was delisted and its status can not be changed
+        TradePairDelisted,
+        /// No resting orders within the acceptable price for a market order
+        NoMarketLiquidity,
+        /// Price level already holds MaxOrdersPerPriceLevel orders
+        PriceLevelFull,
+        /// Account already has OpenedOrdersArrayCap open orders in the trade pair
+        TooManyOpenOrders,
+        /// Trigger price already holds MaxStopOrdersPerPrice stop orders
+        TooManyStopOrders,
+        /// Trade pair side already has MaxStopTriggerPrices different trigger prices
+        TooManyStopTriggerPrices,
         // can't get TPTradeDataBucket
         DataBucketOfTradePairNotExist,
     }
 
     #[pallet::call]
     impl<T: Config> Pallet<T> {
-        #[pallet::weight(1_000_000)]
+        #[pallet::weight(T::WeightInfo::create_trade_pair())]
         pub fn create_trade_pair(origin: OriginFor<T>, base: T::Hash, quote: T::Hash) -> DispatchResult {
             let sender = ensure_signed(origin)?;
             Self::do_create_trade_pair(sender, base, quote)
         }
 
-        #[pallet::weight(1_000_000)]
-        pub fn create_order(origin: OriginFor<T>, base: T::Hash, quote: T::Hash, oopt: OrderOpt, otype: OrderType, price: T::Price, sell_amount: Balance<T>) -> DispatchResult {
+        #[pallet::weight(T::WeightInfo::update_trade_pair_rules())]
+        pub fn update_trade_pair_rules(origin: OriginFor<T>, tp_hash: T::Hash, tick_size: T::Price, lot_size: Balance<T>, min_notional: Balance<T>) -> DispatchResult {
+            T::AdminOrigin::ensure_origin(origin)?;
+            Self::do_update_trade_pair_rules(tp_hash, tick_size, lot_size, min_notional)
+        }
+
+        #[pallet::weight(T::WeightInfo::set_trade_pair_status())]
+        pub fn set_trade_pair_status(origin: OriginFor<T>, tp_hash: T::Hash, status: TradePairStatus) -> DispatchResult {
+            T::AdminOrigin::ensure_origin(origin)?;
+            Self::do_set_trade_pair_status(tp_hash, status)
+        }
+
+        // charge trước theo số lần khớp tối đa, hoàn lại phần thừa theo số mức giá và số order thực tế đã khớp
+        #[pallet::weight(Pallet::<T>::max_create_order_weight())]
+        pub fn create_order(origin: OriginFor<T>, base: T::Hash, quote: T::Hash, oopt: OrderOpt, otype: OrderType, price: T::Price, sell_amount: Balance<T>, tif: TimeInForce, post_only: bool, expires_at: Option<T::BlockNumber>, max_slippage: Option<Permill>) -> DispatchResultWithPostInfo {
             let sender = ensure_signed(origin)?;
-            if oopt == OrderOpt::Limit {
-                Self::do_create_limit_order(sender, base, quote, otype, price, sell_amount)
+            ensure!(!post_only || oopt == OrderOpt::Limit, <Error<T>>::InvalidPostOnlyOrder);
+            let stats = if oopt == OrderOpt::Limit {
+                Self::do_create_limit_order(sender, base, quote, otype, price, sell_amount, tif, post_only, expires_at)?
             } else {
-                Self::do_create_market_order(sender, base, quote, otype, sell_amount)
-            }
+                Self::do_create_market_order(sender, base, quote, otype, MarketOrderAmount::Quote(sell_amount), max_slippage)?
+            };
+            Ok(Some(Self::create_order_weight(stats)).into())
+        }
+
+        #[pallet::weight(Pallet::<T>::max_create_order_weight())]
+        pub fn create_market_order(origin: OriginFor<T>, base: T::Hash, quote: T::Hash, otype: OrderType, amount: MarketOrderAmount<Balance<T>>, max_slippage: Option<Permill>) -> DispatchResultWithPostInfo {
+            let sender = ensure_signed(origin)?;
+            let stats = Self::do_create_market_order(sender, base, quote, otype, amount, max_slippage)?;
+            Ok(Some(Self::create_order_weight(stats)).into())
         }
 
         // #[pallet::weight(100_000_000)]
@@ -531,99 +968,83 @@ pub mod pallet {
         // ) -> DispatchResult {
         //     let sender = ensure_signed(origin)?;
         //     let price = Self::price_as_vec_u8_to_x_by_100m(price)?;
-        //     Self::do_create_limit_order(sender, base, quote, otype, price, sell_amount)
+        //     Self::do_create_limit_order(sender, base, quote, otype, price, sell_amount, TimeInForce::GoodTilCanceled, false, None)
         // }
 
-        #[pallet::weight(1_000_000)]
+        #[pallet::weight(T::WeightInfo::cancel_limit_order())]
         pub fn cancel_limit_order(origin: OriginFor<T>, order_hash: T::Hash) -> DispatchResult {
             let sender = ensure_signed(origin)?;
             Self::do_cancel_limit_order(sender, order_hash)
         }
-    }
-
-    // 2 fn trong hook chủ yếu là cập nhật thông tin tổng volume, giá cao nhất, giá thấp nhất => không ảnh hưởng đến hoạt động trade
-    #[pallet::hooks]
-    impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
-        // logic chạy trước khi executing transaction 
-        fn on_initialize(block_number: T::BlockNumber) -> Weight{
-            // get BlocksPerDay & convert to type BlockNumber
-            let days: T::BlockNumber = <<T as frame_system::Config>::BlockNumber as From<_>>::from(T::BlocksPerDay::get());
-            let total_weight: Weight = Zero::zero();
-            // nếu số block chưa quá 1 ngày thì return
-            if block_number <= days {
-                return total_weight.add(1000)
-            }
-
-            // lặp qua toàn bộ các cặp trade_pair và update lại sum_vol
-            for i in 0..<TradePairsIndex<T>>::get() {
-                let tp_hash = <TradePairsHashByIndex<T>>::get(i).unwrap();
-                let mut tp = <TradePairs<T>>::get(tp_hash).unwrap();
-                let (sum_volume, _, _) = <TPTradeDataBucket<T>>::get((tp_hash, block_number - days)).unwrap_or((Default::default(), None, None)); // sum_of_trade_volume
-                tp.one_day_trade_volume -= sum_volume;
-                <TradePairs<T>>::insert(tp_hash, tp);
 
-                // update lại ds_high_price và ds_low_price
-                let (mut high_pri_vec, mut low_pri_vec) = <TPTradePriceBucket<T>>::get(tp_hash).unwrap_or((Vec::<Option<T::Price>>::new(), Vec::<Option<T::Price>>::new()));
-                if high_pri_vec.len() > 0 {
-                    high_pri_vec.remove(0);
-                }
-                if low_pri_vec.len() > 0 {
-                    low_pri_vec.remove(0);
-                }
-                <TPTradePriceBucket<T>>::insert(tp_hash, (high_pri_vec, low_pri_vec));
-            }
-
-            return total_weight.add(500_000)
+        #[pallet::weight(T::WeightInfo::cancel_limit_order())]
+        pub fn force_cancel_order(origin: OriginFor<T>, order_hash: T::Hash) -> DispatchResult {
+            T::AdminOrigin::ensure_origin(origin)?;
+            Self::do_force_cancel_order(order_hash)
         }
 
-        // logic chạy sau khi transaction execute khi chuẩn bị finalize block
-        fn on_finalize(block_number: T::BlockNumber) {
-            // lặp qua hết các cặp trade_pair
-            for i in 0..<TradePairsIndex<T>>::get() {
-                let tp_hash = <TradePairsHashByIndex<T>>::get(i).unwrap();
-                let mut tp = <TradePairs<T>>::get(tp_hash).unwrap();
+        #[pallet::weight(T::WeightInfo::cancel_limit_order().saturating_mul(T::MaxCancelAllOrders::get() as u64))]
+        pub fn cancel_all_orders(origin: OriginFor<T>, tp_hash: Option<T::Hash>) -> DispatchResultWithPostInfo {
+            let sender = ensure_signed(origin)?;
+            let canceled = Self::do_cancel_all_orders(sender, tp_hash)?;
+            Ok(Some(T::WeightInfo::cancel_limit_order().saturating_mul(canceled as u64)).into())
+        }
 
-                // Lấy sum_volume, giá cao nhất, giá thấp nhất, list giá cao nhất, list giá thấp nhất => cập nhật lại toàn bộ dữ liệu
-                let (sum_volume, highest_price, lowest_pricce) = <TPTradeDataBucket<T>>::get((tp_hash, block_number)).unwrap_or((Default::default(), None, None));
-                let (mut high_pri_vec, mut low_pri_vec) = <TPTradePriceBucket<T>>::get(tp_hash).unwrap_or((Vec::<Option<T::Price>>::new(), Vec::<Option<T::Price>>::new()));
-                high_pri_vec.push(highest_price);
-                low_pri_vec.push(lowest_pricce);
+        #[pallet::weight(orders.iter().fold(Weight::zero(), |weight, spec| weight.saturating_add(Pallet::<T>::max_order_spec_weight(spec))))]
+        pub fn batch_orders(origin: OriginFor<T>, orders: BoundedVec<OrderSpec<T>, T::MaxBatchOrders>, mode: BatchMode) -> DispatchResultWithPostInfo {
+            let sender = ensure_signed(origin)?;
+            let weight = Self::do_batch_orders(sender, orders.into_inner(), mode)?;
+            Ok(Some(weight).into())
+        }
 
-                let mut h_price = T::Price::min_value();
-                for price in high_pri_vec.clone() {
-                    if let Some(_price) = price {
-                        if _price > h_price{
-                            h_price = _price;
-                        }
-                    }
-                }
+        #[pallet::weight(T::WeightInfo::set_self_trade_prevention())]
+        pub fn set_self_trade_prevention(origin: OriginFor<T>, mode: SelfTradePrevention) -> DispatchResult {
+            let sender = ensure_signed(origin)?;
+            <SelfTradePreventionOf<T>>::insert(sender.clone(), mode);
+            Self::deposit_event(Event::SelfTradePreventionSet { owner: sender, mode });
+            Ok(())
+        }
 
-                let mut l_price = T::Price::max_value();
-                for price in low_pri_vec.clone() {
-                    if let Some(_price) = price {
-                        if _price < l_price {
-                            l_price = _price;
-                        }
-                    }
-                }
+        // đổi giá hoặc tăng lượng thì order được khớp lại như 1 order mới => weight giống create_order
+        #[pallet::weight(T::WeightInfo::cancel_limit_order().saturating_add(Pallet::<T>::max_create_order_weight()))]
+        pub fn amend_order(origin: OriginFor<T>, order_hash: T::Hash, new_price: T::Price, new_amount: Balance<T>) -> DispatchResultWithPostInfo {
+            let sender = ensure_signed(origin)?;
+            let stats = Self::do_amend_order(sender, order_hash, new_price, new_amount)?;
+            Ok(Some(T::WeightInfo::cancel_limit_order().saturating_add(Self::create_order_weight(stats))).into())
+        }
 
-                tp.one_day_trade_volume += sum_volume;
+        #[pallet::weight(T::WeightInfo::create_stop_order())]
+        pub fn create_stop_order(origin: OriginFor<T>, base: T::Hash, quote: T::Hash, oopt: OrderOpt, otype: OrderType, trigger_price: T::Price, price: T::Price, sell_amount: Balance<T>) -> DispatchResult {
+            let sender = ensure_signed(origin)?;
+            Self::do_create_stop_order(sender, base, quote, oopt, otype, trigger_price, price, sell_amount)
+        }
 
-                if h_price != T::Price::min_value() {
-                    tp.one_day_highest_price = Some(h_price);
-                } else {
-                    tp.one_day_highest_price = None;
-                }
+        #[pallet::weight(T::WeightInfo::cancel_stop_order())]
+        pub fn cancel_stop_order(origin: OriginFor<T>, stop_order_hash: T::Hash) -> DispatchResult {
+            let sender = ensure_signed(origin)?;
+            Self::do_cancel_stop_order(sender, stop_order_hash)
+        }
+    }
 
-                if l_price != T::Price::max_value() {
-                    tp.one_day_lowest_price = Some(l_price);
-                } else {
-                    tp.one_day_lowest_price = None;
-                }
+    // hook chạy đầu mỗi block: xử lý order hết hạn, stop order, cặp đã delist và cập nhật thống kê 24h
+    #[pallet::hooks]
+    impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
+        // logic chạy trước khi executing transaction 
+        fn on_initialize(block_number: T::BlockNumber) -> Weight{
+            // hủy các order hết hạn tại block này, sau đó check trigger và kích hoạt các stop order
+            // tiếp theo là hủy dần order của các cặp đã delist
+            let total_weight: Weight = Self::expire_orders(block_number)
+                .saturating_add(Self::process_stop_orders())
+                .saturating_add(Self::sweep_delisted_trade_pairs());
+
+            // trừ các block vừa ra khỏi cửa sổ 24h, chỉ với các cặp có trade ở block đó
+            total_weight.saturating_add(Self::expire_one_day_stats(block_number))
+        }
 
-                <TPTradePriceBucket<T>>::insert(tp_hash, (high_pri_vec, low_pri_vec));
-                <TradePairs<T>>::insert(tp_hash, tp);
-            }
+        // check bất biến của sổ lệnh, chạy bằng try-runtime trên snapshot của chain
+        #[cfg(feature = "try-runtime")]
+        fn try_state(_n: T::BlockNumber) -> Result<(), &'static str> {
+            Self::do_try_state()
         }
     }
 
@@ -637,10 +1058,13 @@ pub mod pallet {
             let quote_owner = pallet_tokens::Pallet::<T>::owners(quote);
             ensure!(base_owner.is_some() && quote_owner.is_some(), <Error<T>>::TokenOwnerNotFound);
 
-            // check người tạo cặp giao dịch phải là owner 1 trong 2 token
-            let base_owner = base_owner.unwrap();
-            let quote_owner = quote_owner.unwrap();
-            ensure!(sender == base_owner || sender == quote_owner, <Error<T>>::SenderNotEqualToBaseOrQuoteOwner);
+            // chế độ owner-only => người tạo cặp giao dịch phải là owner 1 trong 2 token
+            let owner_only = T::OwnerOnlyTradePairCreation::get();
+            if owner_only {
+                let base_owner = base_owner.unwrap();
+                let quote_owner = quote_owner.unwrap();
+                ensure!(sender == base_owner || sender == quote_owner, <Error<T>>::SenderNotEqualToBaseOrQuoteOwner);
+            }
 
             // check cặp giao dịch này vẫn chưa được tạo
             let bq = Self::trade_pair_hash_by_base_quote((base, quote));
@@ -657,9 +1081,20 @@ pub mod pallet {
                 latest_matched_price: None, 
                 one_day_trade_volume: Default::default(), 
                 one_day_highest_price: None, 
-                one_day_lowest_price: None 
+                one_day_lowest_price: None,
+                tick_size: One::one(),
+                lot_size: One::one(),
+                min_notional: Zero::zero(),
+                status: TradePairStatus::Active
             };
 
+            // ai cũng tạo được cặp => reserve tiền cọc, hoàn lại khi cặp bị delist
+            if !owner_only {
+                let deposit = T::TradePairDeposit::get();
+                T::DepositCurrency::reserve(&sender, deposit)?;
+                <TradePairDeposits<T>>::insert(hash, (sender.clone(), deposit));
+            }
+
             // update_ nonce
             let new_nonce = nonce.checked_add(1).ok_or(ArithmeticError::Overflow)?;
             <Nonce<T>>::put(new_nonce);
@@ -683,11 +1118,76 @@ pub mod pallet {
             Ok(())
         }
 
-        fn do_create_limit_order(sender: T::AccountId, base: T::Hash, quote: T::Hash, otype: OrderType, price: T::Price, sell_amount: Balance<T>) -> DispatchResult {
+        fn do_update_trade_pair_rules(tp_hash: T::Hash, tick_size: T::Price, lot_size: Balance<T>, min_notional: Balance<T>) -> DispatchResult {
+            ensure!(tick_size > Zero::zero() && lot_size > Zero::zero(), <Error<T>>::InvalidTradePairRules);
+
+            let mut tp = Self::trade_pairs(tp_hash).ok_or(<Error<T>>::NoMatchingTradePair)?;
+            tp.tick_size = tick_size;
+            tp.lot_size = lot_size;
+            tp.min_notional = min_notional;
+            <TradePairs<T>>::insert(tp_hash, tp);
+
+            Self::deposit_event(Event::TradePairRulesUpdated {
+                hash: tp_hash,
+                tick_size,
+                lot_size,
+                min_notional
+            });
+
+            Ok(())
+        }
+
+        fn do_set_trade_pair_status(tp_hash: T::Hash, status: TradePairStatus) -> DispatchResult {
+            let mut tp = Self::trade_pairs(tp_hash).ok_or(<Error<T>>::NoMatchingTradePair)?;
+            ensure!(tp.status != TradePairStatus::Delisted, <Error<T>>::TradePairDelisted);
+
+            tp.status = status;
+            <TradePairs<T>>::insert(tp_hash, tp);
+
+            // delist => đưa vào hàng đợi để on_initialize hủy dần các order còn trên sổ lệnh
+            if status == TradePairStatus::Delisted {
+                <DelistingTradePairs<T>>::insert(tp_hash, ());
+
+                // hoàn lại tiền cọc cho người tạo cặp
+                if let Some((owner, deposit)) = <TradePairDeposits<T>>::take(tp_hash) {
+                    T::DepositCurrency::unreserve(&owner, deposit);
+                    Self::deposit_event(Event::TradePairDepositRefunded {
+                        owner,
+                        hash: tp_hash,
+                        deposit
+                    });
+                }
+            }
+
+            Self::deposit_event(Event::TradePairStatusChanged {
+                hash: tp_hash,
+                status
+            });
+
+            Ok(())
+        }
+
+        fn do_create_limit_order(sender: T::AccountId, base: T::Hash, quote: T::Hash, otype: OrderType, price: T::Price, sell_amount: Balance<T>, tif: TimeInForce, post_only: bool, expires_at: Option<T::BlockNumber>) -> Result<MatchStats, DispatchError> {
+            // check tick_size/lot_size/min_notional của cặp
+            Self::ensure_trade_rules(base, quote, otype, price, sell_amount)?;
             Self::ensure_bounds(price, sell_amount)?;
             let buy_amount = Self::ensure_counterparty_amount_bounds(otype, price, sell_amount)?;
 
             let tp_hash = Self::ensure_trade_pair(base, quote)?;
+            Self::ensure_trade_pair_active(tp_hash)?;
+
+            // check block hết hạn phải ở tương lai và block đó chưa đầy
+            if let Some(expiry) = expires_at {
+                ensure!(expiry > frame_system::Pallet::<T>::block_number(), <Error<T>>::InvalidExpiry);
+                let expiring = <OrdersByExpiry<T>>::decode_len(expiry).unwrap_or(0) as u32;
+                ensure!(expiring < T::MaxExpiringOrdersPerBlock::get(), <Error<T>>::TooManyExpiringOrders);
+            }
+
+            // post-only chỉ được phép nằm trên sổ lệnh => không được khớp với bất kỳ PriceItem nào ở phía đối diện
+            if post_only {
+                ensure!(tif == TimeInForce::GoodTilCanceled, <Error<T>>::InvalidPostOnlyOrder);
+                ensure!(!Self::would_cross(tp_hash, otype, price), <Error<T>>::PostOnlyWouldCross);
+            }
 
             /* check token của sender, giả sử cặp BUSD/BTC 
              => Nếu Buy  => sender phải có BUSD
@@ -708,7 +1208,7 @@ pub mod pallet {
                 => sell_amount: là số lượng token mình đang bán 
                 => buy_amount: là số lượng token mình đang mua
             */ 
-            let mut order = Order::new(base, quote, sender.clone(), price, sell_amount, buy_amount, OrderOpt::Limit, otype);
+            let mut order = Order::new(base, quote, sender.clone(), price, sell_amount, buy_amount, OrderOpt::Limit, otype, tif, expires_at);
             let hash = order.hash;
 
             // Check số dư và đóng băng số dư của sender
@@ -716,32 +1216,36 @@ pub mod pallet {
             pallet_tokens::Pallet::<T>::do_freeze(sender.clone(), op_token_hash, sell_amount)?;
             Orders::insert(hash, order.clone());
 
-             // update_ nonce
-             let nonce = Self::nonce();
-             let new_nonce = nonce.checked_add(1).ok_or(ArithmeticError::Overflow)?;
-             <Nonce<T>>::put(new_nonce);
+            Self::record_new_order(sender.clone(), tp_hash, hash)?;
 
-             <OwnedTPOpenedOrders<T>>::add_order(sender.clone(), tp_hash, order.hash);
+            // order match tối đa MaxMarketOrderFills maker order, post-only thì bỏ qua vì đã check ở trên là không khớp được
+            let (filled, stats) = if post_only { (false, MatchStats::default()) } else { Self::order_match(tp_hash, &mut order, Some(T::MaxMarketOrderFills::get()))? };
 
-             let owned_index = Self::owned_orders_index(sender.clone());
-            OwnedOrders::<T>::insert(sender.clone(), owned_index, hash);
-            let new_owned_index = owned_index.checked_add(1).ok_or(ArithmeticError::Overflow)?;
-            OwnedOrdersIndex::<T>::insert(sender.clone(), new_owned_index);
+            // FOK mà không khớp hết => cả lệnh thất bại, toàn bộ thay đổi storage bị rollback
+            ensure!(filled || tif != TimeInForce::FillOrKill, <Error<T>>::OrderNotFullyFilled);
 
-            let tp_owned_index = Self::trade_pair_owned_order_index(tp_hash);
-            TradePairOwnedOrders::<T>::insert(tp_hash, tp_owned_index, hash);
-            let new_tp_owned_index = tp_owned_index.checked_add(1).ok_or(ArithmeticError::Overflow)?;
-            TradePairOwnedOrdersIndex::<T>::insert(tp_hash, new_tp_owned_index);
+            // order có thể đã bị hủy bởi self-trade prevention trong order_match
+            let stp_canceled = order.status == OrderStatus::Canceled;
 
-            // order match
-            let filled = Self::order_match(tp_hash, &mut order)?;
+            /* IOC mà không khớp hết => hủy phần còn dư và unfreeze, không đưa lên sổ lệnh
+                chạm MaxMarketOrderFills mà phần còn dư vẫn khớp được với phía đối diện => không thể nằm trên sổ lệnh, hủy giống IOC
+            */
+            let ioc_canceled = !filled && !stp_canceled && (tif == TimeInForce::ImmediateOrCancel || Self::would_cross(tp_hash, otype, price));
+            if ioc_canceled {
+                pallet_tokens::Pallet::<T>::do_unfreeze(sender.clone(), op_token_hash, order.remained_sell_amount)?;
+                order.status = OrderStatus::Canceled;
+                Orders::insert(hash, order.clone());
+            }
 
             // add order to the market order list
-            if !filled {
-                <OrderList<T>>::append(tp_hash, price, hash, order.remained_sell_amount, order.remained_buy_amount, otype);
+            if !filled && !stp_canceled && !ioc_canceled {
+                <OrderList<T>>::append(tp_hash, price, hash, order.remained_sell_amount, order.remained_buy_amount, otype)?;
+                if let Some(expiry) = expires_at {
+                    <OrdersByExpiry<T>>::try_append(expiry, hash).map_err(|_| <Error<T>>::TooManyExpiringOrders)?;
+                }
             } else {
                 <OwnedTPOpenedOrders<T>>::remove_order(sender.clone(), tp_hash, order.hash);
-                <OwnedTPClosedOrders<T>>::add_order(sender.clone(), tp_hash, order.hash);
+                <OwnedTPClosedOrders<T>>::add_order(sender.clone(), tp_hash, order.hash)?;
             }
 
             Self::deposit_event(Event::OrderCreated{
@@ -752,97 +1256,722 @@ pub mod pallet {
                 limit_order:  order.clone()}
             );
 
-            Ok(())
+            if ioc_canceled {
+                Self::deposit_event(Event::OrderCanceled {
+                    owner: sender,
+                    order_hash: hash
+                });
+            }
+
+            Ok(stats)
         }
 
-        fn do_create_market_order(sender: T::AccountId, base: T::Hash, quote: T::Hash, otype: OrderType, sell_amount: Balance<T>)-> DispatchResult {
+        /* market order do user đặt => amount là lượng quote_token cần mua/bán (Quote) hoặc lượng base_token cần chi/nhận (Base), check theo rule của cặp
+            max_slippage: giá xấu nhất chấp nhận được tính từ giá tốt nhất lúc đặt lệnh
+            => Buy: giá tốt nhất * (1 + max_slippage), Sell: giá tốt nhất * (1 - max_slippage)
+
+            Toàn bộ market order chỉ tạo 1 Order và khớp qua nhiều mức giá trong 1 lần gọi order_match:
+            + estimate_market_order duyệt sổ lệnh trước để tính lượng base/quote cần freeze, tối đa MaxMarketOrderFills order
+            + order_match khớp với tối đa MaxMarketOrderFills order, mỗi lần khớp tạo 1 Trade
+            + phần chưa khớp được unfreeze và order chuyển sang Canceled giống IOC
+            Khớp xong emit MarketOrderExecuted với giá khớp trung bình
+        */
+        fn do_create_market_order(sender: T::AccountId, base: T::Hash, quote: T::Hash, otype: OrderType, amount: MarketOrderAmount<Balance<T>>, max_slippage: Option<Permill>)-> Result<MatchStats, DispatchError> {
+            Self::ensure_market_trade_rules(base, quote, otype, amount)?;
+
             let tp_hash = Self::ensure_trade_pair(base, quote)?;
-            let head = <OrderList<T>>::read_head(tp_hash);
-            let item_price = Self::next_match_price(&head, !otype);
-            if let Some(price) = item_price {
-                if price != T::Price::min_value() || price != T::Price::max_value() {
-                    if otype == OrderType::Buy {
-                        let temp_price = Self::into_128(price.clone())?;
-                        let temp: Balance<T> = Self::from_128(temp_price/T::PriceFactor::get())?;
-
-                        let sell_amount = sell_amount * temp;
-                        Self::do_create_limit_order(sender.clone(), base, quote, otype, price, sell_amount)?;
-                    } else {
-                        Self::do_create_limit_order(sender.clone(), base, quote, otype, price, sell_amount)?;
-                    }
-                    let index = Self::owned_orders_index(sender.clone()).checked_sub(1).ok_or(<Error<T>>::OverflowError)?;
-                    let o_hash = Self::owned_orders(sender.clone(), index);
-                    if let Some(hash) = o_hash{
-                        let order = Self::orders(hash);
-                        if let Some(mut o) = order {
-                            if !o.is_finished() {
-                                let remained_buy_amount = o.remained_buy_amount;
-                                let remained_sell_amount = o.remained_sell_amount;
-                                o.sell_amount -= remained_sell_amount;
-                                o.buy_amount -= remained_buy_amount;
-                                o.remained_buy_amount = Zero::zero();
-                                o.remained_sell_amount = Zero::zero();
-                                o.status = OrderStatus::Filled;
-                                Orders::insert(hash, o);
-                                if otype == OrderType::Buy {
-                                    Self::do_create_market_order(sender.clone(), base, quote, otype, remained_buy_amount)?;
-                                } else {
-                                    Self::do_create_market_order(sender.clone(), base, quote, otype, remained_sell_amount)?;
-                                }
-                            }
-                        }
-                    }
-                }
-            }
-            Ok(())
-        }
+            Self::ensure_trade_pair_active(tp_hash)?;
 
-        fn do_cancel_limit_order(sender: T::AccountId, order_hash: T::Hash) -> DispatchResult {
-            let mut order = Self::orders(order_hash).ok_or(<Error<T>>::NoMatchingOrder)?;
+            let head = <OrderList<T>>::read_head(tp_hash);
+            let worst_price = match (Self::next_match_price(&head, !otype), max_slippage) {
+                (Some(price), Some(slippage)) if price != T::Price::min_value() && price != T::Price::max_value() => {
+                    let price_128 = Self::into_128(price)?;
+                    let delta = slippage * price_128;
+                    let worst = match otype {
+                        OrderType::Buy => price_128.saturating_add(delta),
+                        OrderType::Sell => price_128.saturating_sub(delta),
+                    };
+                    Some(Self::from_128::<T::Price>(worst)?)
+                },
+                _ => None,
+            };
+
+            let max_fills = T::MaxMarketOrderFills::get();
+            let (sell_amount, buy_amount) = Self::estimate_market_order(tp_hash, otype, amount, worst_price, max_fills)?;
+            ensure!(!sell_amount.is_zero() && !buy_amount.is_zero(), <Error<T>>::NoMarketLiquidity);
+
+            // giá của market order là giá xấu nhất được khớp, không giới hạn trượt giá thì lấy giá biên của kiểu Price
+            let order_price = worst_price.unwrap_or(match otype {
+                OrderType::Buy => T::Price::max_value(),
+                OrderType::Sell => T::Price::min_value(),
+            });
+            let sell_hash = match otype {
+                OrderType::Buy => base,
+                OrderType::Sell => quote,
+            };
+
+            let mut order = Order::new(base, quote, sender.clone(), order_price, sell_amount, buy_amount, OrderOpt::Market, otype, TimeInForce::ImmediateOrCancel, None);
+            let hash = order.hash;
+
+            pallet_tokens::Pallet::<T>::ensure_free_balance(sender.clone(), sell_hash, sell_amount)?;
+            pallet_tokens::Pallet::<T>::do_freeze(sender.clone(), sell_hash, sell_amount)?;
+            Orders::insert(hash, order.clone());
+
+            Self::record_new_order(sender.clone(), tp_hash, hash)?;
+
+            let (filled, stats) = Self::order_match(tp_hash, &mut order, Some(max_fills))?;
+
+            // không khớp hết (chạm giới hạn trượt giá, hết sổ lệnh hoặc hết MaxMarketOrderFills) => unfreeze phần còn dư
+            if !filled && order.status != OrderStatus::Canceled {
+                pallet_tokens::Pallet::<T>::do_unfreeze(sender.clone(), sell_hash, order.remained_sell_amount)?;
+                order.status = OrderStatus::Canceled;
+                Orders::insert(hash, order.clone());
+
+                <OwnedTPOpenedOrders<T>>::remove_order(sender.clone(), tp_hash, hash);
+                <OwnedTPClosedOrders<T>>::add_order(sender.clone(), tp_hash, hash)?;
+            }
+
+            // tổng lượng đã khớp lấy từ các Trade của order, vì phần dư của remained_sell_amount đã bị set về 0 khi unfreeze
+            let (base_amount, quote_amount) = <OrderOwnedTrades<T>>::iter_prefix_values(hash)
+                .filter_map(|trade_hash| Self::trades(trade_hash))
+                .fold((Balance::<T>::zero(), Balance::<T>::zero()), |(b, q), trade| (b + trade.base_amount, q + trade.quote_amount));
+
+            // giá trung bình = tổng base / tổng quote * PriceFactor
+            let avg_price = if quote_amount.is_zero() {
+                None
+            } else {
+                let avg = U256::from(Self::into_128(base_amount)?) * U256::from(T::PriceFactor::get()) / U256::from(Self::into_128(quote_amount)?);
+                let avg: u128 = avg.try_into().map_err(|_| <Error<T>>::OverflowError)?;
+                Some(Self::from_128::<T::Price>(avg)?)
+            };
+
+            Self::deposit_event(Event::OrderCreated{
+                owner: sender.clone(),
+                base_token: base,
+                quote_token: quote,
+                order_hash: hash,
+                limit_order: order.clone()}
+            );
+
+            Self::deposit_event(Event::MarketOrderExecuted {
+                owner: sender,
+                base_token: base,
+                quote_token: quote,
+                order_hash: hash,
+                otype,
+                base_amount,
+                quote_amount,
+                avg_price,
+                unfilled_amount: match amount {
+                MarketOrderAmount::Quote(amount) => amount.saturating_sub(quote_amount),
+                MarketOrderAmount::Base(amount) => amount.saturating_sub(base_amount),
+            }
+            });
+
+            Ok(stats)
+        }
+
+        /* fn duyệt sổ lệnh phía đối diện để tính (sell_amount, buy_amount) cho market order
+            + Buy: sell_amount là lượng base cần freeze (làm tròn lên ở mỗi maker order), buy_amount là lượng quote mua được
+            + Sell: sell_amount là lượng quote bán được, buy_amount là lượng base nhận về (làm tròn xuống ở mỗi maker order)
+            amount là Quote thì dừng khi đủ lượng quote, là Base thì dừng khi lượng base chạm amount
+            Ngoài ra dừng khi hết sổ lệnh, mức giá vượt worst_price hoặc đã duyệt đúng max_fills order giống order_match
+        */
+        fn estimate_market_order(tp_hash: T::Hash, otype: OrderType, amount: MarketOrderAmount<Balance<T>>, worst_price: Option<T::Price>, max_fills: u32) -> Result<(Balance<T>, Balance<T>), DispatchError> {
+            let end_item_price = match otype {
+                OrderType::Buy => Some(T::Price::max_value()),
+                OrderType::Sell => Some(T::Price::min_value()),
+            };
+            let price_factor = U256::from(T::PriceFactor::get());
+
+            // lượng còn lại tính theo đơn vị của amount
+            let mut remained = match amount {
+                MarketOrderAmount::Quote(amount) => U256::from(Self::into_128(amount)?),
+                MarketOrderAmount::Base(amount) => U256::from(Self::into_128(amount)?),
+            };
+            let mut base_amount = U256::zero();
+            let mut quote_amount = U256::zero();
+            let mut fills: u32 = 0;
+
+            let mut item = <OrderList<T>>::read_head(tp_hash);
+            'levels: loop {
+                let item_price = Self::next_match_price(&item, !otype);
+                if item_price == end_item_price || remained.is_zero() || fills >= max_fills {
+                    break;
+                }
+                let price = item_price.ok_or(<Error<T>>::OrderMatchGetPriceError)?;
+                if let Some(worst_price) = worst_price {
+                    if !Self::price_matched(worst_price, otype, price) {
+                        break;
+                    }
+                }
+                let price_u256 = U256::from(Self::into_128(price)?);
+
+                item = <LinkedItemList<T>>::get(tp_hash, item_price).ok_or(<Error<T>>::OrderMatchGetLinkedListItemError)?;
+                for order_hash in item.orders.iter() {
+                    if remained.is_zero() || fills >= max_fills {
+                        break 'levels;
+                    }
+                    fills += 1;
+
+                    // Buy khớp với maker bán => lượng quote maker đang bán, Sell khớp với maker mua => lượng quote maker đang mua
+                    let maker = Self::orders(order_hash).ok_or(<Error<T>>::OrderMatchGetOrderError)?;
+                    let available = U256::from(Self::into_128(match otype {
+                        OrderType::Buy => maker.remained_sell_amount,
+                        OrderType::Sell => maker.remained_buy_amount,
+                    })?);
+
+                    // lượng quote tối đa lấy từ maker này
+                    let take = match amount {
+                        MarketOrderAmount::Quote(_) => available.min(remained),
+                        MarketOrderAmount::Base(_) => available.min(remained * price_factor / price_u256),
+                    };
+                    if take.is_zero() {
+                        break 'levels;
+                    }
+
+                    let value = take * price_u256;
+                    let base_take = match otype {
+                        OrderType::Buy => (value + price_factor - 1) / price_factor,
+                        OrderType::Sell => value / price_factor,
+                    };
+
+                    remained = match amount {
+                        MarketOrderAmount::Quote(_) => remained - take,
+                        MarketOrderAmount::Base(_) => remained.saturating_sub(base_take),
+                    };
+                    base_amount = base_amount + base_take;
+                    quote_amount = quote_amount + take;
+                }
+            }
+
+            let base_amount: u128 = base_amount.try_into().map_err(|_| <Error<T>>::OverflowError)?;
+            let quote_amount: u128 = quote_amount.try_into().map_err(|_| <Error<T>>::OverflowError)?;
+            let base_amount: Balance<T> = Self::from_128(base_amount)?;
+            let quote_amount: Balance<T> = Self::from_128(quote_amount)?;
+
+            match otype {
+                OrderType::Buy => Ok((base_amount, quote_amount)),
+                OrderType::Sell => Ok((quote_amount, base_amount)),
+            }
+        }
+
+        fn do_cancel_limit_order(sender: T::AccountId, order_hash: T::Hash) -> DispatchResult {
+            let order = Self::orders(order_hash).ok_or(<Error<T>>::NoMatchingOrder)?;
     
             ensure!(order.owner == sender, <Error<T>>::CanOnlyCancelOwnOrder);
+
+            let tp_hash = Self::ensure_trade_pair(order.base, order.quote)?;
+            Self::ensure_trade_pair_cancelable(tp_hash)?;
     
+            Self::do_cancel_order(order)?;
+    
+            Self::deposit_event(Event::OrderCanceled {
+                owner: sender,
+                order_hash
+            });
+    
+            Ok(())
+        }
+
+        // admin hủy order của bất kỳ ai, bỏ qua check owner và trạng thái của cặp
+        fn do_force_cancel_order(order_hash: T::Hash) -> DispatchResult {
+            let order = Self::orders(order_hash).ok_or(<Error<T>>::NoMatchingOrder)?;
+            let owner = order.owner.clone();
+
+            Self::do_cancel_order(order)?;
+
+            Self::deposit_event(Event::OrderForceCanceled {
+                owner,
+                order_hash
+            });
+
+            Ok(())
+        }
+
+        /* fn sửa giá / lượng bán còn lại của 1 order đang nằm trên sổ lệnh, new_amount là lượng bán còn lại mới
+            + Giữ nguyên giá và giảm lượng => giữ nguyên vị trí trong PriceItem.orders, unfreeze phần giảm
+            + Đổi giá hoặc tăng lượng => gỡ khỏi PriceItem cũ, freeze lại và khớp lệnh như 1 order mới tại giá mới (mất vị trí ưu tiên)
+            Cả 2 trường hợp đều nằm trong 1 extrinsic nên nếu có lỗi thì toàn bộ được rollback
+        */
+        fn do_amend_order(sender: T::AccountId, order_hash: T::Hash, new_price: T::Price, new_amount: Balance<T>) -> Result<MatchStats, DispatchError> {
+            let mut order = Self::orders(order_hash).ok_or(<Error<T>>::NoMatchingOrder)?;
+            ensure!(order.owner == sender, <Error<T>>::CanOnlyAmendOwnOrder);
+            ensure!(!order.is_finished(), <Error<T>>::CanOnlyAmendNotFinishedOrder);
+
+            Self::ensure_trade_rules(order.base, order.quote, order.otype, new_price, new_amount)?;
+            let new_buy_amount = Self::ensure_counterparty_amount_bounds(order.otype, new_price, new_amount)?;
+            let tp_hash = Self::ensure_trade_pair(order.base, order.quote)?;
+            Self::ensure_trade_pair_active(tp_hash)?;
+
+            let sell_hash = match order.otype {
+                OrderType::Buy => order.base,
+                OrderType::Sell => order.quote,
+            };
+
+            // tổng lượng = lượng đã khớp + lượng còn lại mới
+            let old_remained_sell_amount = order.remained_sell_amount;
+            let old_remained_buy_amount = order.remained_buy_amount;
+            order.sell_amount = order.sell_amount - old_remained_sell_amount + new_amount;
+            order.buy_amount = order.buy_amount - old_remained_buy_amount + new_buy_amount;
+            order.remained_sell_amount = new_amount;
+            order.remained_buy_amount = new_buy_amount;
+
+            let mut stats = MatchStats::default();
+            let mut capped = false;
+            if new_price == order.price && new_amount <= old_remained_sell_amount {
+                // các lần khớp trước có thể đã làm tròn lượng mua còn lại xuống => giảm lượng bán không được làm tăng lượng mua
+                ensure!(new_buy_amount <= old_remained_buy_amount, <Error<T>>::AmendIncreasesBuyAmount);
+                let sell_delta = old_remained_sell_amount - new_amount;
+                let buy_delta = old_remained_buy_amount - order.remained_buy_amount;
+
+                <OrderList<T>>::update_reduce_amount(tp_hash, order.price, sell_delta, buy_delta);
+                pallet_tokens::Pallet::<T>::do_unfreeze(sender.clone(), sell_hash, sell_delta)?;
+                Orders::insert(order_hash, order);
+            } else {
+                <OrderList<T>>::remove_order(tp_hash, order.price, order_hash, old_remained_sell_amount, old_remained_buy_amount)?;
+                pallet_tokens::Pallet::<T>::do_unfreeze(sender.clone(), sell_hash, old_remained_sell_amount)?;
+                pallet_tokens::Pallet::<T>::ensure_free_balance(sender.clone(), sell_hash, new_amount)?;
+                pallet_tokens::Pallet::<T>::do_freeze(sender.clone(), sell_hash, new_amount)?;
+
+                order.price = new_price;
+                Orders::insert(order_hash, order.clone());
+
+                // order khớp như 1 taker => gỡ khỏi OrdersByExpiry, chỉ thêm lại nếu vẫn còn nằm trên sổ lệnh
+                Self::remove_order_expiry(&order);
+
+                let (filled, amend_stats) = Self::order_match(tp_hash, &mut order, Some(T::MaxMarketOrderFills::get()))?;
+                stats = amend_stats;
+
+                // chạm MaxMarketOrderFills mà phần còn dư vẫn khớp được => không thể nằm trên sổ lệnh, hủy phần còn dư
+                capped = !filled && order.status != OrderStatus::Canceled && Self::would_cross(tp_hash, order.otype, new_price);
+                if capped {
+                    pallet_tokens::Pallet::<T>::do_unfreeze(sender.clone(), sell_hash, order.remained_sell_amount)?;
+                    order.status = OrderStatus::Canceled;
+                }
+
+                if !filled && order.status != OrderStatus::Canceled {
+                    <OrderList<T>>::append(tp_hash, new_price, order_hash, order.remained_sell_amount, order.remained_buy_amount, order.otype)?;
+                    if let Some(expiry) = order.expires_at {
+                        <OrdersByExpiry<T>>::try_append(expiry, order_hash).map_err(|_| <Error<T>>::TooManyExpiringOrders)?;
+                    }
+                } else {
+                    <OwnedTPOpenedOrders<T>>::remove_order(sender.clone(), tp_hash, order_hash);
+                    <OwnedTPClosedOrders<T>>::add_order(sender.clone(), tp_hash, order_hash)?;
+                }
+                Orders::insert(order_hash, order);
+            }
+
+            Self::deposit_event(Event::OrderAmended {
+                owner: sender.clone(),
+                order_hash,
+                price: new_price,
+                amount: new_amount
+            });
+
+            if capped {
+                Self::deposit_event(Event::OrderCanceled {
+                    owner: sender,
+                    order_hash
+                });
+            }
+
+            Ok(stats)
+        }
+
+        /* fn thực hiện lần lượt các phần tử trong batch, AllOrNothing thì lỗi là return luôn (extrinsic tự rollback)
+            số phần tử đã bị giới hạn bởi MaxBatchOrders từ lúc decode extrinsic
+            trả về weight thực tế: phần tử thành công tính theo số lần khớp, phần tử lỗi (BestEffort) tính theo weight tối đa
+        */
+        fn do_batch_orders(sender: T::AccountId, orders: Vec<OrderSpec<T>>, mode: BatchMode) -> Result<Weight, DispatchError> {
+            let mut results = Vec::with_capacity(orders.len());
+            let mut weight = Weight::zero();
+            for spec in orders {
+                let max_weight = Self::max_order_spec_weight(&spec);
+                let result = match mode {
+                    BatchMode::AllOrNothing => Ok(Self::execute_order_spec(sender.clone(), spec)?),
+                    BatchMode::BestEffort => with_storage_layer(|| Self::execute_order_spec(sender.clone(), spec)),
+                };
+                weight = weight.saturating_add(*result.as_ref().unwrap_or(&max_weight));
+                results.push(result.map(|_| ()));
+            }
+
+            Self::deposit_event(Event::BatchOrdersExecuted {
+                owner: sender,
+                results
+            });
+
+            Ok(weight)
+        }
+
+        // thực hiện 1 phần tử của batch, trả về weight thực tế của phần tử đó
+        fn execute_order_spec(sender: T::AccountId, spec: OrderSpec<T>) -> Result<Weight, DispatchError> {
+            match spec {
+                OrderSpec::Create { base, quote, otype, price, sell_amount, tif, post_only, expires_at } => {
+                    let stats = Self::do_create_limit_order(sender, base, quote, otype, price, sell_amount, tif, post_only, expires_at)?;
+                    Ok(Self::create_order_weight(stats))
+                },
+                OrderSpec::Cancel { order_hash } => {
+                    Self::do_cancel_limit_order(sender, order_hash)?;
+                    Ok(T::WeightInfo::cancel_limit_order())
+                },
+            }
+        }
+
+        // weight tối đa của 1 lần đặt lệnh: khớp MaxMarketOrderFills order trên MaxMarketOrderFills mức giá
+        pub(crate) fn max_create_order_weight() -> Weight {
+            let max_fills = T::MaxMarketOrderFills::get();
+            T::WeightInfo::create_order(max_fills, max_fills).saturating_add(Self::candles_weight(max_fills))
+        }
+
+        // weight thực tế của 1 lần đặt lệnh theo số mức giá và số order đã khớp
+        fn create_order_weight(stats: MatchStats) -> Weight {
+            T::WeightInfo::create_order(stats.levels, stats.fills).saturating_add(Self::candles_weight(stats.fills))
+        }
+
+        // mỗi order khớp cập nhật nến ở mọi interval trong CandleIntervals
+        fn candles_weight(fills: u32) -> Weight {
+            let intervals = T::CandleIntervals::get().len() as u64;
+            T::WeightInfo::update_candle().saturating_mul(intervals.saturating_mul(fills as u64))
+        }
+
+        // weight tối đa của 1 phần tử trong batch_orders
+        pub(crate) fn max_order_spec_weight(spec: &OrderSpec<T>) -> Weight {
+            match spec {
+                OrderSpec::Create { .. } => Self::max_create_order_weight(),
+                OrderSpec::Cancel { .. } => T::WeightInfo::cancel_limit_order(),
+            }
+        }
+
+        /* fn hủy toàn bộ order đang mở của sender, tối đa MaxCancelAllOrders order mỗi lần gọi
+            + Có tp_hash => lấy ds order từ OwnedTPOpenedOrders của cặp tp đó
+            + Không có tp_hash => duyệt OwnedTPOpenedOrders của các cặp trong OwnedOpenedTradePairs của sender
+              mỗi order lấy ra và mỗi cặp Paused bị bỏ qua đều tính vào MaxCancelAllOrders => số lần đọc storage bị chặn
+            Mỗi order được hủy qua do_cancel_order giống cancel_limit_order, trả về số order đã hủy
+        */
+        fn do_cancel_all_orders(sender: T::AccountId, tp_hash: Option<T::Hash>) -> Result<u32, DispatchError> {
+            let max = T::MaxCancelAllOrders::get();
+
+            let order_hashes: Vec<T::Hash> = match tp_hash {
+                Some(tp_hash) => {
+                    Self::ensure_trade_pair_cancelable(tp_hash)?;
+                    Self::owned_tp_opened_orders((sender.clone(), tp_hash)).map(|orders| orders.into_inner()).unwrap_or_default()
+                },
+                None => {
+                    let mut order_hashes = Vec::new();
+                    let mut budget = max as usize;
+                    for tp_hash in <OwnedOpenedTradePairs<T>>::iter_key_prefix(sender.clone()) {
+                        if budget == 0 {
+                            break;
+                        }
+                        if Self::ensure_trade_pair_cancelable(tp_hash).is_err() {
+                            budget -= 1;
+                            continue;
+                        }
+                        let orders = Self::owned_tp_opened_orders((sender.clone(), tp_hash)).unwrap_or_default();
+                        let count = orders.len().min(budget);
+                        budget -= count;
+                        order_hashes.extend(orders.into_iter().take(count));
+                    }
+                    order_hashes
+                }
+            };
+
+            let mut canceled: u32 = 0;
+            for order_hash in order_hashes {
+                if canceled >= max {
+                    break;
+                }
+
+                let order = match Self::orders(order_hash) {
+                    Some(order) if !order.is_finished() => order,
+                    _ => continue,
+                };
+
+                // không có tp_hash thì bỏ qua order của các cặp đang Paused
+                let order_tp_hash = Self::ensure_trade_pair(order.base, order.quote)?;
+                if Self::ensure_trade_pair_cancelable(order_tp_hash).is_err() {
+                    continue;
+                }
+
+                Self::do_cancel_order(order)?;
+                canceled += 1;
+
+                Self::deposit_event(Event::OrderCanceled {
+                    owner: sender.clone(),
+                    order_hash
+                });
+            }
+
+            Self::deposit_event(Event::AllOrdersCanceled {
+                owner: sender,
+                tp_hash,
+                count: canceled
+            });
+
+            Ok(canceled)
+        }
+
+        // hủy 1 order đang nằm trên sổ lệnh: gỡ khỏi PriceList, unfreeze phần còn dư và chuyển sang closed_order
+        fn do_cancel_order(mut order: Order<T>) -> DispatchResult {
             ensure!(!order.is_finished(), <Error<T>>::CanOnlyCancelNotFinishedOrder);
     
             let tp_hash = Self::ensure_trade_pair(order.base, order.quote)?;
     
-            <OrderList<T>>::remove_order(tp_hash, order.price, order.hash, order.sell_amount, order.buy_amount)?;
+            // PriceItem chỉ giữ lượng còn dư của order nên chỉ trừ đi phần còn dư
+            <OrderList<T>>::remove_order(tp_hash, order.price, order.hash, order.remained_sell_amount, order.remained_buy_amount)?;
     
             order.status = OrderStatus::Canceled;
-            <Orders<T>>::insert(order_hash, order.clone());
+            <Orders<T>>::insert(order.hash, order.clone());
     
-            <OwnedTPOpenedOrders<T>>::remove_order(sender.clone(), tp_hash, order_hash);
-            <OwnedTPClosedOrders<T>>::add_order(sender.clone(), tp_hash, order_hash);
+            <OwnedTPOpenedOrders<T>>::remove_order(order.owner.clone(), tp_hash, order.hash);
+            <OwnedTPClosedOrders<T>>::add_order(order.owner.clone(), tp_hash, order.hash)?;
+            Self::remove_order_expiry(&order);
     
             let sell_hash = match order.otype {
                 OrderType::Buy => order.base,
                 OrderType::Sell => order.quote,
             };
     
-            pallet_tokens::Pallet::<T>::do_unfreeze(sender.clone(), sell_hash, order.remained_sell_amount)?;
+            pallet_tokens::Pallet::<T>::do_unfreeze(order.owner.clone(), sell_hash, order.remained_sell_amount)?;
     
-            Self::deposit_event(Event::OrderCanceled {
-                owner: sender,
+            Ok(())
+        }
+
+        // gỡ order đã kết thúc (hủy, khớp hết) khỏi OrdersByExpiry để không chiếm chỗ của order khác cùng block hết hạn
+        fn remove_order_expiry(order: &Order<T>) {
+            if let Some(expiry) = order.expires_at {
+                <OrdersByExpiry<T>>::mutate_exists(expiry, |hashes| {
+                    if let Some(list) = hashes {
+                        list.retain(|hash| *hash != order.hash);
+                        if list.is_empty() {
+                            *hashes = None;
+                        }
+                    }
+                });
+            }
+        }
+
+        /* hủy order bị migration gỡ khỏi PriceItem vì vượt MaxOrdersPerPriceLevel
+            order không còn trên sổ lệnh nên chỉ unfreeze phần còn dư và chuyển sang closed_order
+            trả về (remained_sell_amount, remained_buy_amount) để trừ khỏi PriceItem, order đã kết thúc thì trả về None
+        */
+        pub(crate) fn cancel_unlisted_order(tp_hash: T::Hash, order_hash: T::Hash) -> Option<(Balance<T>, Balance<T>)> {
+            let mut order = Self::orders(order_hash).filter(|order| !order.is_finished())?;
+            let remained = (order.remained_sell_amount, order.remained_buy_amount);
+
+            order.status = OrderStatus::Canceled;
+            <Orders<T>>::insert(order_hash, order.clone());
+
+            <OwnedTPOpenedOrders<T>>::remove_order(order.owner.clone(), tp_hash, order_hash);
+            let _ = <OwnedTPClosedOrders<T>>::add_order(order.owner.clone(), tp_hash, order_hash);
+            Self::remove_order_expiry(&order);
+
+            let sell_hash = match order.otype {
+                OrderType::Buy => order.base,
+                OrderType::Sell => order.quote,
+            };
+            let _ = pallet_tokens::Pallet::<T>::do_unfreeze(order.owner.clone(), sell_hash, order.remained_sell_amount);
+
+            Self::deposit_event(Event::OrderForceCanceled {
+                owner: order.owner,
                 order_hash
             });
-    
+
+            Some(remained)
+        }
+
+        /* hủy stop order bị migration gỡ khỏi TPStopOrders/TriggeredStopOrders vì vượt giới hạn
+            stop order v0 chưa bị freeze lượng bán nên không unfreeze ở đây
+        */
+        pub(crate) fn cancel_unlisted_stop_order(stop_order_hash: T::Hash) {
+            <StopOrders<T>>::mutate(stop_order_hash, |stop_order| {
+                if let Some(stop_order) = stop_order {
+                    stop_order.status = StopOrderStatus::Canceled;
+                    Self::deposit_event(Event::StopOrderCanceled {
+                        owner: stop_order.owner.clone(),
+                        stop_order_hash
+                    });
+                }
+            });
+        }
+
+        /* stop order v0 đang chờ chưa bị freeze lượng bán => migration freeze lượng bán giống lúc tạo lệnh
+            không đủ số dư hoặc Market Buy (sell_amount v0 là lượng quote cần mua, không biết trước lượng base_token) => gỡ khỏi ds chờ và hủy
+            trả về true nếu stop order bị hủy
+        */
+        pub(crate) fn freeze_v0_stop_order(mut stop_order: StopOrder<T>) -> bool {
+            let funded = !(stop_order.oopt == OrderOpt::Market && stop_order.otype == OrderType::Buy) &&
+                pallet_tokens::Pallet::<T>::ensure_free_balance(stop_order.owner.clone(), stop_order.sell_hash(), stop_order.sell_amount).is_ok() &&
+                pallet_tokens::Pallet::<T>::do_freeze(stop_order.owner.clone(), stop_order.sell_hash(), stop_order.sell_amount).is_ok();
+            if funded {
+                return false
+            }
+
+            match stop_order.status {
+                StopOrderStatus::Pending => {
+                    if let Ok(tp_hash) = Self::ensure_trade_pair(stop_order.base, stop_order.quote) {
+                        Self::remove_pending_stop_order(tp_hash, &stop_order);
+                    }
+                },
+                _ => <TriggeredStopOrders<T>>::mutate(|queue| queue.retain(|&h| h != stop_order.hash)),
+            }
+
+            stop_order.status = StopOrderStatus::Canceled;
+            <StopOrders<T>>::insert(stop_order.hash, stop_order.clone());
+            Self::deposit_event(Event::StopOrderCanceled {
+                owner: stop_order.owner,
+                stop_order_hash: stop_order.hash
+            });
+
+            true
+        }
+
+        // hủy toàn bộ order hết hạn tại block_number, trả về weight đã dùng
+        pub(crate) fn expire_orders(block_number: T::BlockNumber) -> Weight {
+            let expired = <OrdersByExpiry<T>>::take(block_number);
+            let mut canceled: u64 = 0;
+
+            for order_hash in expired.iter() {
+                let order = match Self::orders(order_hash) {
+                    Some(order) => order,
+                    None => continue,
+                };
+                // order đã khớp hết hoặc đã bị hủy trước đó thì bỏ qua
+                if order.is_finished() {
+                    continue;
+                }
+
+                let owner = order.owner.clone();
+                if with_storage_layer(|| Self::do_cancel_order(order)).is_ok() {
+                    canceled += 1;
+                    Self::deposit_event(Event::OrderExpired { owner, order_hash: *order_hash });
+                }
+            }
+
+            T::DbWeight::get().reads_writes(1 + expired.len() as u64, 1)
+                .saturating_add(T::WeightInfo::expire_order().saturating_mul(canceled))
+        }
+
+        /* fn hủy dần order của các cặp đã delist, tối đa MaxDelistOrdersPerBlock order mỗi block
+            Mỗi lần lấy order đầu tiên ở mức giá tốt nhất phía mua, hết phía mua thì sang phía bán
+            => do_cancel_order gỡ order khỏi PriceList và unfreeze phần còn dư qua pallet_tokens::do_unfreeze
+            Sổ lệnh trống thì cặp được bỏ khỏi DelistingTradePairs
+        */
+        pub(crate) fn sweep_delisted_trade_pairs() -> Weight {
+            let pairs: Vec<T::Hash> = <DelistingTradePairs<T>>::iter_keys().collect();
+            if pairs.is_empty() {
+                return T::DbWeight::get().reads(1)
+            }
+
+            let mut budget = T::MaxDelistOrdersPerBlock::get();
+            let mut canceled: u64 = 0;
+            let mut swept = Vec::new();
+
+            for tp_hash in pairs.iter() {
+                loop {
+                    let head = <OrderList<T>>::read_head(*tp_hash);
+                    let item_price = match Self::next_match_price(&head, OrderType::Buy) {
+                        Some(price) if price != T::Price::min_value() => Some(price),
+                        _ => match Self::next_match_price(&head, OrderType::Sell) {
+                            Some(price) if price != T::Price::max_value() => Some(price),
+                            _ => None,
+                        },
+                    };
+
+                    // sổ lệnh đã trống => cặp này sweep xong
+                    let item_price = match item_price {
+                        Some(price) => price,
+                        None => {
+                            swept.push(*tp_hash);
+                            break;
+                        }
+                    };
+
+                    if budget == 0 {
+                        break;
+                    }
+                    budget -= 1;
+
+                    let order_hash = match <LinkedItemList<T>>::get(*tp_hash, Some(item_price)).and_then(|item| item.orders.first().cloned()) {
+                        Some(order_hash) => order_hash,
+                        None => {
+                            // PriceItem rỗng còn sót lại => gỡ luôn khỏi list
+                            <OrderList<T>>::remove_item(*tp_hash, item_price);
+                            continue;
+                        }
+                    };
+
+                    let result = with_storage_layer(|| -> DispatchResult {
+                        let order = Self::orders(order_hash).ok_or(<Error<T>>::NoMatchingOrder)?;
+                        let owner = order.owner.clone();
+                        if order.is_finished() {
+                            // order đã kết thúc nhưng vẫn còn trong PriceItem => chỉ gỡ khỏi list
+                            <OrderList<T>>::remove_order(*tp_hash, item_price, order_hash, Zero::zero(), Zero::zero())?;
+                        } else {
+                            Self::do_cancel_order(order)?;
+                            Self::deposit_event(Event::OrderCanceled { owner, order_hash });
+                        }
+                        Ok(())
+                    });
+
+                    match result {
+                        Ok(_) => canceled += 1,
+                        // không gỡ được order => dừng cặp này, block sau thử lại
+                        Err(_) => break,
+                    }
+                }
+
+                if budget == 0 {
+                    break;
+                }
+            }
+
+            for tp_hash in swept.iter() {
+                <DelistingTradePairs<T>>::remove(tp_hash);
+                Self::deposit_event(Event::TradePairDelistSwept { hash: *tp_hash });
+            }
+
+            T::DbWeight::get().reads_writes(1 + pairs.len() as u64 + swept.len() as u64, swept.len() as u64)
+                .saturating_add(T::WeightInfo::sweep_delisted_order().saturating_mul(canceled))
+        }
+
+        // chỉ cặp Active mới được đặt order mới và khớp lệnh
+        fn ensure_trade_pair_active(tp_hash: T::Hash) -> DispatchResult {
+            let tp = Self::trade_pairs(tp_hash).ok_or(<Error<T>>::NoMatchingTradePair)?;
+            ensure!(tp.status == TradePairStatus::Active, <Error<T>>::TradePairNotActive);
+            Ok(())
+        }
+
+        // user chỉ không được hủy order khi cặp đang Paused
+        fn ensure_trade_pair_cancelable(tp_hash: T::Hash) -> DispatchResult {
+            let tp = Self::trade_pairs(tp_hash).ok_or(<Error<T>>::NoMatchingTradePair)?;
+            ensure!(tp.status != TradePairStatus::Paused, <Error<T>>::TradePairPaused);
             Ok(())
         }
 
-        fn order_match(tp_hash: T::Hash, order: &mut Order<T>) -> Result<bool, DispatchError> {
+        /* max_fills: số maker order tối đa được khớp trong 1 lần gọi, None => không giới hạn
+            trả về (order đã khớp hết hay chưa, số mức giá và số maker order đã khớp)
+        */
+        fn order_match(tp_hash: T::Hash, order: &mut Order<T>, max_fills: Option<u32>) -> Result<(bool, MatchStats), DispatchError> {
             let mut head = <OrderList<T>>::read_head(tp_hash);
+            let mut fills: u32 = 0;
+            let mut levels: u32 = 0;
     
             let end_item_price;
             let otype = order.otype;
             let oprice = order.price;
     
+            // Buy đi theo next về node top (giá max), Sell đi theo prev về node bottom (giá min)
             if otype == OrderType::Buy {
-                end_item_price = Some(T::Price::min_value());
-            } else {
                 end_item_price = Some(T::Price::max_value());
+            } else {
+                end_item_price = Some(T::Price::min_value());
             }
     
             let tp = Self::trade_pairs(tp_hash).ok_or(<Error<T>>::NoMatchingTradePair)?;
+            // chỉ cặp Active mới được khớp lệnh
+            ensure!(tp.status == TradePairStatus::Active, <Error<T>>::TradePairNotActive);
             let give: T::Hash; // token bán
             let have: T::Hash; // token mua
     
@@ -858,7 +1987,10 @@ pub mod pallet {
             };
     
             loop {
-                if order.status == OrderStatus::Filled {
+                if order.status == OrderStatus::Filled || order.status == OrderStatus::Canceled {
+                    break;
+                }
+                if max_fills.map_or(false, |max| fills >= max) {
                     break;
                 }
                 
@@ -874,12 +2006,29 @@ pub mod pallet {
                 if !Self::price_matched(oprice, otype, item_price){
                     break
                 }
+                levels += 1;
     
                 // Lúc này item_price này đã khớp với order_price => lấy Price_item tại mức giá này ra và check list order
                 let item = <LinkedItemList<T>>::get(tp_hash, Some(item_price)).ok_or(<Error<T>>::OrderMatchGetLinkedListItemError)?;
                 for ohash in item.orders.iter() {
+                    if max_fills.map_or(false, |max| fills >= max) {
+                        break;
+                    }
+                    fills += 1;
     
                     let mut o = Self::orders(ohash).ok_or(<Error<T>>::OrderMatchGetOrderError)?;
+
+                    // maker cùng account với taker => xử lý theo chế độ self-trade prevention của account
+                    if o.owner == order.owner {
+                        let mode = Self::self_trade_prevention(order.owner.clone()).unwrap_or(SelfTradePrevention::Allow);
+                        if mode != SelfTradePrevention::Allow {
+                            Self::prevent_self_trade(tp_hash, order, o, mode, give, have)?;
+                            if order.status == OrderStatus::Canceled {
+                                break
+                            }
+                            continue
+                        }
+                    }
     
                     let (base_qty, quote_qty) = Self::calculate_ex_amount(&o, &order)?;
     
@@ -908,6 +2057,12 @@ pub mod pallet {
                     pallet_tokens::Pallet::<T>::do_unfreeze(o.owner.clone(), have, have_qty)?;
                     pallet_tokens::Pallet::<T>::do_transfer(order.owner.clone(), o.owner.clone(), give, give_qty)?;
                     pallet_tokens::Pallet::<T>::do_transfer(o.owner.clone(), order.owner.clone(), have, have_qty)?;
+
+                    // thu phí: maker trả phí bằng token give nhận được, taker trả phí bằng token have nhận được
+                    let maker_fee = T::MakerFee::get() * give_qty;
+                    let taker_fee = T::TakerFee::get() * have_qty;
+                    Self::charge_fee(o.owner.clone(), give, maker_fee)?;
+                    Self::charge_fee(order.owner.clone(), have, taker_fee)?;
     
                     order.remained_sell_amount = order.remained_sell_amount.checked_sub(&give_qty).ok_or(<Error<T>>::OrderMatchSubstractError)?;
                     order.remained_buy_amount = order.remained_buy_amount.checked_sub(&have_qty).ok_or(<Error<T>>::OrderMatchSubstractError)?;
@@ -923,20 +2078,24 @@ pub mod pallet {
                         }
     
                         <OwnedTPOpenedOrders<T>>::remove_order(order.owner.clone(), tp_hash, order.hash);
-                        <OwnedTPClosedOrders<T>>::add_order(order.owner.clone(), tp_hash, order.hash);
+                        <OwnedTPClosedOrders<T>>::add_order(order.owner.clone(), tp_hash, order.hash)?;
     
                         ensure!(order.is_finished(), <Error<T>>::OrderMatchOrderIsNotFinished);
                     }
     
+                    // phần lẻ còn dư của maker khi maker đã khớp hết, được unfreeze và phải trừ luôn khỏi PriceItem
+                    let mut maker_leftover: Balance<T> = Zero::zero();
                     if o.remained_buy_amount == Zero::zero() {
                         o.status = OrderStatus::Filled;
                         if o.remained_sell_amount != Zero::zero() {
                             pallet_tokens::Pallet::<T>::do_unfreeze(o.owner.clone(), have, o.remained_sell_amount)?;
+                            maker_leftover = o.remained_sell_amount;
                             o.remained_sell_amount = Zero::zero();
                         }
     
                         <OwnedTPOpenedOrders<T>>::remove_order(o.owner.clone(), tp_hash, o.hash);
-                        <OwnedTPClosedOrders<T>>::add_order(o.owner.clone(), tp_hash, o.hash);
+                        <OwnedTPClosedOrders<T>>::add_order(o.owner.clone(), tp_hash, o.hash)?;
+                        Self::remove_order_expiry(&o);
     
                         ensure!(o.is_finished(), <Error<T>>::OrderMatchOrderIsNotFinished);
                     }
@@ -945,16 +2104,16 @@ pub mod pallet {
                     Orders::insert(o.hash.clone(), o.clone());
     
                     // save the trade pair market data
-                    Self::set_tp_market_data(tp_hash, o.price, quote_qty)?;
+                    Self::set_tp_market_data(tp_hash, o.price, base_qty, quote_qty)?;
     
                     // update maker order's amount in market
-                    <OrderList<T>>::update_reduce_amount(tp_hash, o.price, have_qty, give_qty);
+                    <OrderList<T>>::update_reduce_amount(tp_hash, o.price, have_qty + maker_leftover, give_qty);
     
                     // remove the matched order
                     <OrderList<T>>::remove_order_match_price(tp_hash, !otype);
     
                     // save the trade data
-                    let trade = Trade::new(tp.base, tp.quote, &o, &order, base_qty, quote_qty);
+                    let trade = Trade::new(tp.base, tp.quote, &o, &order, base_qty, quote_qty, maker_fee, taker_fee);
                     Trades::insert(trade.hash, trade.clone());
     
                     Self::deposit_event(Event::TradeCreated{
@@ -962,7 +2121,9 @@ pub mod pallet {
                         base_token: tp.base,
                         quote_token: tp.quote,
                         trade_hash: trade.hash,
-                        trade: trade.clone()}
+                        trade: trade.clone(),
+                        maker_fee,
+                        taker_fee}
                     );
     
                     // save trade reference data to store
@@ -985,13 +2146,85 @@ pub mod pallet {
                 head = <OrderList<T>>::read_head(tp_hash);
             }
     
-            if order.status == OrderStatus::Filled {
-                Ok(true)
-            } else {
-                Ok(false)
+            Ok((order.status == OrderStatus::Filled, MatchStats { levels, fills }))
+        }
+        
+        /* fn xử lý khi taker (order) khớp với maker của cùng account, gọi trước calculate_ex_amount trong order_match
+            Các order bị hủy ở đây đều được unfreeze phần còn dư, maker thì được gỡ khỏi PriceList qua do_cancel_order
+        */
+        fn prevent_self_trade(tp_hash: T::Hash, order: &mut Order<T>, mut maker: Order<T>, mode: SelfTradePrevention, give: T::Hash, have: T::Hash) -> DispatchResult {
+            let mut canceled = Vec::new();
+
+            match mode {
+                SelfTradePrevention::Allow => return Ok(()),
+                SelfTradePrevention::CancelNewest => {
+                    Self::cancel_taker_order(tp_hash, order, give)?;
+                    canceled.push(order.hash);
+                },
+                SelfTradePrevention::CancelOldest => {
+                    canceled.push(maker.hash);
+                    Self::do_cancel_order(maker.clone())?;
+                },
+                SelfTradePrevention::CancelBoth => {
+                    canceled.push(maker.hash);
+                    Self::do_cancel_order(maker.clone())?;
+                    Self::cancel_taker_order(tp_hash, order, give)?;
+                    canceled.push(order.hash);
+                },
+                SelfTradePrevention::DecrementAndCancel => {
+                    // phần chồng nhau giữa 2 lệnh, tính giống như 1 lần khớp nhưng không chuyển token
+                    let (base_qty, quote_qty) = Self::calculate_ex_amount(&maker, &order)?;
+                    let (give_qty, have_qty) = match order.otype {
+                        OrderType::Buy => (base_qty, quote_qty),
+                        OrderType::Sell => (quote_qty, base_qty),
+                    };
+
+                    pallet_tokens::Pallet::<T>::do_unfreeze(order.owner.clone(), give, give_qty)?;
+                    pallet_tokens::Pallet::<T>::do_unfreeze(maker.owner.clone(), have, have_qty)?;
+
+                    order.remained_sell_amount = order.remained_sell_amount.checked_sub(&give_qty).ok_or(<Error<T>>::OrderMatchSubstractError)?;
+                    order.remained_buy_amount = order.remained_buy_amount.checked_sub(&have_qty).ok_or(<Error<T>>::OrderMatchSubstractError)?;
+                    maker.remained_sell_amount = maker.remained_sell_amount.checked_sub(&have_qty).ok_or(<Error<T>>::OrderMatchSubstractError)?;
+                    maker.remained_buy_amount = maker.remained_buy_amount.checked_sub(&give_qty).ok_or(<Error<T>>::OrderMatchSubstractError)?;
+
+                    <OrderList<T>>::update_reduce_amount(tp_hash, maker.price, have_qty, give_qty);
+                    Orders::insert(maker.hash, maker.clone());
+                    Orders::insert(order.hash, order.clone());
+
+                    if maker.remained_buy_amount.is_zero() {
+                        canceled.push(maker.hash);
+                        Self::do_cancel_order(maker.clone())?;
+                    }
+                    if order.remained_buy_amount.is_zero() {
+                        Self::cancel_taker_order(tp_hash, order, give)?;
+                        canceled.push(order.hash);
+                    }
+                },
             }
+
+            Self::deposit_event(Event::SelfTradePrevented {
+                owner: order.owner.clone(),
+                taker_order_hash: order.hash,
+                maker_order_hash: maker.hash,
+                mode,
+                canceled
+            });
+
+            Ok(())
         }
-        
+
+        // hủy taker đang khớp dở (chưa nằm trên sổ lệnh): unfreeze phần còn dư và chuyển sang closed_order
+        fn cancel_taker_order(tp_hash: T::Hash, order: &mut Order<T>, give: T::Hash) -> DispatchResult {
+            pallet_tokens::Pallet::<T>::do_unfreeze(order.owner.clone(), give, order.remained_sell_amount)?;
+            order.status = OrderStatus::Canceled;
+            Orders::insert(order.hash, order.clone());
+
+            <OwnedTPOpenedOrders<T>>::remove_order(order.owner.clone(), tp_hash, order.hash);
+            <OwnedTPClosedOrders<T>>::add_order(order.owner.clone(), tp_hash, order.hash)?;
+
+            Ok(())
+        }
+
         // fn check bound
         fn ensure_bounds(price: T::Price, sell_amount: Balance<T>) -> DispatchResult{
             // check giá đặt phải > 0 và < max của type Price
@@ -1001,6 +2234,84 @@ pub mod pallet {
             Ok(())
         }
 
+        /* fn check order limit theo rule của cặp, giả sử cặp BUSD/BTC
+            + tick_size: giá đặt phải chia hết cho tick_size
+            + lot_size: lượng BTC (Buy => buy_amount, Sell => sell_amount) phải chia hết cho lot_size
+            + min_notional: lượng BUSD (Buy => sell_amount, Sell => buy_amount) phải >= min_notional
+        */
+        fn ensure_trade_rules(base: T::Hash, quote: T::Hash, otype: OrderType, price: T::Price, sell_amount: Balance<T>) -> DispatchResult {
+            Self::ensure_bounds(price, sell_amount)?;
+            let buy_amount = Self::ensure_counterparty_amount_bounds(otype, price, sell_amount)?;
+
+            let tp_hash = Self::ensure_trade_pair(base, quote)?;
+            let tp = Self::trade_pairs(tp_hash).ok_or(<Error<T>>::NoMatchingTradePair)?;
+
+            let (quote_amount, base_amount) = match otype {
+                OrderType::Buy => (buy_amount, sell_amount),
+                OrderType::Sell => (sell_amount, buy_amount),
+            };
+
+            ensure!((price % tp.tick_size).is_zero(), <Error<T>>::PriceNotMultipleOfTickSize);
+            ensure!((quote_amount % tp.lot_size).is_zero(), <Error<T>>::AmountNotMultipleOfLotSize);
+            ensure!(base_amount >= tp.min_notional, <Error<T>>::BelowMinNotional);
+
+            Ok(())
+        }
+
+        /* market order không có giá đặt
+            + Quote(n): n phải chia hết cho lot_size, min_notional ước tính theo giá tốt nhất ở phía đối diện, sổ lệnh trống thì bỏ qua
+            + Base(n): n chính là giá trị của order nên chỉ cần >= min_notional
+        */
+        fn ensure_market_trade_rules(base: T::Hash, quote: T::Hash, otype: OrderType, amount: MarketOrderAmount<Balance<T>>) -> DispatchResult {
+            let amount = match amount {
+                MarketOrderAmount::Quote(amount) => amount,
+                MarketOrderAmount::Base(amount) => {
+                    ensure!(amount > Zero::zero(), <Error<T>>::BoundsCheckFailedAmount);
+                    let tp_hash = Self::ensure_trade_pair(base, quote)?;
+                    let tp = Self::trade_pairs(tp_hash).ok_or(<Error<T>>::NoMatchingTradePair)?;
+                    ensure!(amount >= tp.min_notional, <Error<T>>::BelowMinNotional);
+                    return Ok(())
+                }
+            };
+            ensure!(amount > Zero::zero(), <Error<T>>::BoundsCheckFailedAmount);
+
+            let tp_hash = Self::ensure_trade_pair(base, quote)?;
+            let tp = Self::trade_pairs(tp_hash).ok_or(<Error<T>>::NoMatchingTradePair)?;
+
+            ensure!((amount % tp.lot_size).is_zero(), <Error<T>>::AmountNotMultipleOfLotSize);
+
+            let head = <OrderList<T>>::read_head(tp_hash);
+            if let Some(price) = Self::next_match_price(&head, !otype) {
+                if price != T::Price::min_value() && price != T::Price::max_value() {
+                    let notional = U256::from(Self::into_128(amount)?) * U256::from(Self::into_128(price)?) / U256::from(T::PriceFactor::get());
+                    ensure!(notional >= U256::from(Self::into_128(tp.min_notional)?), <Error<T>>::BelowMinNotional);
+                }
+            }
+
+            Ok(())
+        }
+
+        // update nonce và các index của order mới: opened_order, OwnedOrders, TradePairOwnedOrders
+        fn record_new_order(sender: T::AccountId, tp_hash: T::Hash, hash: T::Hash) -> DispatchResult {
+            let nonce = Self::nonce();
+            let new_nonce = nonce.checked_add(1).ok_or(ArithmeticError::Overflow)?;
+            <Nonce<T>>::put(new_nonce);
+
+            <OwnedTPOpenedOrders<T>>::add_order(sender.clone(), tp_hash, hash)?;
+
+            let owned_index = Self::owned_orders_index(sender.clone());
+            OwnedOrders::<T>::insert(sender.clone(), owned_index, hash);
+            let new_owned_index = owned_index.checked_add(1).ok_or(ArithmeticError::Overflow)?;
+            OwnedOrdersIndex::<T>::insert(sender.clone(), new_owned_index);
+
+            let tp_owned_index = Self::trade_pair_owned_order_index(tp_hash);
+            TradePairOwnedOrders::<T>::insert(tp_hash, tp_owned_index, hash);
+            let new_tp_owned_index = tp_owned_index.checked_add(1).ok_or(ArithmeticError::Overflow)?;
+            TradePairOwnedOrdersIndex::<T>::insert(tp_hash, new_tp_owned_index);
+
+            Ok(())
+        }
+
         /* fn đảm bảo amount_base_token khớp với amount_quote_token để đáp ứng được 1 giao dịch
             ví dụ: 1 BTC = 20 BUSD hoặc ngược lại
             ở đây sẽ check tương ứng với mức giá đặt ra thì sẽ tương ứng với bao nhiêu
@@ -1136,15 +2447,52 @@ pub mod pallet {
             }
         }
     
-        fn set_tp_market_data(tp_hash: T::Hash, price: T::Price, amount: Balance<T>) -> Result<(), Error<T>> {
+        // chuyển phí từ payer sang FeeCollector, bỏ qua nếu phí = 0 hoặc payer chính là FeeCollector
+        fn charge_fee(payer: T::AccountId, token_hash: T::Hash, fee: Balance<T>) -> DispatchResult {
+            let collector = T::FeeCollector::get();
+            if fee.is_zero() || payer == collector {
+                return Ok(());
+            }
+            pallet_tokens::Pallet::<T>::do_transfer(payer, collector, token_hash, fee)
+        }
+
+        // check lệnh tại mức giá price có khớp với PriceItem tốt nhất ở phía đối diện không
+        fn would_cross(tp_hash: T::Hash, otype: OrderType, price: T::Price) -> bool {
+            let end_item_price = match otype {
+                OrderType::Buy => Some(T::Price::max_value()),
+                OrderType::Sell => Some(T::Price::min_value()),
+            };
+
+            let head = <OrderList<T>>::read_head(tp_hash);
+            match Self::next_match_price(&head, !otype) {
+                Some(best_price) if Some(best_price) != end_item_price => Self::price_matched(price, otype, best_price),
+                _ => false,
+            }
+        }
+
+        fn set_tp_market_data(tp_hash: T::Hash, price: T::Price, base_amount: Balance<T>, amount: Balance<T>) -> Result<(), Error<T>> {
             // get trade_pair nếu nó tồn tại
             let mut tp = Self::trade_pairs(tp_hash).ok_or(<Error<T>>::NoMatchingTradePair)?;
 
+            // cộng dồn giá cũ trước khi update match_price
+            if tp.latest_matched_price != Some(price) {
+                Self::update_price_cumulative(tp_hash, price)?;
+            }
+
             // update match_price
             tp.latest_matched_price = Some(price);
 
             // get data_bucket và update thông tin
-            let (mut sum_volume, mut highest_price, mut lowest_pricce) = <TPTradeDataBucket<T>>::get((tp_hash, frame_system::Pallet::<T>::block_number())).unwrap_or((Default::default(), None, None));
+            let block_number = frame_system::Pallet::<T>::block_number();
+            let bucket = <TPTradeDataBucket<T>>::get((tp_hash, block_number));
+
+            // trade đầu tiên của cặp trong block này => hẹn trừ bucket này ra khỏi thống kê 24h sau BlocksPerDay block
+            if bucket.is_none() {
+                let days: T::BlockNumber = T::BlocksPerDay::get().into();
+                <OneDayStatsExpiry<T>>::insert(block_number + days, tp_hash, ());
+            }
+
+            let (mut sum_volume, mut highest_price, mut lowest_pricce) = bucket.unwrap_or((Default::default(), None, None));
             sum_volume += amount;
 
             match highest_price {
@@ -1169,11 +2517,660 @@ pub mod pallet {
                 }
             }
 
-            <TPTradeDataBucket<T>>::insert((tp_hash, frame_system::Pallet::<T>::block_number()), (sum_volume, highest_price, lowest_pricce));
+            <TPTradeDataBucket<T>>::insert((tp_hash, block_number), (sum_volume, highest_price, lowest_pricce));
+
+            // cập nhật thống kê 24h theo cửa sổ trượt
+            tp.one_day_trade_volume += amount;
+            // giá cao/thấp nhất: deque của bucket theo block, deque của cửa sổ theo bucket => không deque nào dài quá 1 tầng
+            let bucket_start = rolling_window::bucket_start(block_number, T::BlocksPerDay::get());
+            <TPBucketHighPrices<T>>::mutate(tp_hash, bucket_start, |deque| {
+                rolling_window::update(deque, |deque| rolling_window::push_max(deque, block_number, price))
+            });
+            <TPBucketLowPrices<T>>::mutate(tp_hash, bucket_start, |deque| {
+                rolling_window::update(deque, |deque| rolling_window::push_min(deque, block_number, price))
+            });
+            tp.one_day_highest_price = <TPHighPrices<T>>::mutate(tp_hash, |deque| {
+                rolling_window::update(deque, |deque| rolling_window::push_max(deque, bucket_start, price))
+            });
+            tp.one_day_lowest_price = <TPLowPrices<T>>::mutate(tp_hash, |deque| {
+                rolling_window::update(deque, |deque| rolling_window::push_min(deque, bucket_start, price))
+            });
             <TradePairs<T>>::insert(tp_hash, tp);
 
+            Self::update_candles(tp_hash, price, base_amount, amount);
+
+            // cặp tp này có stop order đang chờ => check trigger ở on_initialize block tiếp theo
+            if Self::has_pending_stop_orders(tp_hash) {
+                Self::schedule_stop_check(tp_hash);
+            }
+
             Ok(())
         }
-    
+
+        /* fn cộng dồn giá cho TWAP, gọi khi latest_matched_price thay đổi
+            + block mới => cumulative += giá gần nhất * số block từ lần cập nhật trước, lưu observation nếu đã cách observation trước >= PriceObservationPeriod
+            + cùng block => chỉ đổi giá gần nhất, giá này được tính từ block sau
+            => giá bị đẩy lên/xuống trong 1 block chỉ ảnh hưởng TWAP theo tỉ lệ số block nó tồn tại
+        */
+        fn update_price_cumulative(tp_hash: T::Hash, price: T::Price) -> Result<(), Error<T>> {
+            let block_number = frame_system::Pallet::<T>::block_number();
+
+            let (mut cumulative, last_block, last_price) = match Self::price_cumulative(tp_hash) {
+                Some(data) => data,
+                None => {
+                    // trade đầu tiên của cặp => bắt đầu cộng dồn từ block này
+                    <PriceCumulatives<T>>::insert(tp_hash, (0u128, block_number, price));
+                    let _ = <PriceObservations<T>>::try_append(tp_hash, (block_number, 0u128));
+                    return Ok(())
+                }
+            };
+
+            if block_number > last_block {
+                let elapsed = Self::into_128(block_number - last_block)?;
+                cumulative = cumulative.wrapping_add(Self::into_128(last_price)?.wrapping_mul(elapsed));
+
+                let period: T::BlockNumber = T::PriceObservationPeriod::get().into();
+                <PriceObservations<T>>::mutate(tp_hash, |observations| {
+                    let due = observations.last().map_or(true, |(block, _)| block_number >= block.saturating_add(period));
+                    if due {
+                        // đủ MaxPriceObservations phần tử => bỏ observation cũ nhất
+                        if observations.len() as u32 >= T::MaxPriceObservations::get() && !observations.is_empty() {
+                            observations.remove(0);
+                        }
+                        let _ = observations.try_push((block_number, cumulative));
+                    }
+                });
+            }
+
+            <PriceCumulatives<T>>::insert(tp_hash, (cumulative, block_number, price));
+            Ok(())
+        }
+
+        /* fn bỏ block (block_number - BlocksPerDay) ra khỏi thống kê 24h, chỉ với các cặp có trade ở block đó
+            + trừ volume của bucket tại block đó và xóa luôn bucket
+            + bỏ block đó ở deque của bucket chứa nó (bucket hết block thì xóa), sửa phần tử đầu của deque cửa sổ theo giá
+              còn lại của bucket => giá cao nhất/thấp nhất 24h là phần tử đầu của deque cửa sổ, giống hệt khi tính theo từng block
+        */
+        fn expire_one_day_stats(block_number: T::BlockNumber) -> Weight {
+            let days: T::BlockNumber = T::BlocksPerDay::get().into();
+            if block_number <= days {
+                return Weight::zero()
+            }
+
+            let expired: Vec<T::Hash> = <OneDayStatsExpiry<T>>::drain_prefix(block_number).map(|(tp_hash, _)| tp_hash).collect();
+            let cutoff = block_number - days;
+
+            for tp_hash in expired.iter() {
+                let mut tp = match Self::trade_pairs(tp_hash) {
+                    Some(tp) => tp,
+                    None => continue,
+                };
+
+                let (sum_volume, _, _) = <TPTradeDataBucket<T>>::take((*tp_hash, cutoff)).unwrap_or((Default::default(), None, None));
+                tp.one_day_trade_volume = tp.one_day_trade_volume.saturating_sub(sum_volume);
+
+                let bucket_start = rolling_window::bucket_start(cutoff, T::BlocksPerDay::get());
+                let remaining_high = <TPBucketHighPrices<T>>::mutate_exists(tp_hash, bucket_start, |deque| {
+                    Self::expire_bucket_prices(deque, cutoff)
+                });
+                let remaining_low = <TPBucketLowPrices<T>>::mutate_exists(tp_hash, bucket_start, |deque| {
+                    Self::expire_bucket_prices(deque, cutoff)
+                });
+
+                tp.one_day_highest_price = <TPHighPrices<T>>::mutate(tp_hash, |deque| {
+                    rolling_window::update(deque, |deque| {
+                        rolling_window::shrink_front(deque, bucket_start, remaining_high, |price, next| price <= next)
+                    })
+                });
+                tp.one_day_lowest_price = <TPLowPrices<T>>::mutate(tp_hash, |deque| {
+                    rolling_window::update(deque, |deque| {
+                        rolling_window::shrink_front(deque, bucket_start, remaining_low, |price, next| price >= next)
+                    })
+                });
+
+                <TradePairs<T>>::insert(tp_hash, tp);
+            }
+
+            // 1 read cho OneDayStatsExpiry, mỗi cặp đọc/xóa 1 phần tử OneDayStatsExpiry, đọc tp, bucket, 4 deque và ghi lại cả 6
+            T::DbWeight::get().reads(1)
+                .saturating_add(T::DbWeight::get().reads_writes(7, 7).saturating_mul(expired.len() as u64))
+        }
+
+        // bỏ các block <= cutoff ở deque của 1 bucket, xóa deque nếu bucket hết block, trả về giá max/min còn lại của bucket
+        fn expire_bucket_prices<S: Get<u32>>(deque: &mut Option<BoundedVec<(T::BlockNumber, T::Price), S>>, cutoff: T::BlockNumber) -> Option<T::Price> {
+            let remaining = deque.as_mut().and_then(|deque| {
+                rolling_window::update(deque, |deque| { rolling_window::expire(deque, cutoff); })
+            });
+            if remaining.is_none() {
+                *deque = None;
+            }
+            remaining
+        }
+
+        /* fn cập nhật nến của cặp tp tại mọi interval trong CandleIntervals sau mỗi trade
+            Nến mới được tạo khi block hiện tại sang khoảng interval mới, khi đó nếu số nến vượt CandleRetention thì xóa nến cũ nhất
+        */
+        fn update_candles(tp_hash: T::Hash, price: T::Price, base_amount: Balance<T>, quote_amount: Balance<T>) {
+            let block_number = frame_system::Pallet::<T>::block_number();
+
+            for interval in T::CandleIntervals::get() {
+                Self::update_candle(tp_hash, interval, block_number, price, base_amount, quote_amount);
+            }
+        }
+
+        // cập nhật nến của cặp tp tại 1 interval, weight là WeightInfo::update_candle
+        pub(crate) fn update_candle(tp_hash: T::Hash, interval: u32, block_number: T::BlockNumber, price: T::Price, base_amount: Balance<T>, quote_amount: Balance<T>) {
+            if interval == 0 {
+                return;
+            }
+            let interval_blocks: T::BlockNumber = interval.into();
+            let start = block_number - block_number % interval_blocks;
+
+            match <Candles<T>>::get((tp_hash, interval), start) {
+                Some(mut candle) => {
+                    if price > candle.high {
+                        candle.high = price;
+                    }
+                    if price < candle.low {
+                        candle.low = price;
+                    }
+                    candle.close = price;
+                    candle.base_volume += base_amount;
+                    candle.quote_volume += quote_amount;
+                    candle.trades = candle.trades.saturating_add(1);
+                    <Candles<T>>::insert((tp_hash, interval), start, candle);
+                },
+                None => {
+                    let candle = Candle {
+                        start,
+                        open: price,
+                        high: price,
+                        low: price,
+                        close: price,
+                        base_volume: base_amount,
+                        quote_volume: quote_amount,
+                        trades: 1,
+                    };
+                    <Candles<T>>::insert((tp_hash, interval), start, candle);
+
+                    // prune theo retention: đủ CandleRetention nến thì xóa nến cũ nhất, retention = 0 thì không giữ nến nào
+                    <CandleStarts<T>>::mutate((tp_hash, interval), |starts| {
+                        if starts.len() as u32 >= T::CandleRetention::get() && !starts.is_empty() {
+                            let oldest = starts.remove(0);
+                            <Candles<T>>::remove((tp_hash, interval), oldest);
+                        }
+                        if starts.try_push(start).is_err() {
+                            <Candles<T>>::remove((tp_hash, interval), start);
+                        }
+                    });
+                },
+            }
+        }
+
+        fn do_create_stop_order(sender: T::AccountId, base: T::Hash, quote: T::Hash, oopt: OrderOpt, otype: OrderType, trigger_price: T::Price, price: T::Price, sell_amount: Balance<T>) -> DispatchResult {
+            ensure!(trigger_price > Zero::zero(), <Error<T>>::BoundsCheckFailedPrice);
+            if oopt == OrderOpt::Limit {
+                Self::ensure_trade_rules(base, quote, otype, price, sell_amount)?;
+            } else {
+                ensure!(sell_amount > Zero::zero(), <Error<T>>::BoundsCheckFailedAmount);
+                let tp_hash = Self::ensure_trade_pair(base, quote)?;
+                let tp = Self::trade_pairs(tp_hash).ok_or(<Error<T>>::NoMatchingTradePair)?;
+                match otype {
+                    OrderType::Buy => ensure!(sell_amount >= tp.min_notional, <Error<T>>::BelowMinNotional),
+                    OrderType::Sell => ensure!((sell_amount % tp.lot_size).is_zero(), <Error<T>>::AmountNotMultipleOfLotSize),
+                }
+            }
+
+            let tp_hash = Self::ensure_trade_pair(base, quote)?;
+            Self::ensure_trade_pair_active(tp_hash)?;
+
+            // tạo random hash cho stop order
+            let nonce = Self::nonce();
+            let random = T::TradeRandom::random_seed().0;
+            let hash = (random, frame_system::Pallet::<T>::block_number(), sender.clone(), tp_hash, oopt, otype, trigger_price, price, sell_amount, nonce).using_encoded(T::Hashing::hash);
+            let new_nonce = nonce.checked_add(1).ok_or(ArithmeticError::Overflow)?;
+            <Nonce<T>>::put(new_nonce);
+
+            let stop_order = StopOrder { hash, base, quote, owner: sender.clone(), trigger_price, price, sell_amount, oopt, otype, status: StopOrderStatus::Pending };
+
+            // freeze lượng bán ngay khi tạo => stop order phải có đủ số dư và không thể spam lệnh không có tiền
+            pallet_tokens::Pallet::<T>::ensure_free_balance(sender.clone(), stop_order.sell_hash(), sell_amount)?;
+            pallet_tokens::Pallet::<T>::do_freeze(sender.clone(), stop_order.sell_hash(), sell_amount)?;
+            <StopOrders<T>>::insert(hash, stop_order.clone());
+
+            // thêm vào ds chờ tại trigger_price, giữ ds trigger_price luôn tăng dần
+            <TPStopOrders<T>>::try_append((tp_hash, otype), trigger_price, hash).map_err(|_| <Error<T>>::TooManyStopOrders)?;
+            <TPStopTriggerPrices<T>>::try_mutate((tp_hash, otype), |prices| -> DispatchResult {
+                if let Err(index) = prices.binary_search(&trigger_price) {
+                    prices.try_insert(index, trigger_price).map_err(|_| <Error<T>>::TooManyStopTriggerPrices)?;
+                }
+                Ok(())
+            })?;
+
+            // giá hiện tại có thể đã chạm trigger => check luôn ở block tiếp theo
+            Self::schedule_stop_check(tp_hash);
+
+            Self::deposit_event(Event::StopOrderCreated {
+                owner: sender,
+                stop_order_hash: hash,
+                stop_order
+            });
+
+            Ok(())
+        }
+
+        fn do_cancel_stop_order(sender: T::AccountId, stop_order_hash: T::Hash) -> DispatchResult {
+            let mut stop_order = Self::stop_orders(stop_order_hash).ok_or(<Error<T>>::NoMatchingStopOrder)?;
+            ensure!(stop_order.owner == sender, <Error<T>>::CanOnlyCancelOwnStopOrder);
+
+            match stop_order.status {
+                StopOrderStatus::Pending => {
+                    let tp_hash = Self::ensure_trade_pair(stop_order.base, stop_order.quote)?;
+                    Self::remove_pending_stop_order(tp_hash, &stop_order);
+                },
+                StopOrderStatus::Triggered => {
+                    <TriggeredStopOrders<T>>::mutate(|queue| queue.retain(|&h| h != stop_order_hash));
+                },
+                _ => return Err(<Error<T>>::StopOrderNotCancelable.into()),
+            }
+
+            pallet_tokens::Pallet::<T>::do_unfreeze(sender.clone(), stop_order.sell_hash(), stop_order.sell_amount)?;
+            stop_order.status = StopOrderStatus::Canceled;
+            <StopOrders<T>>::insert(stop_order_hash, stop_order);
+
+            Self::deposit_event(Event::StopOrderCanceled {
+                owner: sender,
+                stop_order_hash
+            });
+
+            Ok(())
+        }
+
+        // gỡ stop order khỏi ds chờ tại trigger_price, xóa luôn trigger_price nếu không còn lệnh nào
+        fn remove_pending_stop_order(tp_hash: T::Hash, stop_order: &StopOrder<T>) {
+            let key = (tp_hash, stop_order.otype);
+            let mut hashes = <TPStopOrders<T>>::get(key, stop_order.trigger_price);
+            hashes.retain(|&h| h != stop_order.hash);
+
+            if hashes.is_empty() {
+                <TPStopOrders<T>>::remove(key, stop_order.trigger_price);
+                <TPStopTriggerPrices<T>>::mutate(key, |prices| prices.retain(|&p| p != stop_order.trigger_price));
+            } else {
+                <TPStopOrders<T>>::insert(key, stop_order.trigger_price, hashes);
+            }
+        }
+
+        fn has_pending_stop_orders(tp_hash: T::Hash) -> bool {
+            <TPStopTriggerPrices<T>>::decode_len((tp_hash, OrderType::Buy)).unwrap_or(0) > 0 ||
+            <TPStopTriggerPrices<T>>::decode_len((tp_hash, OrderType::Sell)).unwrap_or(0) > 0
+        }
+
+        fn schedule_stop_check(tp_hash: T::Hash) {
+            <StopCheckPairs<T>>::insert(tp_hash, ());
+        }
+
+        /* fn chạy trong on_initialize, tối đa MaxStopOrdersPerBlock stop order được trigger và tối đa MaxStopOrdersPerBlock được kích hoạt mỗi block
+            + B1: với các cặp tp vừa có giao dịch, chuyển các stop order đã chạm trigger vào hàng đợi TriggeredStopOrders
+            + B2: lấy các stop order đầu hàng đợi ra và tạo order market/limit tương ứng
+        */
+        fn process_stop_orders() -> Weight {
+            let db_weight = T::DbWeight::get();
+            let mut weight = db_weight.reads_writes(2, 2);
+
+            // B1: trigger, budget không vượt quá chỗ trống của hàng đợi TriggeredStopOrders
+            let queued = <TriggeredStopOrders<T>>::decode_len().unwrap_or(0) as u32;
+            let mut budget = T::MaxStopOrdersPerBlock::get().saturating_sub(queued);
+            let pairs: Vec<T::Hash> = <StopCheckPairs<T>>::iter_keys().collect();
+            for tp_hash in pairs {
+                let last_price = match Self::trade_pairs(tp_hash).and_then(|tp| tp.latest_matched_price) {
+                    Some(price) => price,
+                    None => {
+                        <StopCheckPairs<T>>::remove(tp_hash);
+                        continue
+                    },
+                };
+
+                let mut done = true;
+                for otype in [OrderType::Buy, OrderType::Sell] {
+                    let (triggered, side_done) = Self::trigger_stop_orders(tp_hash, otype, last_price, &mut budget);
+                    weight = weight.saturating_add(db_weight.reads_writes(2, 2).saturating_add(db_weight.reads_writes(1, 1).saturating_mul(triggered as u64)));
+                    done = done && side_done;
+                }
+
+                // hết budget mà vẫn còn trigger_price bị chạm => giữ lại để block sau xử lý tiếp
+                if done {
+                    <StopCheckPairs<T>>::remove(tp_hash);
+                }
+            }
+
+            // B2: kích hoạt
+            let mut queue = <TriggeredStopOrders<T>>::take().into_inner();
+            let count = queue.len().min(T::MaxStopOrdersPerBlock::get() as usize);
+            let activating: Vec<T::Hash> = queue.drain(..count).collect();
+            <TriggeredStopOrders<T>>::put(BoundedVec::<T::Hash, T::MaxStopOrdersPerBlock>::try_from(queue).unwrap_or_default());
+
+            for stop_order_hash in activating {
+                let mut stop_order = match Self::stop_orders(stop_order_hash) {
+                    Some(stop_order) if stop_order.status == StopOrderStatus::Triggered => stop_order,
+                    _ => continue,
+                };
+
+                // unfreeze lượng đã freeze khi tạo stop order, order mới tự freeze lại theo lượng thực tế
+                let result = with_storage_layer(|| -> Result<MatchStats, DispatchError> {
+                    pallet_tokens::Pallet::<T>::do_unfreeze(stop_order.owner.clone(), stop_order.sell_hash(), stop_order.sell_amount)?;
+                    match stop_order.oopt {
+                        OrderOpt::Limit => Self::do_create_limit_order(stop_order.owner.clone(), stop_order.base, stop_order.quote, stop_order.otype, stop_order.price, stop_order.sell_amount, TimeInForce::GoodTilCanceled, false, None),
+                        OrderOpt::Market => Self::do_create_market_order(stop_order.owner.clone(), stop_order.base, stop_order.quote, stop_order.otype, stop_order.market_amount(), None),
+                    }
+                });
+
+                // 1 lần kích hoạt tương đương 1 lần đặt lệnh, lỗi thì không biết đã khớp bao nhiêu => tính weight tối đa
+                let order_weight = match &result {
+                    Ok(stats) => Self::create_order_weight(*stats),
+                    Err(_) => Self::max_create_order_weight(),
+                };
+
+                match result {
+                    Ok(_) => {
+                        stop_order.status = StopOrderStatus::Activated;
+                        Self::deposit_event(Event::StopOrderActivated { owner: stop_order.owner.clone(), stop_order_hash });
+                    },
+                    Err(error) => {
+                        // thay đổi trong storage layer đã bị rollback => lượng bán vẫn đang bị freeze
+                        let _ = pallet_tokens::Pallet::<T>::do_unfreeze(stop_order.owner.clone(), stop_order.sell_hash(), stop_order.sell_amount);
+                        stop_order.status = StopOrderStatus::Failed;
+                        Self::deposit_event(Event::StopOrderActivationFailed { owner: stop_order.owner.clone(), stop_order_hash, error });
+                    }
+                }
+                <StopOrders<T>>::insert(stop_order_hash, stop_order);
+
+                weight = weight.saturating_add(order_weight).saturating_add(db_weight.reads_writes(1, 1));
+            }
+
+            weight
+        }
+
+        /* chuyển các stop order 1 phía của cặp tp đã chạm trigger vào hàng đợi
+            + Buy  => duyệt trigger_price từ thấp lên cao, dừng khi trigger_price > last_price
+            + Sell => duyệt trigger_price từ cao xuống thấp, dừng khi trigger_price < last_price
+            trả về (số stop order đã trigger, đã xử lý hết các trigger_price bị chạm hay chưa)
+        */
+        fn trigger_stop_orders(tp_hash: T::Hash, otype: OrderType, last_price: T::Price, budget: &mut u32) -> (u32, bool) {
+            let key = (tp_hash, otype);
+            let mut prices = <TPStopTriggerPrices<T>>::get(key);
+            let mut triggered: u32 = 0;
+            let mut done = true;
+
+            loop {
+                let trigger_price = match otype {
+                    OrderType::Buy => prices.first().copied(),
+                    OrderType::Sell => prices.last().copied(),
+                };
+                let trigger_price = match trigger_price {
+                    Some(price) if StopOrder::<T>::is_triggered_by(otype, price, last_price) => price,
+                    _ => break,
+                };
+
+                if *budget == 0 {
+                    done = false;
+                    break;
+                }
+
+                let mut hashes = <TPStopOrders<T>>::get(key, trigger_price).into_inner();
+                let count = hashes.len().min(*budget as usize);
+                for stop_order_hash in hashes.drain(..count) {
+                    <StopOrders<T>>::mutate(stop_order_hash, |stop_order| {
+                        if let Some(stop_order) = stop_order {
+                            stop_order.status = StopOrderStatus::Triggered;
+                            Self::deposit_event(Event::StopOrderTriggered { owner: stop_order.owner.clone(), stop_order_hash, last_price });
+                        }
+                    });
+                    // budget đã tính theo chỗ trống của hàng đợi nên luôn append được
+                    let _ = <TriggeredStopOrders<T>>::try_append(stop_order_hash);
+                }
+                *budget -= count as u32;
+                triggered += count as u32;
+
+                if hashes.is_empty() {
+                    <TPStopOrders<T>>::remove(key, trigger_price);
+                    prices.retain(|&p| p != trigger_price);
+                } else {
+                    <TPStopOrders<T>>::insert(key, trigger_price, BoundedVec::<T::Hash, T::MaxStopOrdersPerPrice>::try_from(hashes).unwrap_or_default());
+                    done = false;
+                    break;
+                }
+            }
+
+            <TPStopTriggerPrices<T>>::insert(key, prices);
+            (triggered, done)
+        }
+
+        // fn dùng cho runtime api: đọc order book của 1 cặp tp, bắt đầu từ head đi ra 2 phía (giá tốt nhất trước)
+        pub fn order_book(tp_hash: T::Hash, depth: u32) -> OrderBook<T::Price, Balance<T>> {
+            let mut book = OrderBook { bids: Vec::new(), asks: Vec::new() };
+
+            // chưa có list giá => cặp tp chưa có order nào, không khởi tạo list ở đây vì đây chỉ là fn đọc
+            let head = match <LinkedItemList<T>>::get(tp_hash, None::<T::Price>) {
+                Some(head) => head,
+                None => return book,
+            };
+
+            book.bids = Self::collect_levels(tp_hash, &head, OrderType::Buy, depth);
+            book.asks = Self::collect_levels(tp_hash, &head, OrderType::Sell, depth);
+            book
+        }
+
+        // fn dùng cho runtime api: lấy tối đa limit nến mới nhất của cặp tp tại interval, cũ trước mới sau
+        pub fn candles(tp_hash: T::Hash, interval: u32, limit: u32) -> Vec<CandleOf<T>> {
+            let starts = <CandleStarts<T>>::get((tp_hash, interval));
+            let skip = starts.len().saturating_sub(limit as usize);
+            starts.iter()
+                .skip(skip)
+                .filter_map(|start| <Candles<T>>::get((tp_hash, interval), start))
+                .collect()
+        }
+
+        // duyệt từ head về bottom (Buy) hoặc về top (Sell), lấy tối đa depth mức giá
+        fn collect_levels(tp_hash: T::Hash, head: &OrderItem<T>, otype: OrderType, depth: u32) -> Vec<OrderBookLevel<T::Price, Balance<T>>> {
+            let end_item_price = match otype {
+                OrderType::Buy => Some(T::Price::min_value()),
+                OrderType::Sell => Some(T::Price::max_value()),
+            };
+
+            let mut levels = Vec::new();
+            let mut item_price = Self::next_match_price(head, otype);
+            while item_price != end_item_price && (levels.len() as u32) < depth {
+                let item = match <LinkedItemList<T>>::get(tp_hash, item_price) {
+                    Some(item) => item,
+                    None => break,
+                };
+                if let Some(price) = item.price {
+                    levels.push(OrderBookLevel {
+                        price,
+                        buy_amount: item.buy_amount,
+                        sell_amount: item.sell_amount,
+                        orders: item.orders.len() as u32,
+                    });
+                }
+                item_price = Self::next_match_price(&item, otype);
+            }
+            levels
+        }
+
+        /* fn tính TWAP của cặp tp trong window block gần nhất
+            + window nằm trọn sau lần cập nhật giá cuối => giá không đổi trong cả window => trả về giá gần nhất
+            + ngược lại lấy observation mới nhất có block <= block hiện tại - window. Observation chỉ được lưu khi có trade làm đổi giá
+              nên với cặp ít trade cửa sổ thực tế có thể dài hơn window bao nhiêu block cũng được, TWAP khi đó là trung bình trên cả cửa sổ thực tế
+            Trả về None nếu cặp chưa có trade hoặc lịch sử observation chưa đủ dài
+        */
+        pub fn twap(tp_hash: T::Hash, window: T::BlockNumber) -> Option<T::Price> {
+            let (cumulative, last_block, last_price) = Self::price_cumulative(tp_hash)?;
+            let block_number = frame_system::Pallet::<T>::block_number();
+
+            // cộng dồn đến block hiện tại với giá gần nhất
+            let elapsed: u128 = (block_number - last_block).try_into().ok()?;
+            let cumulative_now = cumulative.wrapping_add(Into::<u128>::into(last_price).wrapping_mul(elapsed));
+
+            if window.is_zero() || block_number < window {
+                return None
+            }
+            let target = block_number - window;
+            if target >= last_block {
+                return Some(last_price)
+            }
+            let (start_block, start_cumulative) = Self::price_observations(tp_hash).into_iter()
+                .rev()
+                .find(|(block, _)| *block <= target)?;
+
+            let span: u128 = (block_number - start_block).try_into().ok()?;
+            if span == 0 {
+                return None
+            }
+            Some(T::Price::from(cumulative_now.wrapping_sub(start_cumulative) / span))
+        }
+    }
+
+    impl<T: Config> Pallet<T> {
+        /* fn check bất biến của sổ lệnh, dùng cho try-runtime và test
+            + LinkedItemList của mỗi cặp: duyệt từ bottom theo next đến top thì prev của node sau phải trỏ về node trước,
+              giá tăng dần, đi qua head đúng 1 lần và đi hết mọi node của cặp, top.next và bottom.prev nối vòng với nhau
+            + mọi order trong PriceItem đều chưa finish, đúng giá, đúng phía (trước head là Buy, sau head là Sell)
+              và buy_amount/sell_amount của PriceItem bằng tổng phần còn lại của các order
+            + số dư bị đóng băng của mỗi account >= tổng lượng bán còn lại của các order của account đó trên sổ lệnh
+              cộng lượng bán của các stop order chưa kích hoạt
+            + OrdersByExpiry chỉ chứa order chưa finish và hết hạn đúng block của key
+            + OwnedOpenedTradePairs khớp với các ds OwnedTPOpenedOrders khác rỗng
+        */
+        #[cfg(any(feature = "try-runtime", test))]
+        pub fn do_try_state() -> Result<(), &'static str> {
+            use frame_support::sp_std::collections::btree_map::BTreeMap;
+
+            let bottom_price = Some(T::Price::min_value());
+            let top_price = Some(T::Price::max_value());
+            let mut required_freezed: BTreeMap<(T::AccountId, T::Hash), Balance<T>> = BTreeMap::new();
+
+            for (tp_hash, tp) in <TradePairs<T>>::iter() {
+                // chưa có list giá => cặp chưa có order nào
+                let total_items = <LinkedItemList<T>>::iter_prefix(tp_hash).count();
+                if total_items == 0 {
+                    continue;
+                }
+
+                let mut price = bottom_price;
+                let mut last_price: Option<T::Price> = None;
+                let mut passed_head = false;
+                let mut visited = 0;
+
+                loop {
+                    let item = <LinkedItemList<T>>::get(tp_hash, price).ok_or("PriceItem in the price list does not exist")?;
+                    ensure!(item.price == price, "PriceItem price does not match its key");
+
+                    visited += 1;
+                    ensure!(visited <= total_items, "Price list has a cycle");
+
+                    match price {
+                        None => {
+                            ensure!(!passed_head, "Price list passes head twice");
+                            passed_head = true;
+                        },
+                        Some(p) => {
+                            ensure!(last_price.map_or(true, |last| p > last), "Prices are not strictly increasing from bottom to top");
+                            last_price = Some(p);
+                        }
+                    }
+
+                    if price.is_none() || price == bottom_price || price == top_price {
+                        ensure!(item.orders.is_empty() && item.sell_amount.is_zero() && item.buy_amount.is_zero(), "Fixed PriceItem holds orders");
+                    } else {
+                        let side = if passed_head { OrderType::Sell } else { OrderType::Buy };
+                        let mut sell_amount: Balance<T> = Zero::zero();
+                        let mut buy_amount: Balance<T> = Zero::zero();
+
+                        for order_hash in item.orders.iter() {
+                            let order = Self::orders(order_hash).ok_or("Order in PriceItem does not exist")?;
+                            ensure!(!order.is_finished(), "Finished order in PriceItem");
+                            ensure!(Some(order.price) == price && order.otype == side, "Order is at the wrong price or side of the price list");
+
+                            sell_amount += order.remained_sell_amount;
+                            buy_amount += order.remained_buy_amount;
+
+                            let sell_hash = match order.otype {
+                                OrderType::Buy => tp.base,
+                                OrderType::Sell => tp.quote,
+                            };
+                            let required = required_freezed.entry((order.owner, sell_hash)).or_insert_with(Zero::zero);
+                            *required += order.remained_sell_amount;
+                        }
+
+                        ensure!(item.sell_amount == sell_amount && item.buy_amount == buy_amount, "PriceItem amounts do not match its orders");
+                    }
+
+                    if price == top_price {
+                        ensure!(item.next == bottom_price, "Top is not linked to bottom");
+                        break;
+                    }
+
+                    // node sau phải trỏ ngược về node hiện tại
+                    let next = <LinkedItemList<T>>::get(tp_hash, item.next).ok_or("Next PriceItem does not exist")?;
+                    ensure!(next.prev == price, "PriceItem links are not bidirectional");
+                    price = item.next;
+                }
+
+                ensure!(passed_head, "Price list has no head");
+                ensure!(visited == total_items, "PriceItem is not reachable from bottom");
+
+                let bottom = <LinkedItemList<T>>::get(tp_hash, bottom_price).ok_or("Bottom PriceItem does not exist")?;
+                ensure!(bottom.prev == top_price, "Bottom is not linked to top");
+            }
+
+            // OwnedOpenedTradePairs chứa đúng các cặp mà account còn order mở
+            for ((account, tp_hash), orders) in <OwnedTPOpenedOrders<T>>::iter() {
+                ensure!(orders.is_empty() != <OwnedOpenedTradePairs<T>>::contains_key(account, tp_hash), "OwnedOpenedTradePairs does not match OwnedTPOpenedOrders");
+            }
+            for (account, tp_hash, _) in <OwnedOpenedTradePairs<T>>::iter() {
+                ensure!(<OwnedTPOpenedOrders<T>>::contains_key((account, tp_hash)), "OwnedOpenedTradePairs has a pair without opened orders");
+            }
+
+            // order trong OrdersByExpiry phải còn mở và hết hạn đúng block đó
+            for (expiry, hashes) in <OrdersByExpiry<T>>::iter() {
+                for order_hash in hashes.iter() {
+                    let order = Self::orders(order_hash).ok_or("Order in OrdersByExpiry does not exist")?;
+                    ensure!(!order.is_finished() && order.expires_at == Some(expiry), "Stale order in OrdersByExpiry");
+                }
+            }
+
+            // stop order đang chờ/đã trigger giữ lượng bán bị freeze đến khi kích hoạt
+            for stop_order in <StopOrders<T>>::iter_values() {
+                if matches!(stop_order.status, StopOrderStatus::Pending | StopOrderStatus::Triggered) {
+                    let required = required_freezed.entry((stop_order.owner.clone(), stop_order.sell_hash())).or_insert_with(Zero::zero);
+                    *required += stop_order.sell_amount;
+                }
+            }
+
+            for ((owner, token_hash), required) in required_freezed.into_iter() {
+                let freezed_balance = pallet_tokens::Pallet::<T>::freezed_balance_of(owner, token_hash);
+                ensure!(freezed_balance >= required, "Freezed balance does not cover open orders");
+            }
+
+            Ok(())
+        }
+    }
+
+    // Giá chống thao túng của 1 cặp trade_pair cho các pallet khác trong runtime (lending, collateral, ...)
+    pub trait PriceOracle<Hash, BlockNumber, Price> {
+        // giá trung bình theo thời gian trong window block gần nhất
+        fn twap(tp_hash: Hash, window: BlockNumber) -> Option<Price>;
+        // giá khớp gần nhất
+        fn latest_price(tp_hash: Hash) -> Option<Price>;
+    }
+
+    impl<T: Config> PriceOracle<T::Hash, T::BlockNumber, T::Price> for Pallet<T> {
+        fn twap(tp_hash: T::Hash, window: T::BlockNumber) -> Option<T::Price> {
+            Self::twap(tp_hash, window)
+        }
+
+        fn latest_price(tp_hash: T::Hash) -> Option<T::Price> {
+            Self::trade_pairs(tp_hash).and_then(|tp| tp.latest_matched_price)
+        }
     }
 }
\ No newline at end of file
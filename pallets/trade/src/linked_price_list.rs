@@ -1,30 +1,33 @@
-use codec::{Encode, Decode, EncodeLike};
+use codec::{Encode, Decode, EncodeLike, MaxEncodedLen};
 use frame_support::{
     pallet_prelude::*,
     sp_runtime::{
         traits::{AtLeast32Bit, Bounded}
     },
     sp_std::{
+        fmt::Debug,
         marker::PhantomData,
         borrow::Borrow
     },
-    inherent::Vec,
-    StorageDoubleMap, RuntimeDebug, Parameter, 
+    StorageDoubleMap, Parameter, CloneNoBound, PartialEqNoBound, EqNoBound, RuntimeDebugNoBound,
 };
 use scale_info::TypeInfo;
 pub use crate as trade;
 type OrderType = trade::OrderType;
 
 
-#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+#[derive(Encode, Decode, CloneNoBound, PartialEqNoBound, EqNoBound, RuntimeDebugNoBound, TypeInfo, MaxEncodedLen)]
+#[scale_info(skip_type_params(M))]
+#[codec(mel_bound(P1: MaxEncodedLen, P2: MaxEncodedLen, P3: MaxEncodedLen))]
 /* Ở đây dùng 3 generic type là P1, P2, P3.
  - P1: là order_hash hoặc token_hash => kiểu hash
  - P2: là mức giá => kiểu price
  - P3: là amount => kiểu balance
+ - M: số order tối đa tại 1 mức giá
  */
 
 // struct PriceItem => nó là các node trong linked_list chứa thông tin của 1 mức giá cụ thể
-pub struct PriceItem<P1, P2, P3>{
+pub struct PriceItem<P1: Clone + Eq + Debug, P2: Clone + Eq + Debug, P3: Clone + Eq + Debug, M: Get<u32>>{
     pub prev: Option<P2>,       // mức giá của item prev
     pub next: Option<P2>,       // mức giá của item next
 
@@ -32,9 +35,11 @@ pub struct PriceItem<P1, P2, P3>{
     pub price: Option<P2>,      // mức giá hiện tại của item
     pub buy_amount: P3,         // tổng lượng mua
     pub sell_amount: P3,        // tổng lượng bán
-    pub orders: Vec<P1>         // list các order_hash tại mức giá của item
+    pub orders: BoundedVec<P1, M>   // list các order_hash tại mức giá của item
 }
 
+type Item<T, P1, P2, P3> = PriceItem<P1, P2, P3, <T as trade::Config>::MaxOrdersPerPriceLevel>;
+
 // struct PriceList => chính là linked_list, mỗi token sẽ có 1 linked_list để quản lý list giá
 pub struct PriceList<T, S, P1, P2, P3>(PhantomData<(T, S, P1, P2, P3)>);
  
@@ -43,9 +48,9 @@ where
     // config của mod trade
     T: trade::Config,
     // Mapping token_hash => mức giá => price_item tại mức giá đó
-    S: StorageDoubleMap<P1, Option<P2>, PriceItem<P1, P2, P3>, Query = Option<PriceItem<P1, P2, P3>>>,
+    S: StorageDoubleMap<P1, Option<P2>, Item<T, P1, P2, P3>, Query = Option<Item<T, P1, P2, P3>>>,
     // P1, P2, P3 tương tự như struct PriceItem
-    P1: EncodeLike + Encode + Decode + Clone + Copy + PartialEq + Borrow<<T as frame_system::Config>::Hash>,
+    P1: EncodeLike + Encode + Decode + Clone + Copy + PartialEq + Eq + Debug + Borrow<<T as frame_system::Config>::Hash>,
     P2: Parameter + Default + AtLeast32Bit + Bounded + Copy + EncodeLike + Encode + Decode,
     P3: Parameter + Default + AtLeast32Bit + Bounded + Copy
 {
@@ -79,28 +84,28 @@ price_list:  |  BOTTOM:          |            |  PriceItem:        |
     */ 
 
     // Đọc node head
-    pub fn read_head(thash: P1) -> PriceItem<P1, P2, P3>{
+    pub fn read_head(thash: P1) -> Item<T, P1, P2, P3>{
         Self::read(thash, None)
     }
 
     #[allow(dead_code)]
-    pub fn read_bottom(thash: P1) -> PriceItem<P1, P2, P3> {
+    pub fn read_bottom(thash: P1) -> Item<T, P1, P2, P3> {
         Self::read(thash, Some(P2::min_value()))
     }
 
     #[allow(dead_code)]
-    pub fn read_top(thash: P1) -> PriceItem<P1, P2, P3> {
+    pub fn read_top(thash: P1) -> Item<T, P1, P2, P3> {
         Self::read(thash, Some(P2::max_value()))
     }
 
     // Get dữ liệu từ node, nếu chưa có thì khởi tạo 1 circular_linked_list mới rồi trả về node head
-    pub fn read(thash: P1, price: Option<P2>) -> PriceItem<P1, P2, P3> {
+    pub fn read(thash: P1, price: Option<P2>) -> Item<T, P1, P2, P3> {
         S::get(thash, price).unwrap_or_else(|| {
             let bottom = PriceItem {
                 prev: Some(P2::max_value()),
                 next: None,
                 price: Some(P2::min_value()),
-                orders: Vec::<P1>::new(),
+                orders: BoundedVec::default(),
                 buy_amount: Default::default(),
                 sell_amount: Default::default(),
             };
@@ -109,7 +114,7 @@ price_list:  |  BOTTOM:          |            |  PriceItem:        |
                 prev: None,
                 next: Some(P2::min_value()),
                 price: Some(P2::max_value()),
-                orders: Vec::<P1>::new(),
+                orders: BoundedVec::default(),
                 buy_amount: Default::default(),
                 sell_amount: Default::default(),
             };
@@ -118,7 +123,7 @@ price_list:  |  BOTTOM:          |            |  PriceItem:        |
                 prev: Some(P2::min_value()),
                 next: Some(P2::max_value()),
                 price: None,
-                orders: Vec::<P1>::new(),
+                orders: BoundedVec::default(),
                 buy_amount: Default::default(),
                 sell_amount: Default::default(),
             };
@@ -131,22 +136,26 @@ price_list:  |  BOTTOM:          |            |  PriceItem:        |
     }
 
     // fn dùng để insert các mapping
-    pub fn write(thash: P1, price: Option<P2>, item: PriceItem<P1, P2, P3>) {
+    pub fn write(thash: P1, price: Option<P2>, item: Item<T, P1, P2, P3>) {
         S::insert(thash, price, item);
     }
 
-    // fn dùng để update khi có order
-    pub fn append(thash: P1, price: P2, ohash: P1, sell_amount: P3, buy_amount: P3, otype: OrderType) {
+    // fn dùng để update khi có order, mức giá đã đủ MaxOrdersPerPriceLevel order thì trả về lỗi PriceLevelFull
+    pub fn append(thash: P1, price: P2, ohash: P1, sell_amount: P3, buy_amount: P3, otype: OrderType) -> DispatchResult {
         // lấy PriceItem từ list ra
         let item = S::get(thash, Some(price));
 
+        // ds orders của PriceItem mới, tạo trước khi sửa list để lỗi thì không phải rollback gì
+        let mut orders = BoundedVec::default();
+        orders.try_push(ohash).map_err(|_| trade::Error::<T>::PriceLevelFull)?;
+
         match item {
             Some(mut item) => {  // Nếu PriceItem đã tồn tại, mình chỉ cần update amount và push order_hash vô
-                item.orders.push(ohash);
+                item.orders.try_push(ohash).map_err(|_| trade::Error::<T>::PriceLevelFull)?;
                 item.buy_amount += buy_amount;
                 item.sell_amount += sell_amount;
                 Self::write(thash, Some(price), item);
-                return;
+                Ok(())
             },
             None => { // Nếu PriceItem chưa tồn tại mình sẽ tạo PriceItem mới và chèn nó vô list
                 let start_item;
@@ -200,23 +209,22 @@ price_list:  |  BOTTOM:          |            |  PriceItem:        |
                 Self::write(thash, new_next.price, new_next.clone());
 
                 // update new_item and insert it to list
-                let mut v = Vec::new();
-                v.push(ohash);
                 let new_item = PriceItem {
                     prev: new_prev.price,
                     next: new_next.price,
                     buy_amount,
                     sell_amount,
-                    orders: v,
+                    orders,
                     price: Some(price),
                 };
                 Self::write(thash, new_item.price, new_item);
+                Ok(())
             }
         }
     }
 
     // trả về giá match tiếp theo, nếu mua thì là mức giá prev, còn bán là mức giá next
-    pub fn next_match_price(price_item: &PriceItem<P1, P2, P3>, otype: OrderType) -> Option<P2>{
+    pub fn next_match_price(price_item: &Item<T, P1, P2, P3>, otype: OrderType) -> Option<P2>{
         if otype == OrderType::Buy {
             price_item.prev
         } else {
@@ -259,11 +267,12 @@ price_list:  |  BOTTOM:          |            |  PriceItem:        |
                 item.buy_amount -= buy_amount;
                 item.sell_amount -= sell_amount;
 
-                // nếu list orders bằng 0 thì mình xóa luôn PriceItem này
+                // nếu list orders bằng 0 thì mình xóa luôn PriceItem này, còn không thì update lại
                 if item.orders.len() == 0 {
                     Self::remove_item(thash, price);
+                } else {
+                    Self::write(thash, Some(price), item);
                 }
-                Self::write(thash, Some(price), item);
             },
             None => {} // ????
         }
@@ -0,0 +1,397 @@
+use crate::{
+    rolling_window, Candles, CandleStarts, Config, LinkedItemList, OrderType, OrdersByExpiry,
+    OwnedOpenedTradePairs, OwnedTPClosedOrders, OwnedTPOpenedOrders, Pallet, PriceObservations,
+    StopOrderStatus, StopOrders, TPBucketHighPrices, TPBucketLowPrices, TPHighPrices, TPLowPrices, TPStopOrders, TPStopTriggerPrices,
+    TriggeredStopOrders,
+};
+use crate::linked_price_list::PriceItem;
+use codec::Decode;
+use frame_support::{
+    pallet_prelude::*,
+    sp_runtime::traits::Saturating,
+    sp_std::{marker::PhantomData, vec::Vec},
+    traits::{GetStorageVersion, OnRuntimeUpgrade, StorageVersion},
+};
+use pallet_tokens::Balance;
+
+/* v0 => v1: các storage dạng Vec chuyển sang BoundedVec với giới hạn lấy từ Config
+    Encode của Vec và BoundedVec giống nhau nên phần lớn chỉ cần decode lại và cắt bớt phần vượt giới hạn:
+    => ds lịch sử (opened/closed orders, price observations, candles) giữ lại các phần tử mới nhất, deque high/low 24h tách thành 2 tầng theo bucket
+    => order/stop order không còn chỗ trên sổ lệnh thì bị hủy và unfreeze phần còn dư
+    DelistingTradePairs, StopCheckPairs, OneDayStatsExpiry đổi từ Vec sang map nên phải chuyển dữ liệu sang key mới
+    OwnedOpenedTradePairs mới được dựng từ OwnedTPOpenedOrders
+    Stop order v1 freeze lượng bán từ lúc tạo => freeze cho các stop order đang chờ, không freeze được thì hủy
+*/
+pub mod v1 {
+    use super::*;
+
+    // kiểu storage cũ, chỉ dùng để đọc dữ liệu trước khi migrate
+    mod v0 {
+        use super::*;
+
+        #[frame_support::storage_alias]
+        pub type DelistingTradePairs<T: Config> = StorageValue<Pallet<T>, Vec<<T as frame_system::Config>::Hash>, ValueQuery>;
+
+        #[frame_support::storage_alias]
+        pub type StopCheckPairs<T: Config> = StorageValue<Pallet<T>, Vec<<T as frame_system::Config>::Hash>, ValueQuery>;
+
+        #[frame_support::storage_alias]
+        pub type OneDayStatsExpiry<T: Config> = StorageMap<
+            Pallet<T>,
+            Blake2_128Concat,
+            <T as frame_system::Config>::BlockNumber,
+            Vec<<T as frame_system::Config>::Hash>,
+            ValueQuery,
+        >;
+    }
+
+    #[derive(Decode)]
+    struct OldPriceItem<P1, P2, P3> {
+        prev: Option<P2>,
+        next: Option<P2>,
+        price: Option<P2>,
+        buy_amount: P3,
+        sell_amount: P3,
+        orders: Vec<P1>,
+    }
+
+    // giữ lại S::get() phần tử đầu của ds, trả về ds mới cùng các phần tử bị cắt
+    fn split_first<X, S: Get<u32>>(mut items: Vec<X>) -> (BoundedVec<X, S>, Vec<X>) {
+        let dropped = items.split_off(items.len().min(S::get() as usize));
+        (BoundedVec::try_from(items).unwrap_or_default(), dropped)
+    }
+
+    // khai báo trong ds Migrations của runtime (Executive) để chạy khi nâng spec_version
+    pub struct MigrateToV1<T>(PhantomData<T>);
+
+    impl<T: Config> OnRuntimeUpgrade for MigrateToV1<T> {
+        fn on_runtime_upgrade() -> Weight {
+            migrate::<T>()
+        }
+
+        #[cfg(feature = "try-runtime")]
+        fn post_upgrade(_state: Vec<u8>) -> Result<(), &'static str> {
+            ensure!(StorageVersion::get::<Pallet<T>>() == crate::STORAGE_VERSION, "Storage version was not updated");
+            Pallet::<T>::do_try_state()
+        }
+    }
+
+    pub fn migrate<T: Config>() -> Weight {
+        let onchain_version = Pallet::<T>::on_chain_storage_version();
+        if onchain_version >= 1 {
+            return T::DbWeight::get().reads(1)
+        }
+
+        // số entry được đọc/ghi lại và số order/stop order/nến bị hủy
+        let mut translated: u64 = 0;
+        let mut removed: u64 = 0;
+
+        // 1. Vec => map
+        for tp_hash in v0::DelistingTradePairs::<T>::take() {
+            translated += 1;
+            <crate::DelistingTradePairs<T>>::insert(tp_hash, ());
+        }
+        for tp_hash in v0::StopCheckPairs::<T>::take() {
+            translated += 1;
+            <crate::StopCheckPairs<T>>::insert(tp_hash, ());
+        }
+        // phải drain hết trước khi ghi vì map cũ và double map mới dùng chung prefix
+        let expiries: Vec<_> = v0::OneDayStatsExpiry::<T>::drain().collect();
+        for (block_number, tp_hashes) in expiries {
+            for tp_hash in tp_hashes {
+                translated += 1;
+                <crate::OneDayStatsExpiry<T>>::insert(block_number, tp_hash, ());
+            }
+        }
+
+        // 2. ds order theo user, order mới nhất nằm đầu ds, đánh dấu các cặp còn order mở vào OwnedOpenedTradePairs
+        <OwnedTPOpenedOrders<T>>::translate::<Vec<T::Hash>, _>(|(account, tp_hash), hashes| {
+            translated += 1;
+            let kept = split_first(hashes).0;
+            if !kept.is_empty() {
+                <OwnedOpenedTradePairs<T>>::insert(account, tp_hash, ());
+            }
+            Some(kept)
+        });
+        <OwnedTPClosedOrders<T>>::translate::<Vec<T::Hash>, _>(|_, hashes| {
+            translated += 1;
+            Some(split_first(hashes).0)
+        });
+
+        // 3. PriceItem: giữ các order đến trước, hủy phần vượt MaxOrdersPerPriceLevel
+        <LinkedItemList<T>>::translate::<OldPriceItem<T::Hash, T::Price, Balance<T>>, _>(|tp_hash, _, old| {
+            translated += 1;
+            let (orders, dropped) = split_first(old.orders);
+            let mut item = PriceItem {
+                prev: old.prev,
+                next: old.next,
+                price: old.price,
+                buy_amount: old.buy_amount,
+                sell_amount: old.sell_amount,
+                orders,
+            };
+            for order_hash in dropped {
+                if let Some((sell_amount, buy_amount)) = Pallet::<T>::cancel_unlisted_order(tp_hash, order_hash) {
+                    removed += 1;
+                    item.sell_amount = item.sell_amount.saturating_sub(sell_amount);
+                    item.buy_amount = item.buy_amount.saturating_sub(buy_amount);
+                }
+            }
+            Some(item)
+        });
+
+        // 4. order hết hạn theo block, giới hạn đã được check khi tạo order
+        // v0 không gỡ order đã hủy/khớp hết khỏi ds => bỏ luôn các order đã kết thúc
+        <OrdersByExpiry<T>>::translate::<Vec<T::Hash>, _>(|_, mut hashes| {
+            translated += 1 + hashes.len() as u64;
+            hashes.retain(|hash| Pallet::<T>::orders(hash).map_or(false, |order| !order.is_finished()));
+            if hashes.is_empty() {
+                return None
+            }
+            Some(split_first(hashes).0)
+        });
+
+        // 5. price observations, candles, high/low 24h: giữ các phần tử mới nhất
+        <PriceObservations<T>>::translate::<Vec<(T::BlockNumber, u128)>, _>(|_, observations| {
+            translated += 1;
+            Some(rolling_window::keep_latest(observations))
+        });
+        <CandleStarts<T>>::translate::<Vec<T::BlockNumber>, _>(|key, mut starts| {
+            translated += 1;
+            let overflow = starts.len().saturating_sub(T::CandleRetention::get() as usize);
+            for start in starts.drain(..overflow) {
+                removed += 1;
+                <Candles<T>>::remove(key, start);
+            }
+            Some(rolling_window::keep_latest(starts))
+        });
+        /* deque cũ có 1 entry mỗi block => chia các entry vào deque của bucket chứa block đó, deque cửa sổ giữ max/min theo bucket
+            Entry bị deque cũ bỏ vì bị lấn át bởi giá sau thì không bao giờ còn là max/min nữa => kết quả vẫn giống hệt deque cũ
+        */
+        let days = T::BlocksPerDay::get();
+        <TPHighPrices<T>>::translate::<Vec<(T::BlockNumber, T::Price)>, _>(|tp_hash, old| {
+            translated += 1 + old.len() as u64;
+            let mut deque = Vec::new();
+            for (block, price) in old {
+                let bucket_start = rolling_window::bucket_start(block, days);
+                <TPBucketHighPrices<T>>::mutate(tp_hash, bucket_start, |bucket| {
+                    rolling_window::update(bucket, |bucket| rolling_window::push_max(bucket, block, price))
+                });
+                rolling_window::push_max(&mut deque, bucket_start, price);
+            }
+            Some(rolling_window::keep_latest(deque))
+        });
+        <TPLowPrices<T>>::translate::<Vec<(T::BlockNumber, T::Price)>, _>(|tp_hash, old| {
+            translated += 1 + old.len() as u64;
+            let mut deque = Vec::new();
+            for (block, price) in old {
+                let bucket_start = rolling_window::bucket_start(block, days);
+                <TPBucketLowPrices<T>>::mutate(tp_hash, bucket_start, |bucket| {
+                    rolling_window::update(bucket, |bucket| rolling_window::push_min(bucket, block, price))
+                });
+                rolling_window::push_min(&mut deque, bucket_start, price);
+            }
+            Some(rolling_window::keep_latest(deque))
+        });
+
+        // 6. stop orders: hủy các lệnh không còn chỗ
+        <TPStopOrders<T>>::translate::<Vec<T::Hash>, _>(|_, _, hashes| {
+            translated += 1;
+            let (kept, dropped) = split_first(hashes);
+            for stop_order_hash in dropped {
+                removed += 1;
+                Pallet::<T>::cancel_unlisted_stop_order(stop_order_hash);
+            }
+            Some(kept)
+        });
+        /* giữ các trigger_price gần giá thị trường nhất (sẽ được kích hoạt trước):
+            => Buy kích hoạt khi giá >= trigger_price nên giữ các giá thấp nhất (đầu ds)
+            => Sell kích hoạt khi giá <= trigger_price nên giữ các giá cao nhất (cuối ds)
+        */
+        <TPStopTriggerPrices<T>>::translate::<Vec<T::Price>, _>(|key, mut prices| {
+            translated += 1;
+            if key.1 == OrderType::Sell {
+                prices.reverse();
+            }
+            let (kept, dropped) = split_first::<_, T::MaxStopTriggerPrices>(prices);
+            for trigger_price in dropped {
+                for stop_order_hash in <TPStopOrders<T>>::take(key, trigger_price) {
+                    removed += 1;
+                    Pallet::<T>::cancel_unlisted_stop_order(stop_order_hash);
+                }
+            }
+            let mut kept = kept.into_inner();
+            if key.1 == OrderType::Sell {
+                kept.reverse();
+            }
+            Some(BoundedVec::try_from(kept).unwrap_or_default())
+        });
+        let _ = <TriggeredStopOrders<T>>::translate::<Vec<T::Hash>, _>(|queue| {
+            translated += 1;
+            let (kept, dropped) = split_first(queue?);
+            for stop_order_hash in dropped {
+                removed += 1;
+                Pallet::<T>::cancel_unlisted_stop_order(stop_order_hash);
+            }
+            Some(kept)
+        });
+
+        // 7. freeze lượng bán của các stop order còn đang chờ/đã trigger (phần bị cắt ở trên đã bị hủy)
+        let waiting: Vec<_> = <StopOrders<T>>::iter_values()
+            .filter(|stop_order| matches!(stop_order.status, StopOrderStatus::Pending | StopOrderStatus::Triggered))
+            .collect();
+        for stop_order in waiting {
+            translated += 1;
+            if Pallet::<T>::freeze_v0_stop_order(stop_order) {
+                removed += 1;
+            }
+        }
+
+        StorageVersion::new(1).put::<Pallet<T>>();
+
+        T::DbWeight::get().reads_writes(1 + translated, 1 + translated)
+            .saturating_add(T::DbWeight::get().reads_writes(4, 4).saturating_mul(removed))
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::{mock::*, OrderOpt, OrderStatus, TimeInForce};
+        use frame_support::{assert_ok, storage::unhashed};
+        use sp_core::H256;
+
+        // dữ liệu v0 được ghi thẳng vào storage với encode cũ (Vec) rồi đưa StorageVersion về 0
+        fn buy(who: AccountId, base: H256, quote: H256, units: u128, sell_amount: Balance) {
+            assert_ok!(TradeModule::create_order(RuntimeOrigin::signed(who), base, quote, OrderOpt::Limit, OrderType::Buy, price(units), sell_amount, TimeInForce::GoodTilCanceled, false, None, None));
+        }
+
+        #[test]
+        fn migrate_moves_vec_storage_and_bumps_version() {
+            new_test_ext().execute_with(|| {
+                let (_, _, tp_hash) = setup_trade_pair();
+                let other = H256::repeat_byte(7);
+
+                v0::DelistingTradePairs::<Test>::put(vec![tp_hash, other]);
+                v0::StopCheckPairs::<Test>::put(vec![tp_hash]);
+                v0::OneDayStatsExpiry::<Test>::insert(20, vec![tp_hash, other]);
+                StorageVersion::new(0).put::<TradeModule>();
+
+                migrate::<Test>();
+
+                assert_eq!(TradeModule::on_chain_storage_version(), 1);
+                assert_eq!(<crate::DelistingTradePairs<Test>>::iter_keys().count(), 2);
+                assert!(TradeModule::delisting_trade_pairs(other).is_some());
+                assert!(<crate::StopCheckPairs<Test>>::contains_key(tp_hash));
+                let mut expiring = <crate::OneDayStatsExpiry<Test>>::iter_key_prefix(20).collect::<Vec<_>>();
+                expiring.sort();
+                let mut expected = vec![tp_hash, other];
+                expected.sort();
+                assert_eq!(expiring, expected);
+
+                // đã ở v1 => chạy lại không làm gì
+                v0::StopCheckPairs::<Test>::put(vec![other]);
+                assert_eq!(migrate::<Test>(), <Test as frame_system::Config>::DbWeight::get().reads(1));
+                assert!(!<crate::StopCheckPairs<Test>>::contains_key(other));
+            });
+        }
+
+        #[test]
+        fn migrate_truncates_order_lists_and_cancels_overflowing_orders() {
+            new_test_ext().execute_with(|| {
+                let (busd, btc, tp_hash) = setup_trade_pair();
+
+                // v0 không giới hạn số order mỗi mức giá => tạm nới giới hạn để dựng PriceItem 12 order
+                MaxOrdersPerPriceLevel::set(12);
+                for _ in 0..12 {
+                    buy(ALICE, busd, btc, 2, 200);
+                }
+                MaxOrdersPerPriceLevel::set(10);
+                let orders = <OwnedTPOpenedOrders<Test>>::get((ALICE, tp_hash)).unwrap().into_inner();
+
+                // ds closed orders của BOB dài hơn ClosedOrdersArrayCap (100), order mới nhất nằm đầu ds
+                let closed: Vec<H256> = (0..105u8).map(H256::repeat_byte).collect();
+                unhashed::put(&<OwnedTPClosedOrders<Test>>::hashed_key_for((BOB, tp_hash)), &closed);
+                StorageVersion::new(0).put::<TradeModule>();
+
+                migrate::<Test>();
+
+                assert_eq!(<OwnedTPClosedOrders<Test>>::get((BOB, tp_hash)).unwrap().into_inner(), closed[..100].to_vec());
+
+                // giữ 10 order đến trước, 2 order sau bị hủy và unfreeze
+                let item = <LinkedItemList<Test>>::get(tp_hash, Some(price(2))).unwrap();
+                let (kept, dropped): (Vec<H256>, Vec<H256>) = orders.iter().partition(|hash| item.orders.contains(hash));
+                assert_eq!((kept.len(), dropped.len()), (10, 2));
+                assert_eq!((item.sell_amount, item.buy_amount), (2_000, 1_000));
+                for order_hash in dropped {
+                    assert_eq!(TradeModule::orders(order_hash).unwrap().status, OrderStatus::Canceled);
+                    System::assert_has_event(RuntimeEvent::TradeModule(crate::Event::OrderForceCanceled { owner: ALICE, order_hash }));
+                }
+                assert_eq!((Tokens::free_balance_of(ALICE, busd), Tokens::freezed_balance_of(ALICE, busd)), (INITIAL_TOKENS - 2_000, 2_000));
+                assert_eq!(<OwnedTPOpenedOrders<Test>>::get((ALICE, tp_hash)).unwrap().len(), 10);
+                assert_ok!(TradeModule::do_try_state());
+            });
+        }
+
+        #[test]
+        fn migrate_splits_high_low_deques_into_exact_buckets() {
+            new_test_ext().execute_with(|| {
+                // 360 block => mỗi bucket 3 block: bucket 0 = {1}, bucket 3 = {4, 5}, bucket 6 = {8}
+                let days = 360;
+                BlocksPerDay::set(days);
+                let (_, _, tp_hash) = setup_trade_pair();
+                let trades: Vec<(u64, Vec<u128>)> = vec![(1, vec![9, 2]), (4, vec![7, 5]), (5, vec![6]), (8, vec![3, 4])];
+
+                // v0 giữ 1 deque theo từng block, expiry theo ds tp_hash của từng block
+                let (mut highs, mut lows) = (Vec::new(), Vec::new());
+                for (block, prices) in trades.iter() {
+                    for units in prices {
+                        rolling_window::push_max(&mut highs, *block, price(*units));
+                        rolling_window::push_min(&mut lows, *block, price(*units));
+                    }
+                    v0::OneDayStatsExpiry::<Test>::insert(block + days as u64, vec![tp_hash]);
+                }
+                unhashed::put(&<TPHighPrices<Test>>::hashed_key_for(tp_hash), &highs);
+                unhashed::put(&<TPLowPrices<Test>>::hashed_key_for(tp_hash), &lows);
+                System::set_block_number(9);
+                StorageVersion::new(0).put::<TradeModule>();
+
+                migrate::<Test>();
+
+                assert_eq!(<TPHighPrices<Test>>::get(tp_hash).into_inner(), vec![(0, price(9)), (3, price(7)), (6, price(4))]);
+                assert_eq!(<TPBucketHighPrices<Test>>::get(tp_hash, 3).into_inner(), vec![(4, price(7)), (5, price(6))]);
+                assert_eq!(<TPLowPrices<Test>>::get(tp_hash).into_inner(), vec![(0, price(2)), (6, price(3))]);
+                assert!(!<TPBucketLowPrices<Test>>::contains_key(tp_hash, 3));
+
+                // sau migrate, high/low 24h vẫn giống hệt khi quét giá theo từng block, kể cả khi bucket 3 mới ra khỏi cửa sổ 1 nửa
+                for block in days as u64 + 1..=days as u64 + 9 {
+                    run_to_block(block);
+                    let window = trades.iter().filter(|(traded_at, _)| traded_at + days as u64 > block).flat_map(|(_, prices)| prices.iter().map(|units| price(*units)));
+                    let tp = TradeModule::trade_pairs(tp_hash).unwrap();
+                    assert_eq!((tp.one_day_highest_price, tp.one_day_lowest_price), (window.clone().max(), window.min()), "block {}", block);
+                }
+                assert_eq!(<TPBucketHighPrices<Test>>::iter_prefix(tp_hash).count(), 0);
+            });
+        }
+
+        // pallet_tokens chưa có mock riêng => test migration của tokens qua mock của trade
+        #[test]
+        fn tokens_migration_truncates_long_symbols() {
+            new_test_ext().execute_with(|| {
+                let (busd, _, _) = setup_trade_pair();
+                let long = H256::repeat_byte(1);
+
+                // Token v0: (hash, symbol: Vec<u8>, total_supply), MaxSymbolLength của mock là 8
+                unhashed::put(&pallet_tokens::Tokens::<Test>::hashed_key_for(long), &(long, b"VERYLONGSYMBOL".to_vec(), 1_000u128));
+                StorageVersion::new(0).put::<Tokens>();
+
+                pallet_tokens::migrations::v1::migrate::<Test>();
+
+                assert_eq!(Tokens::on_chain_storage_version(), 1);
+                let token = Tokens::tokens(long).unwrap();
+                assert_eq!((token.symbol.into_inner(), token.total_supply), (b"VERYLONG".to_vec(), 1_000));
+                assert_eq!(Tokens::tokens(busd).unwrap().symbol.into_inner(), b"BUSD".to_vec());
+                assert_eq!(pallet_tokens::migrations::v1::migrate::<Test>(), <Test as frame_system::Config>::DbWeight::get().reads(1));
+            });
+        }
+    }
+}
@@ -0,0 +1,201 @@
+use crate as pallet_trade;
+use codec::Encode;
+use frame_support::{
+    bounded_vec, parameter_types,
+    traits::{ConstBool, ConstU16, ConstU32, ConstU64, ConstU128, Everything, Hooks, Randomness},
+};
+use frame_system::EnsureRoot;
+use sp_core::H256;
+use sp_runtime::{
+    testing::Header,
+    traits::{BlakeTwo256, Hash, IdentityLookup},
+    BoundedVec, Permill,
+};
+
+type UncheckedExtrinsic = frame_system::mocking::MockUncheckedExtrinsic<Test>;
+type Block = frame_system::mocking::MockBlock<Test>;
+
+pub type AccountId = u64;
+pub type Balance = u128;
+pub type Price = u128;
+
+pub const ALICE: AccountId = 1;
+pub const BOB: AccountId = 2;
+pub const CHARLIE: AccountId = 3;
+// account không được chia token trong setup_trade_pair
+pub const DAVE: AccountId = 4;
+pub const FEE_COLLECTOR: AccountId = 99;
+
+// số dư native ban đầu của mỗi account, dùng để reserve tiền cọc khi tạo cặp
+pub const INITIAL_NATIVE: Balance = 1_000_000;
+// số token BUSD/BTC được chia cho mỗi account trong setup_trade_pair
+pub const INITIAL_TOKENS: Balance = 1_000_000;
+
+frame_support::construct_runtime!(
+    pub enum Test where
+        Block = Block,
+        NodeBlock = Block,
+        UncheckedExtrinsic = UncheckedExtrinsic,
+    {
+        System: frame_system,
+        Balances: pallet_balances,
+        Tokens: pallet_tokens,
+        TradeModule: pallet_trade,
+    }
+);
+
+impl frame_system::Config for Test {
+    type BaseCallFilter = Everything;
+    type BlockWeights = ();
+    type BlockLength = ();
+    type DbWeight = ();
+    type RuntimeOrigin = RuntimeOrigin;
+    type RuntimeCall = RuntimeCall;
+    type Index = u64;
+    type BlockNumber = u64;
+    type Hash = H256;
+    type Hashing = BlakeTwo256;
+    type AccountId = AccountId;
+    type Lookup = IdentityLookup<Self::AccountId>;
+    type Header = Header;
+    type RuntimeEvent = RuntimeEvent;
+    type BlockHashCount = ConstU64<250>;
+    type Version = ();
+    type PalletInfo = PalletInfo;
+    type AccountData = pallet_balances::AccountData<Balance>;
+    type OnNewAccount = ();
+    type OnKilledAccount = ();
+    type SystemWeightInfo = ();
+    type SS58Prefix = ConstU16<42>;
+    type OnSetCode = ();
+    type MaxConsumers = ConstU32<16>;
+}
+
+impl pallet_balances::Config for Test {
+    type MaxLocks = ();
+    type MaxReserves = ();
+    type ReserveIdentifier = [u8; 8];
+    type Balance = Balance;
+    type RuntimeEvent = RuntimeEvent;
+    type DustRemoval = ();
+    type ExistentialDeposit = ConstU128<1>;
+    type AccountStore = System;
+    type WeightInfo = ();
+}
+
+/* Randomness cố định cho test: chỉ phụ thuộc subject và block hiện tại
+    => các hash (token, trade_pair, order, trade) vẫn khác nhau nhờ nonce, nhưng mỗi lần chạy test đều giống nhau
+*/
+pub struct TestRandomness;
+impl Randomness<H256, u64> for TestRandomness {
+    fn random(subject: &[u8]) -> (H256, u64) {
+        let block_number = System::block_number();
+        ((subject, block_number).using_encoded(BlakeTwo256::hash), block_number)
+    }
+}
+
+impl pallet_tokens::Config for Test {
+    type RuntimeEvent = RuntimeEvent;
+    type Currency = Balances;
+    type TokenRandom = TestRandomness;
+    type AdminOrigin = EnsureRoot<AccountId>;
+    type MaxSymbolLength = ConstU32<8>;
+    type WeightInfo = ();
+}
+
+parameter_types! {
+    pub const FeeCollector: AccountId = FEE_COLLECTOR;
+    pub CandleIntervals: BoundedVec<u32, ConstU32<4>> = bounded_vec![5, 10];
+    // phí mặc định = 0 để dễ kiểm tra số dư, test phí thì set lại
+    pub static MakerFee: Permill = Permill::zero();
+    pub static TakerFee: Permill = Permill::zero();
+    // test giới hạn số lần khớp của market order thì set lại
+    pub static MaxMarketOrderFills: u32 = 50;
+    // test cửa sổ 24h chia nhiều block mỗi bucket thì set lại
+    pub static BlocksPerDay: u32 = 10;
+    // test sweep cặp đã delist qua nhiều block thì set lại
+    pub static MaxDelistOrdersPerBlock: u32 = 50;
+    // test migration dựng PriceItem vượt giới hạn thì set lại
+    pub static MaxOrdersPerPriceLevel: u32 = 10;
+}
+
+impl pallet_trade::Config for Test {
+    type RuntimeEvent = RuntimeEvent;
+    type Price = Price;
+    type TradeRandom = TestRandomness;
+    type PriceFactor = ConstU128<100_000_000>;
+    type BlocksPerDay = BlocksPerDay;
+    type OpenedOrdersArrayCap = ConstU32<20>;
+    type ClosedOrdersArrayCap = ConstU32<100>;
+    type MaxOrdersPerPriceLevel = MaxOrdersPerPriceLevel;
+    type MakerFee = MakerFee;
+    type TakerFee = TakerFee;
+    type FeeCollector = FeeCollector;
+    type MaxExpiringOrdersPerBlock = ConstU32<2>;
+    type MaxStopOrdersPerBlock = ConstU32<50>;
+    type MaxCancelAllOrders = ConstU32<50>;
+    type MaxBatchOrders = ConstU32<20>;
+    type MaxDelistOrdersPerBlock = MaxDelistOrdersPerBlock;
+    type AdminOrigin = EnsureRoot<AccountId>;
+    type DepositCurrency = Balances;
+    type TradePairDeposit = ConstU128<100>;
+    type OwnerOnlyTradePairCreation = ConstBool<false>;
+    type MaxMarketOrderFills = MaxMarketOrderFills;
+    type MaxCandleIntervals = ConstU32<4>;
+    type CandleIntervals = CandleIntervals;
+    type CandleRetention = ConstU32<10>;
+    type PriceObservationPeriod = ConstU32<1>;
+    type MaxPriceObservations = ConstU32<100>;
+    type MaxStopOrdersPerPrice = ConstU32<10>;
+    type MaxStopTriggerPrices = ConstU32<10>;
+    type WeightInfo = ();
+}
+
+pub fn new_test_ext() -> sp_io::TestExternalities {
+    let mut t = frame_system::GenesisConfig::default().build_storage::<Test>().unwrap();
+    pallet_balances::GenesisConfig::<Test> {
+        balances: vec![(ALICE, INITIAL_NATIVE), (BOB, INITIAL_NATIVE), (CHARLIE, INITIAL_NATIVE)],
+    }
+    .assimilate_storage(&mut t)
+    .unwrap();
+
+    let mut ext = sp_io::TestExternalities::new(t);
+    ext.execute_with(|| System::set_block_number(1));
+    ext
+}
+
+// giá theo PriceFactor, ví dụ price(2) => 1 BTC = 2 BUSD
+pub fn price(units: u128) -> Price {
+    units * 100_000_000
+}
+
+// chạy on_initialize của pallet trade từ block hiện tại + 1 đến block n
+pub fn run_to_block(n: u64) {
+    while System::block_number() < n {
+        let next = System::block_number() + 1;
+        System::set_block_number(next);
+        TradeModule::on_initialize(next);
+    }
+}
+
+/* ALICE issue BUSD, BOB issue BTC, chia đều cho 3 account rồi ALICE tạo cặp BUSD/BTC
+    => trả về (busd, btc, tp_hash)
+*/
+pub fn setup_trade_pair() -> (H256, H256, H256) {
+    let supply = INITIAL_TOKENS * 3;
+    Tokens::issue(RuntimeOrigin::signed(ALICE), b"BUSD".to_vec(), supply).unwrap();
+    Tokens::issue(RuntimeOrigin::signed(BOB), b"BTC".to_vec(), supply).unwrap();
+    let busd = Tokens::owned_token_index(ALICE, 0).unwrap();
+    let btc = Tokens::owned_token_index(BOB, 0).unwrap();
+
+    for account in [BOB, CHARLIE] {
+        Tokens::transfer(RuntimeOrigin::signed(ALICE), account, busd, INITIAL_TOKENS).unwrap();
+    }
+    for account in [ALICE, CHARLIE] {
+        Tokens::transfer(RuntimeOrigin::signed(BOB), account, btc, INITIAL_TOKENS).unwrap();
+    }
+
+    TradeModule::create_trade_pair(RuntimeOrigin::signed(ALICE), busd, btc).unwrap();
+    let tp_hash = TradeModule::trade_pair_hash_by_base_quote((busd, btc)).unwrap();
+    (busd, btc, tp_hash)
+}
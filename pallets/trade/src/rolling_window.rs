@@ -0,0 +1,389 @@
+use frame_support::{inherent::Vec, sp_runtime::traits::AtLeast32BitUnsigned, sp_std::{marker::PhantomData, mem}, traits::{ConstU32, Get}, BoundedVec};
+
+/* Các fn hỗ trợ thống kê 24h theo cửa sổ trượt (rolling window) bằng monotonic deque
+    Mỗi deque là 1 Vec<(block_number, price)> được sắp xếp theo block tăng dần:
+    + deque max: giá giảm dần từ đầu đến cuối => phần tử đầu tiên là giá cao nhất trong cửa sổ
+    + deque min: giá tăng dần từ đầu đến cuối => phần tử đầu tiên là giá thấp nhất trong cửa sổ
+    Khi có giá mới thì bỏ các phần tử cuối không bao giờ còn là max/min nữa, khi block cũ ra khỏi cửa sổ thì bỏ các phần tử đầu
+    => mỗi giá chỉ được push và pop tối đa 1 lần
+    Các giá cùng block ra khỏi cửa sổ cùng lúc nên mỗi block chỉ giữ lại 1 phần tử => deque không dài quá số block của cửa sổ
+    Giá cao/thấp nhất 24h dùng 2 tầng deque để mỗi deque nhỏ mà kết quả vẫn giống hệt 1 deque theo block:
+    + deque của từng bucket: key là block, chỉ chứa các block trong bucket đó => không dài quá bucket_len phần tử
+    + deque của cửa sổ: key là block bắt đầu của bucket, giá là max/min của phần còn trong cửa sổ của bucket đó
+      => không dài quá số bucket của cửa sổ
+    Khi block cũ ra khỏi cửa sổ thì chỉ bucket cũ nhất bị bớt block => bỏ block đó ở deque của bucket rồi sửa phần tử đầu
+    của deque cửa sổ theo giá còn lại của bucket (shrink_front)
+*/
+
+// số bucket chia cửa sổ 24h khi tính giá cao/thấp nhất, ~căn bậc 2 của 14400 block để 2 tầng deque dài xấp xỉ nhau
+pub const BUCKETS_PER_WINDOW: u32 = 120;
+
+/* số phần tử tối đa của deque cửa sổ: bucket bị bỏ khi block cuối cùng có trade của nó ra khỏi cửa sổ
+    => các bucket còn lại đều giao với (block - days, block] => tối đa days / bucket_len + 2 bucket
+*/
+pub type MaxBuckets = ConstU32<{ BUCKETS_PER_WINDOW + 2 }>;
+
+// số phần tử tối đa của deque 1 bucket = số block của 1 bucket với cửa sổ Days block
+pub struct BucketLen<Days>(PhantomData<Days>);
+
+impl<Days: Get<u32>> Get<u32> for BucketLen<Days> {
+    fn get() -> u32 {
+        bucket_len(Days::get())
+    }
+}
+
+// số block của 1 bucket = ceil(days / BUCKETS_PER_WINDOW), tối thiểu 1 block
+pub fn bucket_len(days: u32) -> u32 {
+    (days.saturating_add(BUCKETS_PER_WINDOW - 1) / BUCKETS_PER_WINDOW).max(1)
+}
+
+// block bắt đầu của bucket chứa block, dùng làm key của phần tử trong deque
+pub fn bucket_start<B: AtLeast32BitUnsigned + Copy>(block: B, days: u32) -> B {
+    let len: B = bucket_len(days).into();
+    block - block % len
+}
+
+/* bucket key vừa bỏ bớt block cũ, remaining là giá max/min của các block còn lại của bucket
+    key chỉ có thể là bucket cũ nhất nên chỉ cần sửa phần tử đầu: bucket hết block hoặc bị phần tử sau lấn át
+    (dominated(remaining, giá sau) = true) thì bỏ, còn lại thì thay giá
+*/
+pub fn shrink_front<B: PartialEq, P: Copy>(deque: &mut Vec<(B, P)>, key: B, remaining: Option<P>, dominated: impl Fn(P, P) -> bool) {
+    if !matches!(deque.first(), Some((first, _)) if *first == key) {
+        return;
+    }
+    match remaining {
+        Some(price) if !matches!(deque.get(1), Some((_, next)) if dominated(price, *next)) => deque[0].1 = price,
+        _ => {
+            deque.remove(0);
+        },
+    }
+}
+
+// thêm giá mới vào deque max
+pub fn push_max<B: PartialEq, P: PartialOrd>(deque: &mut Vec<(B, P)>, block: B, price: P) {
+    if matches!(deque.last(), Some((last_block, last)) if *last_block == block && *last > price) {
+        return;
+    }
+    while matches!(deque.last(), Some((_, last)) if *last <= price) {
+        deque.pop();
+    }
+    deque.push((block, price));
+}
+
+// thêm giá mới vào deque min
+pub fn push_min<B: PartialEq, P: PartialOrd>(deque: &mut Vec<(B, P)>, block: B, price: P) {
+    if matches!(deque.last(), Some((last_block, last)) if *last_block == block && *last < price) {
+        return;
+    }
+    while matches!(deque.last(), Some((_, last)) if *last >= price) {
+        deque.pop();
+    }
+    deque.push((block, price));
+}
+
+// bỏ các phần tử có block <= cutoff (đã ra khỏi cửa sổ), trả về số phần tử đã bỏ
+pub fn expire<B: PartialOrd, P>(deque: &mut Vec<(B, P)>, cutoff: B) -> usize {
+    let expired = deque.iter().take_while(|(block, _)| *block <= cutoff).count();
+    deque.drain(..expired);
+    expired
+}
+
+// giá max/min hiện tại của cửa sổ
+pub fn front<B, P: Copy>(deque: &[(B, P)]) -> Option<P> {
+    deque.first().map(|(_, price)| *price)
+}
+
+// chạy f trên deque đang lưu trong storage rồi ghi lại, trả về giá max/min hiện tại của cửa sổ
+pub fn update<B, P: Copy, S: Get<u32>>(deque: &mut BoundedVec<(B, P), S>, f: impl FnOnce(&mut Vec<(B, P)>)) -> Option<P> {
+    let mut items = mem::take(deque).into_inner();
+    f(&mut items);
+    *deque = keep_latest(items);
+    front(deque)
+}
+
+// giữ tối đa S phần tử cuối (mới nhất) của ds cũ trước mới sau
+pub fn keep_latest<X, S: Get<u32>>(mut items: Vec<X>) -> BoundedVec<X, S> {
+    let excess = items.len().saturating_sub(S::get() as usize);
+    items.drain(..excess);
+    BoundedVec::try_from(items).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    // (volume, high, low) sau khi kết thúc 1 block
+    type Stats = (u128, Option<u128>, Option<u128>);
+
+    /* Mô phỏng lại thuật toán cũ trong on_initialize / on_finalize:
+        + on_initialize(b): nếu b > days => trừ volume của bucket (b - days), bỏ phần tử đầu của 2 vec high/low
+        + on_finalize(b): push high/low của bucket b vào vec, duyệt toàn bộ vec để tính lại high/low, cộng volume của bucket b
+    */
+    #[derive(Default)]
+    struct OldAlgorithm {
+        volume: u128,
+        high_vec: Vec<Option<u128>>,
+        low_vec: Vec<Option<u128>>,
+        buckets: BTreeMap<u32, (u128, Option<u128>, Option<u128>)>,
+    }
+
+    impl OldAlgorithm {
+        fn run_block(&mut self, block: u32, days: u32, trades: &[(u128, u128)]) -> Stats {
+            if block > days {
+                let (volume, _, _) = self.buckets.get(&(block - days)).cloned().unwrap_or_default();
+                self.volume -= volume;
+                if !self.high_vec.is_empty() {
+                    self.high_vec.remove(0);
+                }
+                if !self.low_vec.is_empty() {
+                    self.low_vec.remove(0);
+                }
+            }
+
+            for (price, amount) in trades {
+                let bucket = self.buckets.entry(block).or_insert((0, None, None));
+                bucket.0 += amount;
+                bucket.1 = Some(bucket.1.map_or(*price, |high| high.max(*price)));
+                bucket.2 = Some(bucket.2.map_or(*price, |low| low.min(*price)));
+            }
+
+            let (volume, high, low) = self.buckets.get(&block).cloned().unwrap_or_default();
+            self.high_vec.push(high);
+            self.low_vec.push(low);
+            self.volume += volume;
+
+            let high = self.high_vec.iter().flatten().max().cloned();
+            let low = self.low_vec.iter().flatten().min().cloned();
+            (self.volume, high, low)
+        }
+    }
+
+    /* Thuật toán mới giống trong pallet:
+        + khi có trade: cộng volume, push vào 2 deque, nếu là trade đầu tiên của block thì hẹn expire ở block + days
+        + on_initialize(b): chỉ xử lý các cặp được hẹn ở block b => trừ volume của bucket (b - days), expire 2 deque
+    */
+    #[derive(Default)]
+    struct RollingWindow {
+        volume: u128,
+        highs: Vec<(u32, u128)>,
+        lows: Vec<(u32, u128)>,
+        buckets: BTreeMap<u32, u128>,
+        expiry: BTreeMap<u32, ()>,
+    }
+
+    impl RollingWindow {
+        fn run_block(&mut self, block: u32, days: u32, trades: &[(u128, u128)]) -> Stats {
+            if self.expiry.remove(&block).is_some() {
+                let cutoff = block - days;
+                self.volume -= self.buckets.remove(&cutoff).unwrap_or_default();
+                expire(&mut self.highs, cutoff);
+                expire(&mut self.lows, cutoff);
+            }
+
+            for (price, amount) in trades {
+                if !self.buckets.contains_key(&block) {
+                    self.expiry.insert(block + days, ());
+                }
+                *self.buckets.entry(block).or_default() += amount;
+                self.volume += amount;
+                push_max(&mut self.highs, block, *price);
+                push_min(&mut self.lows, block, *price);
+            }
+
+            (self.volume, front(&self.highs), front(&self.lows))
+        }
+    }
+
+    // LCG đơn giản để sinh dữ liệu giả ngẫu nhiên, không cần thêm dependency
+    struct Lcg(u64);
+
+    impl Lcg {
+        fn next(&mut self, max: u64) -> u64 {
+            self.0 = self.0.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            (self.0 >> 33) % max
+        }
+    }
+
+    fn compare(days: u32, blocks: u32, seed: u64) {
+        let mut rng = Lcg(seed);
+        let mut old = OldAlgorithm::default();
+        let mut new = RollingWindow::default();
+
+        for block in 1..=blocks {
+            // khoảng 1/3 số block không có trade
+            let trades: Vec<(u128, u128)> = (0..rng.next(4))
+                .map(|_| (1 + rng.next(1_000) as u128, 1 + rng.next(10_000) as u128))
+                .collect();
+
+            assert_eq!(old.run_block(block, days, &trades), new.run_block(block, days, &trades), "block {} days {} seed {}", block, days, seed);
+        }
+    }
+
+    #[test]
+    fn push_max_keeps_decreasing_prices() {
+        let mut deque = Vec::new();
+        push_max(&mut deque, 1, 5);
+        push_max(&mut deque, 2, 3);
+        push_max(&mut deque, 3, 4);
+        assert_eq!(deque, vec![(1, 5), (3, 4)]);
+        push_max(&mut deque, 4, 6);
+        assert_eq!(deque, vec![(4, 6)]);
+    }
+
+    #[test]
+    fn push_min_keeps_increasing_prices() {
+        let mut deque = Vec::new();
+        push_min(&mut deque, 1, 5);
+        push_min(&mut deque, 2, 7);
+        push_min(&mut deque, 3, 6);
+        assert_eq!(deque, vec![(1, 5), (3, 6)]);
+        push_min(&mut deque, 4, 1);
+        assert_eq!(deque, vec![(4, 1)]);
+    }
+
+    #[test]
+    fn same_block_keeps_one_entry() {
+        let mut highs = Vec::new();
+        let mut lows = Vec::new();
+        for price in [5, 3, 4, 2] {
+            push_max(&mut highs, 1, price);
+            push_min(&mut lows, 1, price);
+        }
+        assert_eq!(highs, vec![(1, 5)]);
+        assert_eq!(lows, vec![(1, 2)]);
+
+        push_max(&mut highs, 2, 1);
+        push_max(&mut highs, 2, 3);
+        assert_eq!(highs, vec![(1, 5), (2, 3)]);
+    }
+
+    #[test]
+    fn keep_latest_drops_oldest() {
+        use frame_support::traits::ConstU32;
+
+        let kept: BoundedVec<u32, ConstU32<2>> = keep_latest(vec![1, 2, 3]);
+        assert_eq!(kept.into_inner(), vec![2, 3]);
+    }
+
+    #[test]
+    fn expire_drops_blocks_outside_window() {
+        let mut deque = vec![(1, 9), (3, 7), (5, 2)];
+        assert_eq!(expire(&mut deque, 3), 2);
+        assert_eq!(front(&deque), Some(2));
+        assert_eq!(expire(&mut deque, 4), 0);
+        assert_eq!(expire(&mut deque, 5), 1);
+        assert_eq!(front(&deque), None);
+    }
+
+    /* 2 tầng deque giống trong pallet (set_tp_market_data / expire_one_day_stats):
+        + khi có trade: push vào deque của bucket hiện tại theo block và vào deque cửa sổ theo bucket
+        + on_initialize(b): bỏ block (b - days) ở deque của bucket chứa nó, sửa phần tử đầu của deque cửa sổ
+    */
+    #[derive(Default)]
+    struct Bucketed {
+        highs: Vec<(u32, u128)>,
+        lows: Vec<(u32, u128)>,
+        bucket_highs: BTreeMap<u32, Vec<(u32, u128)>>,
+        bucket_lows: BTreeMap<u32, Vec<(u32, u128)>>,
+        traded: BTreeMap<u32, ()>,
+    }
+
+    impl Bucketed {
+        fn run_block(&mut self, block: u32, days: u32, trades: &[(u128, u128)]) -> (Option<u128>, Option<u128>) {
+            if block > days && self.traded.remove(&(block - days)).is_some() {
+                let cutoff = block - days;
+                let start = bucket_start(cutoff, days);
+                let highs = self.bucket_highs.get_mut(&start).unwrap();
+                expire(highs, cutoff);
+                shrink_front(&mut self.highs, start, front(highs), |price, next| price <= next);
+                let lows = self.bucket_lows.get_mut(&start).unwrap();
+                expire(lows, cutoff);
+                shrink_front(&mut self.lows, start, front(lows), |price, next| price >= next);
+            }
+
+            let start = bucket_start(block, days);
+            for (price, _) in trades {
+                self.traded.insert(block, ());
+                push_max(self.bucket_highs.entry(start).or_default(), block, *price);
+                push_min(self.bucket_lows.entry(start).or_default(), block, *price);
+                push_max(&mut self.highs, start, *price);
+                push_min(&mut self.lows, start, *price);
+            }
+
+            let max_len = bucket_len(days) as usize;
+            assert!(self.highs.len() as u32 <= MaxBuckets::get() && self.lows.len() as u32 <= MaxBuckets::get(), "block {}", block);
+            assert!(self.bucket_highs.values().chain(self.bucket_lows.values()).all(|deque| deque.len() <= max_len), "block {}", block);
+            (front(&self.highs), front(&self.lows))
+        }
+    }
+
+    #[test]
+    fn bucket_start_rounds_down_to_bucket() {
+        assert_eq!(bucket_len(14_400), 120);
+        assert_eq!(bucket_len(241), 3);
+        assert_eq!(bucket_len(10), 1);
+        assert_eq!(bucket_start(359u32, 14_400), 240);
+        assert_eq!(bucket_start(360u32, 14_400), 360);
+        assert_eq!(bucket_start(7u32, 10), 7);
+    }
+
+    #[test]
+    fn shrink_front_updates_or_drops_oldest_bucket() {
+        let mut deque = vec![(0, 9), (10, 5)];
+        shrink_front(&mut deque, 0, Some(7), |price, next| price <= next);
+        assert_eq!(deque, vec![(0, 7), (10, 5)]);
+        shrink_front(&mut deque, 0, Some(5), |price, next| price <= next);
+        assert_eq!(deque, vec![(10, 5)]);
+        // bucket không phải phần tử đầu (đã bị lấn át) => không đổi
+        shrink_front(&mut deque, 0, None, |price, next| price <= next);
+        assert_eq!(deque, vec![(10, 5)]);
+        shrink_front(&mut deque, 10, None, |price, next| price <= next);
+        assert!(deque.is_empty());
+    }
+
+    #[test]
+    fn bucketed_window_matches_old_algorithm() {
+        for days in [10, 240, 361] {
+            for seed in 0..5 {
+                let mut rng = Lcg(seed);
+                let mut old = OldAlgorithm::default();
+                let mut new = Bucketed::default();
+                for block in 1..=3 * days {
+                    let trades: Vec<(u128, u128)> = (0..rng.next(4)).map(|_| (1 + rng.next(1_000) as u128, 1)).collect();
+                    let (_, high, low) = old.run_block(block, days, &trades);
+                    assert_eq!((high, low), new.run_block(block, days, &trades), "block {} days {} seed {}", block, days, seed);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn bucketed_window_stays_bounded() {
+        // giá giảm dần (deque max) và tăng dần (deque min) qua từng block là trường hợp xấu nhất: không phần tử nào bị pop khi push
+        let days = 14_400;
+        let mut new = Bucketed::default();
+        for block in 1..=3 * days {
+            let (high, low) = new.run_block(block, days, &[((10 * days - block) as u128, 1)]);
+            let oldest = block.saturating_sub(days) + 1;
+            assert_eq!((high, low), (Some((10 * days - oldest) as u128), Some((10 * days - block) as u128)));
+        }
+    }
+
+    #[test]
+    fn window_without_trades_is_empty() {
+        let mut new = RollingWindow::default();
+        for block in 1..=20 {
+            assert_eq!(new.run_block(block, 5, &[]), (0, None, None));
+        }
+    }
+
+    #[test]
+    fn rolling_window_matches_old_algorithm() {
+        for days in [1, 2, 5, 24] {
+            for seed in 0..20 {
+                compare(days, 200, seed);
+            }
+        }
+    }
+}
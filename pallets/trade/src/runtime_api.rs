@@ -0,0 +1,53 @@
+use codec::{Codec, Decode, Encode, MaxEncodedLen};
+use frame_support::{inherent::Vec, RuntimeDebug};
+use scale_info::TypeInfo;
+#[cfg(feature = "std")]
+use serde::{Deserialize, Serialize};
+
+// 1 mức giá trong order book => gộp thông tin của PriceItem tại mức giá đó
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub struct OrderBookLevel<Price, Balance> {
+    pub price: Price,               // mức giá
+    pub buy_amount: Balance,        // tổng lượng mua còn lại tại mức giá
+    pub sell_amount: Balance,       // tổng lượng bán còn lại tại mức giá
+    pub orders: u32,                // số order đang nằm tại mức giá
+}
+
+// Snapshot order book của 1 cặp trade_pair, cả 2 phía đều sắp xếp từ giá tốt nhất (gần head nhất)
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub struct OrderBook<Price, Balance> {
+    pub bids: Vec<OrderBookLevel<Price, Balance>>,   // phía mua: giá giảm dần
+    pub asks: Vec<OrderBookLevel<Price, Balance>>,   // phía bán: giá tăng dần
+}
+
+// 1 cây nến OHLCV của 1 cặp trade_pair trong khoảng [start, start + interval) block
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub struct Candle<BlockNumber, Price, Balance> {
+    pub start: BlockNumber,         // block bắt đầu của nến
+    pub open: Price,                // giá khớp đầu tiên
+    pub high: Price,                // giá khớp cao nhất
+    pub low: Price,                 // giá khớp thấp nhất
+    pub close: Price,               // giá khớp cuối cùng
+    pub base_volume: Balance,       // tổng lượng base_token đã khớp
+    pub quote_volume: Balance,      // tổng lượng quote_token đã khớp
+    pub trades: u32,                // số trade
+}
+
+sp_api::decl_runtime_apis! {
+    pub trait TradeApi<Hash, BlockNumber, Price, Balance> where
+        Hash: Codec,
+        BlockNumber: Codec,
+        Price: Codec,
+        Balance: Codec,
+    {
+        // trả về tối đa `depth` mức giá mỗi phía của cặp tp_hash
+        fn order_book(tp_hash: Hash, depth: u32) -> OrderBook<Price, Balance>;
+        // trả về tối đa `limit` cây nến mới nhất của cặp tp_hash tại interval (tính bằng block), cũ trước mới sau
+        fn candles(tp_hash: Hash, interval: u32, limit: u32) -> Vec<Candle<BlockNumber, Price, Balance>>;
+        // giá trung bình theo thời gian (TWAP) của cặp tp_hash trong window block gần nhất
+        fn twap(tp_hash: Hash, window: BlockNumber) -> Option<Price>;
+    }
+}
@@ -0,0 +1,1492 @@
+use crate::{mock::*, runtime_api::Candle, BatchMode, Error, WeightInfo, Event, LinkedItemList, MarketOrderAmount, OneDayStatsExpiry, Order, OrderOpt, OrderSpec, OrderStatus, OrderType, Orders, SelfTradePrevention, StopOrderStatus, TimeInForce, TradePairStatus};
+use codec::{Decode, Encode};
+use frame_support::{assert_noop, assert_ok, dispatch::DispatchResultWithPostInfo};
+use sp_core::H256;
+use sp_runtime::{DispatchError, Permill};
+
+// đặt lệnh limit GTC, không post-only, không hết hạn
+fn limit_order(who: AccountId, base: H256, quote: H256, otype: OrderType, price: Price, sell_amount: Balance) -> DispatchResultWithPostInfo {
+    TradeModule::create_order(RuntimeOrigin::signed(who), base, quote, OrderOpt::Limit, otype, price, sell_amount, TimeInForce::GoodTilCanceled, false, None, None)
+}
+
+// order được tạo gần nhất của account
+fn last_order(who: AccountId) -> Order<Test> {
+    let index = TradeModule::owned_orders_index(who);
+    let hash = TradeModule::owned_orders(who, index - 1).unwrap();
+    TradeModule::orders(hash).unwrap()
+}
+
+// trade thứ index của order
+fn order_trade(order_hash: H256, index: u64) -> crate::Trade<Test> {
+    TradeModule::trades(TradeModule::order_owned_trades(order_hash, index).unwrap()).unwrap()
+}
+
+// (balance, free, freezed) của account với token
+fn balances(who: AccountId, token: H256) -> (Balance, Balance, Balance) {
+    (Tokens::balance_of(who, token), Tokens::free_balance_of(who, token), Tokens::freezed_balance_of(who, token))
+}
+
+#[test]
+fn create_trade_pair_works() {
+    new_test_ext().execute_with(|| {
+        let (busd, btc, tp_hash) = setup_trade_pair();
+
+        let tp = TradeModule::trade_pairs(tp_hash).unwrap();
+        assert_eq!((tp.hash, tp.base, tp.quote), (tp_hash, busd, btc));
+        assert_eq!(tp.latest_matched_price, None);
+        assert_eq!(tp.one_day_trade_volume, 0);
+        assert_eq!((tp.tick_size, tp.lot_size, tp.min_notional), (1, 1, 0));
+        assert_eq!(tp.status, TradePairStatus::Active);
+
+        assert_eq!(TradeModule::trade_pair_index(), 1);
+        assert_eq!(TradeModule::trade_pair_hash_by_index(0), Some(tp_hash));
+
+        // người tạo cặp bị reserve tiền cọc
+        assert_eq!(Balances::reserved_balance(ALICE), 100);
+        assert_eq!(TradeModule::trade_pair_deposits(tp_hash), Some((ALICE, 100)));
+
+        System::assert_has_event(RuntimeEvent::TradeModule(Event::TradePairCreated { owner: ALICE, hash: tp_hash, trade_pair: tp }));
+    });
+}
+
+#[test]
+fn create_trade_pair_fails() {
+    new_test_ext().execute_with(|| {
+        let (busd, btc, _) = setup_trade_pair();
+
+        assert_noop!(TradeModule::create_trade_pair(RuntimeOrigin::signed(ALICE), busd, busd), Error::<Test>::BaseEqualQuote);
+        assert_noop!(TradeModule::create_trade_pair(RuntimeOrigin::signed(ALICE), busd, H256::zero()), Error::<Test>::TokenOwnerNotFound);
+        assert_noop!(TradeModule::create_trade_pair(RuntimeOrigin::signed(BOB), busd, btc), Error::<Test>::TradePairExisted);
+        assert_noop!(TradeModule::create_trade_pair(RuntimeOrigin::signed(BOB), btc, busd), Error::<Test>::TradePairExisted);
+
+        // account không có native token => không đủ tiền cọc
+        Tokens::issue(RuntimeOrigin::signed(ALICE), b"ETH".to_vec(), 1_000).unwrap();
+        let eth = Tokens::owned_token_index(ALICE, 1).unwrap();
+        assert_noop!(TradeModule::create_trade_pair(RuntimeOrigin::signed(4), busd, eth), pallet_balances::Error::<Test>::InsufficientBalance);
+
+        // symbol dài hơn MaxSymbolLength
+        assert_noop!(Tokens::issue(RuntimeOrigin::signed(ALICE), b"TOOLONGSYM".to_vec(), 1_000), pallet_tokens::Error::<Test>::SymbolTooLong);
+    });
+}
+
+#[test]
+fn limit_order_rests_on_book_and_freezes_balance() {
+    new_test_ext().execute_with(|| {
+        let (busd, btc, tp_hash) = setup_trade_pair();
+
+        // mua 100 BTC giá 2 => bán 200 BUSD
+        assert_ok!(limit_order(ALICE, busd, btc, OrderType::Buy, price(2), 200));
+
+        let order = last_order(ALICE);
+        assert_eq!((order.sell_amount, order.buy_amount), (200, 100));
+        assert_eq!(order.status, OrderStatus::Created);
+        assert_eq!(balances(ALICE, busd), (INITIAL_TOKENS, INITIAL_TOKENS - 200, 200));
+        assert_eq!(TradeModule::owned_tp_opened_orders((ALICE, tp_hash)).map(|orders| orders.into_inner()), Some(vec![order.hash]));
+
+        let book = TradeModule::order_book(tp_hash, 10);
+        assert!(book.asks.is_empty());
+        assert_eq!(book.bids.len(), 1);
+        assert_eq!((book.bids[0].price, book.bids[0].buy_amount, book.bids[0].sell_amount, book.bids[0].orders), (price(2), 100, 200, 1));
+    });
+}
+
+#[test]
+fn limit_order_fails_without_exact_counterparty_amount() {
+    new_test_ext().execute_with(|| {
+        let (busd, btc, _) = setup_trade_pair();
+
+        // bán 3 BTC giá 1.5 => 4.5 BUSD, không chia hết
+        assert_noop!(limit_order(ALICE, busd, btc, OrderType::Sell, 150_000_000, 3), Error::<Test>::BoundsCheckFailedAmount2);
+        assert_noop!(limit_order(ALICE, busd, btc, OrderType::Buy, 0, 200), Error::<Test>::BoundsCheckFailedPrice);
+        assert_noop!(limit_order(ALICE, busd, btc, OrderType::Buy, price(2), 0), Error::<Test>::BoundsCheckFailedAmount);
+        assert_noop!(limit_order(ALICE, busd, btc, OrderType::Buy, price(2), INITIAL_TOKENS * 2), pallet_tokens::Error::<Test>::BalanceNotEnough);
+    });
+}
+
+#[test]
+fn sell_taker_partially_fills_buy_maker() {
+    new_test_ext().execute_with(|| {
+        let (busd, btc, tp_hash) = setup_trade_pair();
+
+        assert_ok!(limit_order(ALICE, busd, btc, OrderType::Buy, price(2), 200));
+        let maker = last_order(ALICE);
+        assert_ok!(limit_order(BOB, busd, btc, OrderType::Sell, price(2), 40));
+        let taker = last_order(BOB);
+
+        assert_eq!(taker.status, OrderStatus::Filled);
+        assert_eq!((taker.remained_sell_amount, taker.remained_buy_amount), (0, 0));
+
+        let maker = TradeModule::orders(maker.hash).unwrap();
+        assert_eq!(maker.status, OrderStatus::PartialFilled);
+        assert_eq!((maker.remained_sell_amount, maker.remained_buy_amount), (120, 60));
+
+        let trade = order_trade(taker.hash, 0);
+        assert_eq!((trade.buyer, trade.seller, trade.maker, trade.taker), (ALICE, BOB, ALICE, BOB));
+        assert_eq!((trade.price, trade.base_amount, trade.quote_amount), (price(2), 80, 40));
+        assert_eq!(order_trade(maker.hash, 0), trade);
+
+        assert_eq!(balances(ALICE, busd), (INITIAL_TOKENS - 80, INITIAL_TOKENS - 200, 120));
+        assert_eq!(balances(ALICE, btc), (INITIAL_TOKENS + 40, INITIAL_TOKENS + 40, 0));
+        assert_eq!(balances(BOB, busd), (INITIAL_TOKENS + 80, INITIAL_TOKENS + 80, 0));
+        assert_eq!(balances(BOB, btc), (INITIAL_TOKENS - 40, INITIAL_TOKENS - 40, 0));
+
+        let book = TradeModule::order_book(tp_hash, 10);
+        assert!(book.asks.is_empty());
+        assert_eq!((book.bids[0].buy_amount, book.bids[0].sell_amount), (60, 120));
+
+        let tp = TradeModule::trade_pairs(tp_hash).unwrap();
+        assert_eq!(tp.latest_matched_price, Some(price(2)));
+    });
+}
+
+#[test]
+fn buy_taker_partially_fills_sell_maker() {
+    new_test_ext().execute_with(|| {
+        let (busd, btc, tp_hash) = setup_trade_pair();
+
+        // bán 50 BTC giá 3 => nhận 150 BUSD
+        assert_ok!(limit_order(ALICE, busd, btc, OrderType::Sell, price(3), 50));
+        let maker = last_order(ALICE);
+        // mua 20 BTC giá 3 => bán 60 BUSD
+        assert_ok!(limit_order(BOB, busd, btc, OrderType::Buy, price(3), 60));
+        let taker = last_order(BOB);
+
+        assert_eq!(taker.status, OrderStatus::Filled);
+        let maker = TradeModule::orders(maker.hash).unwrap();
+        assert_eq!(maker.status, OrderStatus::PartialFilled);
+        assert_eq!((maker.remained_sell_amount, maker.remained_buy_amount), (30, 90));
+
+        let trade = order_trade(taker.hash, 0);
+        assert_eq!((trade.buyer, trade.seller, trade.maker, trade.taker), (BOB, ALICE, ALICE, BOB));
+        assert_eq!((trade.base_amount, trade.quote_amount), (60, 20));
+
+        assert_eq!(balances(ALICE, btc), (INITIAL_TOKENS - 20, INITIAL_TOKENS - 50, 30));
+        assert_eq!(balances(ALICE, busd), (INITIAL_TOKENS + 60, INITIAL_TOKENS + 60, 0));
+        assert_eq!(balances(BOB, busd), (INITIAL_TOKENS - 60, INITIAL_TOKENS - 60, 0));
+        assert_eq!(balances(BOB, btc), (INITIAL_TOKENS + 20, INITIAL_TOKENS + 20, 0));
+
+        let book = TradeModule::order_book(tp_hash, 10);
+        assert!(book.bids.is_empty());
+        assert_eq!((book.asks[0].price, book.asks[0].sell_amount, book.asks[0].buy_amount), (price(3), 30, 90));
+    });
+}
+
+#[test]
+fn complete_fill_closes_both_orders() {
+    new_test_ext().execute_with(|| {
+        let (busd, btc, tp_hash) = setup_trade_pair();
+
+        assert_ok!(limit_order(ALICE, busd, btc, OrderType::Buy, price(2), 200));
+        let maker = last_order(ALICE);
+        assert_ok!(limit_order(BOB, busd, btc, OrderType::Sell, price(2), 100));
+        let taker = last_order(BOB);
+
+        let maker = TradeModule::orders(maker.hash).unwrap();
+        assert_eq!(maker.status, OrderStatus::Filled);
+        assert_eq!(taker.status, OrderStatus::Filled);
+        assert!(maker.is_finished() && taker.is_finished());
+
+        let trade = order_trade(taker.hash, 0);
+        assert_eq!((trade.base_amount, trade.quote_amount), (200, 100));
+
+        assert_eq!(balances(ALICE, busd), (INITIAL_TOKENS - 200, INITIAL_TOKENS - 200, 0));
+        assert_eq!(balances(BOB, btc), (INITIAL_TOKENS - 100, INITIAL_TOKENS - 100, 0));
+
+        let book = TradeModule::order_book(tp_hash, 10);
+        assert!(book.bids.is_empty() && book.asks.is_empty());
+
+        assert_eq!(TradeModule::owned_tp_opened_orders((ALICE, tp_hash)).map(|orders| orders.into_inner()), Some(vec![]));
+        assert_eq!(TradeModule::owned_tp_closed_orders((ALICE, tp_hash)).map(|orders| orders.into_inner()), Some(vec![maker.hash]));
+        assert_eq!(TradeModule::owned_tp_closed_orders((BOB, tp_hash)).map(|orders| orders.into_inner()), Some(vec![taker.hash]));
+    });
+}
+
+#[test]
+fn create_order_refunds_weight_of_unused_fills() {
+    new_test_ext().execute_with(|| {
+        let (busd, btc, _) = setup_trade_pair();
+
+        // không khớp => chỉ tính weight của việc đặt lệnh
+        let info = limit_order(ALICE, busd, btc, OrderType::Buy, price(1), 100).unwrap();
+        assert_eq!(info.actual_weight, Some(<() as WeightInfo>::create_order(0, 0)));
+
+        assert_ok!(limit_order(ALICE, busd, btc, OrderType::Buy, price(2), 200));
+
+        // khớp 2 order trên 2 mức giá, mỗi order khớp cập nhật nến ở 2 interval
+        let info = limit_order(BOB, busd, btc, OrderType::Sell, price(1), 300).unwrap();
+        let candles = <() as WeightInfo>::update_candle().saturating_mul(2 * 2);
+        assert_eq!(info.actual_weight, Some(<() as WeightInfo>::create_order(2, 2).saturating_add(candles)));
+        assert!(info.actual_weight.unwrap().ref_time() < TradeModule::max_create_order_weight().ref_time());
+    });
+}
+
+#[test]
+fn taker_remainder_rests_after_filling_maker() {
+    new_test_ext().execute_with(|| {
+        let (busd, btc, tp_hash) = setup_trade_pair();
+
+        assert_ok!(limit_order(ALICE, busd, btc, OrderType::Buy, price(2), 200));
+        let maker = last_order(ALICE);
+        assert_ok!(limit_order(BOB, busd, btc, OrderType::Sell, price(2), 150));
+        let taker = last_order(BOB);
+
+        assert_eq!(TradeModule::orders(maker.hash).unwrap().status, OrderStatus::Filled);
+        assert_eq!(taker.status, OrderStatus::PartialFilled);
+        assert_eq!((taker.remained_sell_amount, taker.remained_buy_amount), (50, 100));
+        assert_eq!(balances(BOB, btc), (INITIAL_TOKENS - 100, INITIAL_TOKENS - 150, 50));
+
+        // phần còn dư của taker nằm trên sổ lệnh phía bán
+        let book = TradeModule::order_book(tp_hash, 10);
+        assert!(book.bids.is_empty());
+        assert_eq!(book.asks.len(), 1);
+        assert_eq!((book.asks[0].price, book.asks[0].sell_amount, book.asks[0].buy_amount), (price(2), 50, 100));
+    });
+}
+
+#[test]
+fn buy_order_sweeps_multiple_ask_levels() {
+    new_test_ext().execute_with(|| {
+        let (busd, btc, tp_hash) = setup_trade_pair();
+
+        assert_ok!(limit_order(ALICE, busd, btc, OrderType::Sell, price(1), 10));
+        let ask_1 = last_order(ALICE);
+        assert_ok!(limit_order(ALICE, busd, btc, OrderType::Sell, price(2), 10));
+        let ask_2 = last_order(ALICE);
+        assert_ok!(limit_order(CHARLIE, busd, btc, OrderType::Sell, price(3), 10));
+        let ask_3 = last_order(CHARLIE);
+
+        // mua 20 BTC giá tối đa 3 => khớp hết mức 1 và mức 2, giá khớp là giá của maker
+        assert_ok!(limit_order(BOB, busd, btc, OrderType::Buy, price(3), 60));
+        let taker = last_order(BOB);
+
+        assert_eq!(taker.status, OrderStatus::Filled);
+        assert_eq!(TradeModule::order_owned_trades_index(taker.hash), 2);
+        let trade_1 = order_trade(taker.hash, 0);
+        let trade_2 = order_trade(taker.hash, 1);
+        assert_eq!((trade_1.price, trade_1.base_amount, trade_1.quote_amount), (price(1), 10, 10));
+        assert_eq!((trade_2.price, trade_2.base_amount, trade_2.quote_amount), (price(2), 20, 10));
+
+        assert_eq!(TradeModule::orders(ask_1.hash).unwrap().status, OrderStatus::Filled);
+        assert_eq!(TradeModule::orders(ask_2.hash).unwrap().status, OrderStatus::Filled);
+        assert_eq!(TradeModule::orders(ask_3.hash).unwrap().status, OrderStatus::Created);
+
+        // BOB chỉ trả 30 BUSD, phần freeze còn dư được unfreeze
+        assert_eq!(balances(BOB, busd), (INITIAL_TOKENS - 30, INITIAL_TOKENS - 30, 0));
+        assert_eq!(balances(BOB, btc), (INITIAL_TOKENS + 20, INITIAL_TOKENS + 20, 0));
+        assert_eq!(balances(ALICE, busd), (INITIAL_TOKENS + 30, INITIAL_TOKENS + 30, 0));
+
+        let book = TradeModule::order_book(tp_hash, 10);
+        assert_eq!(book.asks.len(), 1);
+        assert_eq!(book.asks[0].price, price(3));
+
+        let tp = TradeModule::trade_pairs(tp_hash).unwrap();
+        assert_eq!(tp.latest_matched_price, Some(price(2)));
+        assert_eq!((tp.one_day_trade_volume, tp.one_day_highest_price, tp.one_day_lowest_price), (20, Some(price(2)), Some(price(1))));
+    });
+}
+
+#[test]
+fn sell_order_sweeps_multiple_bid_levels() {
+    new_test_ext().execute_with(|| {
+        let (busd, btc, tp_hash) = setup_trade_pair();
+
+        assert_ok!(limit_order(ALICE, busd, btc, OrderType::Buy, price(3), 30));
+        let bid_3 = last_order(ALICE);
+        assert_ok!(limit_order(CHARLIE, busd, btc, OrderType::Buy, price(2), 20));
+        let bid_2 = last_order(CHARLIE);
+
+        // bán BTC để nhận 40 BUSD, giá tối thiểu 2 => khớp từ giá mua cao nhất xuống
+        assert_ok!(limit_order(BOB, busd, btc, OrderType::Sell, price(2), 20));
+        let taker = last_order(BOB);
+
+        assert_eq!(taker.status, OrderStatus::Filled);
+        let trade_1 = order_trade(taker.hash, 0);
+        let trade_2 = order_trade(taker.hash, 1);
+        assert_eq!((trade_1.price, trade_1.base_amount, trade_1.quote_amount), (price(3), 30, 10));
+        assert_eq!((trade_2.price, trade_2.base_amount, trade_2.quote_amount), (price(2), 10, 5));
+
+        assert_eq!(TradeModule::orders(bid_3.hash).unwrap().status, OrderStatus::Filled);
+        let bid_2 = TradeModule::orders(bid_2.hash).unwrap();
+        assert_eq!(bid_2.status, OrderStatus::PartialFilled);
+        assert_eq!((bid_2.remained_sell_amount, bid_2.remained_buy_amount), (10, 5));
+
+        assert_eq!(balances(BOB, btc), (INITIAL_TOKENS - 15, INITIAL_TOKENS - 15, 0));
+        assert_eq!(balances(BOB, busd), (INITIAL_TOKENS + 40, INITIAL_TOKENS + 40, 0));
+
+        let book = TradeModule::order_book(tp_hash, 10);
+        assert!(book.asks.is_empty());
+        assert_eq!(book.bids.len(), 1);
+        assert_eq!((book.bids[0].price, book.bids[0].sell_amount, book.bids[0].buy_amount), (price(2), 10, 5));
+    });
+}
+
+#[test]
+fn cancel_limit_order_unfreezes_remainder() {
+    new_test_ext().execute_with(|| {
+        let (busd, btc, tp_hash) = setup_trade_pair();
+
+        assert_ok!(limit_order(ALICE, busd, btc, OrderType::Buy, price(2), 200));
+        let order = last_order(ALICE);
+        assert_ok!(limit_order(BOB, busd, btc, OrderType::Sell, price(2), 40));
+
+        assert_ok!(TradeModule::cancel_limit_order(RuntimeOrigin::signed(ALICE), order.hash));
+
+        let order = TradeModule::orders(order.hash).unwrap();
+        assert_eq!(order.status, OrderStatus::Canceled);
+        assert!(order.is_finished());
+        // chỉ phần còn dư (120 BUSD) được unfreeze, 80 BUSD đã khớp
+        assert_eq!(balances(ALICE, busd), (INITIAL_TOKENS - 80, INITIAL_TOKENS - 80, 0));
+
+        let book = TradeModule::order_book(tp_hash, 10);
+        assert!(book.bids.is_empty() && book.asks.is_empty());
+        assert_eq!(TradeModule::owned_tp_opened_orders((ALICE, tp_hash)).map(|orders| orders.into_inner()), Some(vec![]));
+        assert_eq!(TradeModule::owned_tp_closed_orders((ALICE, tp_hash)).map(|orders| orders.into_inner()), Some(vec![order.hash]));
+
+        System::assert_last_event(RuntimeEvent::TradeModule(Event::OrderCanceled { owner: ALICE, order_hash: order.hash }));
+    });
+}
+
+#[test]
+fn cancel_limit_order_fails() {
+    new_test_ext().execute_with(|| {
+        let (busd, btc, _) = setup_trade_pair();
+
+        assert_noop!(TradeModule::cancel_limit_order(RuntimeOrigin::signed(ALICE), H256::zero()), Error::<Test>::NoMatchingOrder);
+
+        assert_ok!(limit_order(ALICE, busd, btc, OrderType::Buy, price(2), 200));
+        let order = last_order(ALICE);
+        assert_noop!(TradeModule::cancel_limit_order(RuntimeOrigin::signed(BOB), order.hash), Error::<Test>::CanOnlyCancelOwnOrder);
+
+        assert_ok!(limit_order(BOB, busd, btc, OrderType::Sell, price(2), 100));
+        assert_noop!(TradeModule::cancel_limit_order(RuntimeOrigin::signed(ALICE), order.hash), Error::<Test>::CanOnlyCancelNotFinishedOrder);
+    });
+}
+
+#[test]
+fn force_cancel_order_requires_admin_and_unfreezes_remainder() {
+    new_test_ext().execute_with(|| {
+        let (busd, btc, tp_hash) = setup_trade_pair();
+
+        assert_ok!(limit_order(ALICE, busd, btc, OrderType::Buy, price(2), 200));
+        let order = last_order(ALICE);
+        assert_ok!(limit_order(BOB, busd, btc, OrderType::Sell, price(2), 40));
+
+        // chỉ AdminOrigin (root trong mock) được hủy order của người khác, kể cả owner cũng không gọi được
+        assert_noop!(TradeModule::force_cancel_order(RuntimeOrigin::signed(BOB), order.hash), DispatchError::BadOrigin);
+        assert_noop!(TradeModule::force_cancel_order(RuntimeOrigin::signed(ALICE), order.hash), DispatchError::BadOrigin);
+        assert_noop!(TradeModule::force_cancel_order(RuntimeOrigin::root(), H256::zero()), Error::<Test>::NoMatchingOrder);
+
+        assert_ok!(TradeModule::force_cancel_order(RuntimeOrigin::root(), order.hash));
+
+        let order = TradeModule::orders(order.hash).unwrap();
+        assert_eq!(order.status, OrderStatus::Canceled);
+        // chỉ phần còn dư (120 BUSD) được unfreeze, 80 BUSD đã khớp
+        assert_eq!(balances(ALICE, busd), (INITIAL_TOKENS - 80, INITIAL_TOKENS - 80, 0));
+
+        let book = TradeModule::order_book(tp_hash, 10);
+        assert!(book.bids.is_empty() && book.asks.is_empty());
+        assert_eq!(TradeModule::owned_tp_opened_orders((ALICE, tp_hash)).map(|orders| orders.into_inner()), Some(vec![]));
+        assert_eq!(TradeModule::owned_tp_closed_orders((ALICE, tp_hash)).map(|orders| orders.into_inner()), Some(vec![order.hash]));
+        System::assert_last_event(RuntimeEvent::TradeModule(Event::OrderForceCanceled { owner: ALICE, order_hash: order.hash }));
+
+        assert_noop!(TradeModule::force_cancel_order(RuntimeOrigin::root(), order.hash), Error::<Test>::CanOnlyCancelNotFinishedOrder);
+    });
+}
+
+#[test]
+fn force_transfer_requires_admin_and_moves_only_free_balance() {
+    new_test_ext().execute_with(|| {
+        let (busd, btc, tp_hash) = setup_trade_pair();
+
+        // ALICE freeze 200 BUSD trên sổ lệnh
+        assert_ok!(limit_order(ALICE, busd, btc, OrderType::Buy, price(2), 200));
+        let free = INITIAL_TOKENS - 200;
+
+        assert_noop!(Tokens::force_transfer(RuntimeOrigin::signed(ALICE), ALICE, DAVE, busd, 100), DispatchError::BadOrigin);
+        assert_noop!(Tokens::force_transfer(RuntimeOrigin::signed(DAVE), ALICE, DAVE, busd, 100), DispatchError::BadOrigin);
+        assert_noop!(Tokens::force_transfer(RuntimeOrigin::root(), ALICE, ALICE, busd, 100), pallet_tokens::Error::<Test>::MustNotTransferYourself);
+        // phần đang freeze không bị chuyển đi
+        assert_noop!(Tokens::force_transfer(RuntimeOrigin::root(), ALICE, DAVE, busd, free + 1), pallet_tokens::Error::<Test>::BalanceNotEnough);
+
+        assert_ok!(Tokens::force_transfer(RuntimeOrigin::root(), ALICE, DAVE, busd, free));
+        assert_eq!(balances(ALICE, busd), (200, 0, 200));
+        assert_eq!(balances(DAVE, busd), (free, free, 0));
+        System::assert_last_event(RuntimeEvent::Tokens(pallet_tokens::Event::ForceTransferred { from: ALICE, to: DAVE, token_hash: busd, amount: free }));
+
+        // order của ALICE vẫn nằm trên sổ lệnh và vẫn khớp được bằng phần đã freeze
+        let book = TradeModule::order_book(tp_hash, 10);
+        assert_eq!((book.bids[0].price, book.bids[0].sell_amount, book.bids[0].orders), (price(2), 200, 1));
+        assert_ok!(limit_order(BOB, busd, btc, OrderType::Sell, price(2), 100));
+        assert_eq!(last_order(ALICE).status, OrderStatus::Filled);
+        assert_eq!(balances(ALICE, busd), (0, 0, 0));
+        assert_eq!(balances(ALICE, btc), (INITIAL_TOKENS + 100, INITIAL_TOKENS + 100, 0));
+    });
+}
+
+#[test]
+fn cancel_respects_trade_pair_status() {
+    new_test_ext().execute_with(|| {
+        let (busd, btc, tp_hash) = setup_trade_pair();
+
+        assert_ok!(limit_order(ALICE, busd, btc, OrderType::Buy, price(2), 200));
+        let order = last_order(ALICE);
+
+        assert_noop!(TradeModule::set_trade_pair_status(RuntimeOrigin::signed(ALICE), tp_hash, TradePairStatus::Paused), sp_runtime::DispatchError::BadOrigin);
+
+        assert_ok!(TradeModule::set_trade_pair_status(RuntimeOrigin::root(), tp_hash, TradePairStatus::Paused));
+        assert_noop!(TradeModule::cancel_limit_order(RuntimeOrigin::signed(ALICE), order.hash), Error::<Test>::TradePairPaused);
+
+        // CancelOnly => không đặt được order mới nhưng vẫn hủy được
+        assert_ok!(TradeModule::set_trade_pair_status(RuntimeOrigin::root(), tp_hash, TradePairStatus::CancelOnly));
+        assert_noop!(limit_order(BOB, busd, btc, OrderType::Sell, price(2), 100), Error::<Test>::TradePairNotActive);
+        assert_ok!(TradeModule::cancel_limit_order(RuntimeOrigin::signed(ALICE), order.hash));
+    });
+}
+
+#[test]
+fn order_limits_are_enforced() {
+    new_test_ext().execute_with(|| {
+        let (busd, btc, tp_hash) = setup_trade_pair();
+
+        // mức giá 2 đã đủ MaxOrdersPerPriceLevel order
+        for _ in 0..10 {
+            assert_ok!(limit_order(ALICE, busd, btc, OrderType::Buy, price(2), 200));
+        }
+        assert_noop!(limit_order(BOB, busd, btc, OrderType::Buy, price(2), 200), Error::<Test>::PriceLevelFull);
+        assert_eq!(LinkedItemList::<Test>::get(tp_hash, Some(price(2))).unwrap().orders.len(), 10);
+
+        // ALICE đã có OpenedOrdersArrayCap order đang mở trong cặp
+        for _ in 0..10 {
+            assert_ok!(limit_order(ALICE, busd, btc, OrderType::Buy, price(1), 100));
+        }
+        assert_noop!(limit_order(ALICE, busd, btc, OrderType::Buy, price(3), 300), Error::<Test>::TooManyOpenOrders);
+
+        // hủy bớt 1 order thì đặt lại được
+        let order = last_order(ALICE);
+        assert_ok!(TradeModule::cancel_limit_order(RuntimeOrigin::signed(ALICE), order.hash));
+        assert_ok!(limit_order(ALICE, busd, btc, OrderType::Buy, price(3), 300));
+    });
+}
+
+#[test]
+fn rounding_gives_extra_base_to_sell_maker() {
+    new_test_ext().execute_with(|| {
+        let (busd, btc, _) = setup_trade_pair();
+
+        // bán 2 BTC giá 1.5 => nhận 3 BUSD
+        assert_ok!(limit_order(ALICE, busd, btc, OrderType::Sell, 150_000_000, 2));
+        let maker = last_order(ALICE);
+        // mua 1 BTC giá 2 => 1 BTC ở giá maker là 1.5 BUSD, phần lẻ được làm tròn lên => trả 2 BUSD
+        assert_ok!(limit_order(BOB, busd, btc, OrderType::Buy, price(2), 2));
+        let taker = last_order(BOB);
+
+        let trade = order_trade(taker.hash, 0);
+        assert_eq!((trade.base_amount, trade.quote_amount), (2, 1));
+        assert_eq!(taker.status, OrderStatus::Filled);
+
+        let maker = TradeModule::orders(maker.hash).unwrap();
+        assert_eq!(maker.status, OrderStatus::PartialFilled);
+        assert_eq!((maker.remained_sell_amount, maker.remained_buy_amount), (1, 1));
+
+        assert_eq!(balances(BOB, busd), (INITIAL_TOKENS - 2, INITIAL_TOKENS - 2, 0));
+        assert_eq!(balances(ALICE, btc), (INITIAL_TOKENS - 1, INITIAL_TOKENS - 2, 1));
+    });
+}
+
+#[test]
+fn rounding_gives_extra_quote_to_buy_maker() {
+    new_test_ext().execute_with(|| {
+        let (busd, btc, _) = setup_trade_pair();
+
+        // mua 2 BTC giá 1.5 => bán 3 BUSD
+        assert_ok!(limit_order(ALICE, busd, btc, OrderType::Buy, 150_000_000, 3));
+        let maker = last_order(ALICE);
+        // bán 1 BTC để nhận 1 BUSD => ở giá maker 1 BUSD chỉ mua được 0.67 BTC, phần lẻ được làm tròn lên => bán 1 BTC
+        assert_ok!(limit_order(BOB, busd, btc, OrderType::Sell, price(1), 1));
+        let taker = last_order(BOB);
+
+        let trade = order_trade(taker.hash, 0);
+        assert_eq!((trade.base_amount, trade.quote_amount), (1, 1));
+        assert_eq!(taker.status, OrderStatus::Filled);
+        assert_eq!((taker.remained_sell_amount, taker.remained_buy_amount), (0, 0));
+
+        let maker = TradeModule::orders(maker.hash).unwrap();
+        assert_eq!(maker.status, OrderStatus::PartialFilled);
+        assert_eq!((maker.remained_sell_amount, maker.remained_buy_amount), (2, 1));
+
+        assert_eq!(balances(ALICE, busd), (INITIAL_TOKENS - 1, INITIAL_TOKENS - 3, 2));
+        assert_eq!(balances(ALICE, btc), (INITIAL_TOKENS + 1, INITIAL_TOKENS + 1, 0));
+    });
+}
+
+#[test]
+fn filled_maker_leftover_is_removed_from_price_item() {
+    new_test_ext().execute_with(|| {
+        let (busd, btc, tp_hash) = setup_trade_pair();
+
+        // 2 lệnh mua 2 BTC giá 1.5 cùng mức giá => mức giá có 6 BUSD
+        assert_ok!(limit_order(ALICE, busd, btc, OrderType::Buy, 150_000_000, 3));
+        let maker = last_order(ALICE);
+        assert_ok!(limit_order(CHARLIE, busd, btc, OrderType::Buy, 150_000_000, 3));
+
+        // 2 lần bán 1 BTC => mỗi lần ALICE trả 1 BUSD, ALICE khớp hết và còn dư 1 BUSD được unfreeze
+        assert_ok!(limit_order(BOB, busd, btc, OrderType::Sell, price(1), 1));
+        assert_ok!(limit_order(BOB, busd, btc, OrderType::Sell, price(1), 1));
+
+        let maker = TradeModule::orders(maker.hash).unwrap();
+        assert_eq!(maker.status, OrderStatus::Filled);
+        assert_eq!((maker.remained_sell_amount, maker.remained_buy_amount), (0, 0));
+        assert_eq!(balances(ALICE, busd), (INITIAL_TOKENS - 2, INITIAL_TOKENS - 2, 0));
+
+        // mức giá chỉ còn lệnh của CHARLIE, phần dư của ALICE không được tính vào
+        let item = TradeModule::linked_item(tp_hash, Some(150_000_000)).unwrap();
+        assert_eq!(item.orders, vec![TradeModule::owned_orders(CHARLIE, 0).unwrap()]);
+        assert_eq!((item.sell_amount, item.buy_amount), (3, 2));
+    });
+}
+
+#[test]
+fn fees_are_charged_to_maker_and_taker() {
+    new_test_ext().execute_with(|| {
+        MakerFee::set(Permill::from_percent(1));
+        TakerFee::set(Permill::from_percent(2));
+        let (busd, btc, _) = setup_trade_pair();
+
+        assert_ok!(limit_order(ALICE, busd, btc, OrderType::Buy, price(2), 200));
+        assert_ok!(limit_order(BOB, busd, btc, OrderType::Sell, price(2), 100));
+
+        // maker ALICE nhận 100 BTC => phí 1 BTC, taker BOB nhận 200 BUSD => phí 4 BUSD
+        let trade = order_trade(last_order(BOB).hash, 0);
+        assert_eq!((trade.maker_fee, trade.taker_fee), (1, 4));
+        assert_eq!(Tokens::balance_of(FEE_COLLECTOR, btc), 1);
+        assert_eq!(Tokens::balance_of(FEE_COLLECTOR, busd), 4);
+        assert_eq!(Tokens::balance_of(ALICE, btc), INITIAL_TOKENS + 99);
+        assert_eq!(Tokens::balance_of(BOB, busd), INITIAL_TOKENS + 196);
+    });
+}
+
+#[test]
+fn trade_pair_rules_are_enforced() {
+    new_test_ext().execute_with(|| {
+        let (busd, btc, tp_hash) = setup_trade_pair();
+
+        assert_noop!(TradeModule::update_trade_pair_rules(RuntimeOrigin::root(), tp_hash, 0, 1, 0), Error::<Test>::InvalidTradePairRules);
+        assert_ok!(TradeModule::update_trade_pair_rules(RuntimeOrigin::root(), tp_hash, price(1), 10, 100));
+
+        assert_noop!(limit_order(ALICE, busd, btc, OrderType::Sell, 150_000_000, 100), Error::<Test>::PriceNotMultipleOfTickSize);
+        assert_noop!(limit_order(ALICE, busd, btc, OrderType::Sell, price(2), 55), Error::<Test>::AmountNotMultipleOfLotSize);
+        assert_noop!(limit_order(ALICE, busd, btc, OrderType::Sell, price(2), 40), Error::<Test>::BelowMinNotional);
+        assert_ok!(limit_order(ALICE, busd, btc, OrderType::Sell, price(2), 50));
+    });
+}
+
+#[test]
+fn one_day_stats_expire_after_blocks_per_day() {
+    new_test_ext().execute_with(|| {
+        let (busd, btc, tp_hash) = setup_trade_pair();
+
+        // block 1: khớp 40 BTC giá 2
+        assert_ok!(limit_order(ALICE, busd, btc, OrderType::Buy, price(2), 80));
+        assert_ok!(limit_order(BOB, busd, btc, OrderType::Sell, price(2), 40));
+        assert_eq!(OneDayStatsExpiry::<Test>::iter_key_prefix(11).collect::<Vec<_>>(), vec![tp_hash]);
+        assert_eq!(TradeModule::trade_pair_trade_data_bucket((tp_hash, 1)), Some((40, Some(price(2)), Some(price(2)))));
+
+        // block 4: khớp 10 BTC giá 3
+        run_to_block(4);
+        assert_ok!(limit_order(ALICE, busd, btc, OrderType::Sell, price(3), 10));
+        assert_ok!(limit_order(BOB, busd, btc, OrderType::Buy, price(3), 30));
+
+        let tp = TradeModule::trade_pairs(tp_hash).unwrap();
+        assert_eq!((tp.one_day_trade_volume, tp.one_day_highest_price, tp.one_day_lowest_price), (50, Some(price(3)), Some(price(2))));
+
+        // block 10: block 1 vẫn còn trong cửa sổ 24h
+        run_to_block(10);
+        let tp = TradeModule::trade_pairs(tp_hash).unwrap();
+        assert_eq!(tp.one_day_trade_volume, 50);
+
+        // block 11: block 1 ra khỏi cửa sổ
+        run_to_block(11);
+        let tp = TradeModule::trade_pairs(tp_hash).unwrap();
+        assert_eq!((tp.one_day_trade_volume, tp.one_day_highest_price, tp.one_day_lowest_price), (10, Some(price(3)), Some(price(3))));
+        assert_eq!(TradeModule::trade_pair_trade_data_bucket((tp_hash, 1)), None);
+        assert_eq!(OneDayStatsExpiry::<Test>::iter_key_prefix(11).count(), 0);
+
+        // block 14: không còn trade nào trong cửa sổ, giá khớp gần nhất vẫn giữ nguyên
+        run_to_block(14);
+        let tp = TradeModule::trade_pairs(tp_hash).unwrap();
+        assert_eq!((tp.one_day_trade_volume, tp.one_day_highest_price, tp.one_day_lowest_price), (0, None, None));
+        assert_eq!(tp.latest_matched_price, Some(price(3)));
+    });
+}
+
+#[test]
+fn one_day_stats_schedule_expiry_once_per_block() {
+    new_test_ext().execute_with(|| {
+        let (busd, btc, tp_hash) = setup_trade_pair();
+
+        assert_ok!(limit_order(ALICE, busd, btc, OrderType::Buy, price(2), 200));
+        assert_ok!(limit_order(BOB, busd, btc, OrderType::Sell, price(2), 40));
+        assert_ok!(limit_order(CHARLIE, busd, btc, OrderType::Sell, price(2), 20));
+
+        assert_eq!(OneDayStatsExpiry::<Test>::iter_key_prefix(11).collect::<Vec<_>>(), vec![tp_hash]);
+        assert_eq!(TradeModule::trade_pair_trade_data_bucket((tp_hash, 1)), Some((60, Some(price(2)), Some(price(2)))));
+
+        run_to_block(11);
+        let tp = TradeModule::trade_pairs(tp_hash).unwrap();
+        assert_eq!((tp.one_day_trade_volume, tp.one_day_highest_price, tp.one_day_lowest_price), (0, None, None));
+    });
+}
+
+#[test]
+fn one_day_high_low_match_per_block_scan() {
+    new_test_ext().execute_with(|| {
+        // 360 block => mỗi bucket 3 block, cửa sổ bắt đầu giữa bucket ở 2/3 số block
+        let days = 360;
+        BlocksPerDay::set(days);
+        let (busd, btc, tp_hash) = setup_trade_pair();
+
+        // LCG để sinh giá giả ngẫu nhiên, giống nhau ở mọi lần chạy
+        let mut seed = 7u64;
+        let mut next = |max: u64| {
+            seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            (seed >> 33) % max
+        };
+
+        // thuật toán cũ: lưu giá khớp của mọi block, high/low 24h là max/min của các block trong (block - days, block]
+        let mut traded: Vec<(u64, Price)> = Vec::new();
+        let days = days as u64;
+        for block in 1..=3 * days {
+            run_to_block(block);
+
+            // khoảng 1/3 số block không có trade, block có nhiều trade để test nhiều giá cùng block
+            for _ in 0..next(4) {
+                let units = 1 + next(20) as u128;
+                assert_ok!(limit_order(BOB, busd, btc, OrderType::Sell, price(units), 1));
+                assert_ok!(limit_order(ALICE, busd, btc, OrderType::Buy, price(units), units));
+                traded.push((block, price(units)));
+            }
+
+            let window = traded.iter().filter(|(traded_at, _)| traded_at + days > block).map(|(_, price)| *price);
+            let expected = (window.clone().max(), window.min());
+            let tp = TradeModule::trade_pairs(tp_hash).unwrap();
+            assert_eq!((tp.one_day_highest_price, tp.one_day_lowest_price), expected, "block {}", block);
+        }
+
+        // hết trade trong cửa sổ => deque của mọi bucket đều bị xóa
+        run_to_block(4 * days);
+        let tp = TradeModule::trade_pairs(tp_hash).unwrap();
+        assert_eq!((tp.one_day_highest_price, tp.one_day_lowest_price), (None, None));
+        assert_eq!(crate::TPBucketHighPrices::<Test>::iter_prefix(tp_hash).count(), 0);
+        assert_eq!(crate::TPBucketLowPrices::<Test>::iter_prefix(tp_hash).count(), 0);
+    });
+}
+
+#[test]
+fn deposit_is_refunded_when_trade_pair_is_delisted() {
+    new_test_ext().execute_with(|| {
+        let (_, _, tp_hash) = setup_trade_pair();
+
+        assert_ok!(TradeModule::set_trade_pair_status(RuntimeOrigin::root(), tp_hash, TradePairStatus::Delisted));
+        assert_eq!(Balances::reserved_balance(ALICE), 0);
+        assert_eq!(TradeModule::trade_pair_deposits(tp_hash), None);
+        assert_noop!(TradeModule::set_trade_pair_status(RuntimeOrigin::root(), tp_hash, TradePairStatus::Active), Error::<Test>::TradePairDelisted);
+    });
+}
+
+#[test]
+fn delisted_trade_pair_is_swept_over_several_blocks() {
+    new_test_ext().execute_with(|| {
+        MaxDelistOrdersPerBlock::set(2);
+        let (busd, btc, tp_hash) = setup_trade_pair();
+
+        // 3 bid của ALICE, 2 ask của BOB, không order nào khớp nhau => 5 order > 2 order mỗi block
+        let mut orders = Vec::new();
+        for units in 1..=3 {
+            assert_ok!(limit_order(ALICE, busd, btc, OrderType::Buy, price(units), 100));
+            orders.push(last_order(ALICE).hash);
+        }
+        for units in 5..=6 {
+            assert_ok!(limit_order(BOB, busd, btc, OrderType::Sell, price(units), 40));
+            orders.push(last_order(BOB).hash);
+        }
+
+        assert_ok!(TradeModule::set_trade_pair_status(RuntimeOrigin::root(), tp_hash, TradePairStatus::Delisted));
+        assert!(TradeModule::delisting_trade_pairs(tp_hash).is_some());
+
+        // mỗi block hủy tối đa 2 order, phần freeze của order bị hủy được trả lại ngay trong block đó
+        for (block, canceled) in [(2, 2), (3, 4), (4, 5)] {
+            run_to_block(block);
+            let open: Vec<Order<Test>> = orders.iter().map(|hash| TradeModule::orders(hash).unwrap()).filter(|order| order.status != OrderStatus::Canceled).collect();
+            assert_eq!(orders.len() - open.len(), canceled, "block {}", block);
+
+            let freezed = |who: AccountId| open.iter().filter(|order| order.owner == who).map(|order| order.sell_amount).sum::<Balance>();
+            assert_eq!(balances(ALICE, busd), (INITIAL_TOKENS, INITIAL_TOKENS - freezed(ALICE), freezed(ALICE)), "block {}", block);
+            assert_eq!(balances(BOB, btc), (INITIAL_TOKENS, INITIAL_TOKENS - freezed(BOB), freezed(BOB)), "block {}", block);
+
+            let book = TradeModule::order_book(tp_hash, 10);
+            assert_eq!(book.bids.len() + book.asks.len(), open.len(), "block {}", block);
+            assert_eq!(TradeModule::delisting_trade_pairs(tp_hash).is_some(), !open.is_empty(), "block {}", block);
+        }
+
+        System::assert_last_event(RuntimeEvent::TradeModule(Event::TradePairDelistSwept { hash: tp_hash }));
+        assert_eq!(TradeModule::owned_tp_opened_orders((ALICE, tp_hash)).map(|orders| orders.into_inner()), Some(vec![]));
+        assert_eq!(TradeModule::owned_tp_opened_orders((BOB, tp_hash)).map(|orders| orders.into_inner()), Some(vec![]));
+        assert_ok!(TradeModule::do_try_state());
+
+        // đã sweep xong => các block sau không còn gì để làm
+        run_to_block(5);
+        assert_eq!(TradeModule::delisting_trade_pairs(tp_hash), None);
+    });
+}
+
+#[test]
+fn try_state_checks_price_list() {
+    new_test_ext().execute_with(|| {
+        let (busd, btc, tp_hash) = setup_trade_pair();
+
+        assert_ok!(limit_order(ALICE, busd, btc, OrderType::Buy, price(1), 100));
+        assert_ok!(limit_order(ALICE, busd, btc, OrderType::Buy, price(2), 200));
+        assert_ok!(limit_order(BOB, busd, btc, OrderType::Sell, price(3), 10));
+        assert_ok!(limit_order(CHARLIE, busd, btc, OrderType::Sell, price(2), 40));
+        assert_eq!(TradeModule::do_try_state(), Ok(()));
+
+        // link 1 chiều
+        let mut item = LinkedItemList::<Test>::get(tp_hash, Some(price(2))).unwrap();
+        let original = item.clone();
+        item.prev = None;
+        LinkedItemList::<Test>::insert(tp_hash, Some(price(2)), item);
+        assert_eq!(TradeModule::do_try_state(), Err("PriceItem links are not bidirectional"));
+        LinkedItemList::<Test>::insert(tp_hash, Some(price(2)), original.clone());
+
+        // PriceItem không khớp với phần còn lại của order
+        let mut item = original.clone();
+        item.sell_amount += 1;
+        LinkedItemList::<Test>::insert(tp_hash, Some(price(2)), item);
+        assert_eq!(TradeModule::do_try_state(), Err("PriceItem amounts do not match its orders"));
+        LinkedItemList::<Test>::insert(tp_hash, Some(price(2)), original.clone());
+
+        // order đã finish nhưng vẫn nằm trên sổ lệnh
+        let order_hash = original.orders[0];
+        Orders::<Test>::mutate(order_hash, |order| order.as_mut().unwrap().status = OrderStatus::Canceled);
+        assert_eq!(TradeModule::do_try_state(), Err("Finished order in PriceItem"));
+        Orders::<Test>::mutate(order_hash, |order| order.as_mut().unwrap().status = OrderStatus::PartialFilled);
+
+        // số dư bị đóng băng không đủ cho order đang mở
+        pallet_tokens::FreezedBalanceOf::<Test>::insert(ALICE, busd, 100);
+        assert_eq!(TradeModule::do_try_state(), Err("Freezed balance does not cover open orders"));
+    });
+}
+
+// đặt lệnh limit GTC hết hạn ở block expires_at
+fn expiring_order(who: AccountId, base: H256, quote: H256, otype: OrderType, price: Price, sell_amount: Balance, expires_at: u64) -> DispatchResultWithPostInfo {
+    TradeModule::create_order(RuntimeOrigin::signed(who), base, quote, OrderOpt::Limit, otype, price, sell_amount, TimeInForce::GoodTilCanceled, false, Some(expires_at), None)
+}
+
+#[test]
+fn expired_order_is_canceled_on_initialize() {
+    new_test_ext().execute_with(|| {
+        let (busd, btc, _) = setup_trade_pair();
+
+        // block hết hạn phải ở tương lai
+        assert_noop!(expiring_order(ALICE, busd, btc, OrderType::Buy, price(2), 10, 1), Error::<Test>::InvalidExpiry);
+
+        assert_ok!(expiring_order(ALICE, busd, btc, OrderType::Buy, price(2), 10, 5));
+        let order = last_order(ALICE);
+        assert_eq!(TradeModule::orders_by_expiry(5), vec![order.hash]);
+        assert_eq!(balances(ALICE, busd), (INITIAL_TOKENS, INITIAL_TOKENS - 10, 10));
+
+        run_to_block(4);
+        assert_eq!(TradeModule::orders(order.hash).unwrap().status, OrderStatus::Created);
+
+        run_to_block(5);
+        assert_eq!(TradeModule::orders(order.hash).unwrap().status, OrderStatus::Canceled);
+        assert_eq!(balances(ALICE, busd), (INITIAL_TOKENS, INITIAL_TOKENS, 0));
+        assert!(TradeModule::orders_by_expiry(5).is_empty());
+        System::assert_has_event(RuntimeEvent::TradeModule(Event::OrderExpired { owner: ALICE, order_hash: order.hash }));
+    });
+}
+
+#[test]
+fn finished_orders_free_their_expiry_slot() {
+    new_test_ext().execute_with(|| {
+        let (busd, btc, _) = setup_trade_pair();
+
+        // MaxExpiringOrdersPerBlock = 2 => block 5 đã đầy
+        assert_ok!(expiring_order(ALICE, busd, btc, OrderType::Buy, price(1), 10, 5));
+        let canceled = last_order(ALICE);
+        assert_ok!(expiring_order(ALICE, busd, btc, OrderType::Buy, price(1), 20, 5));
+        let filled = last_order(ALICE);
+        assert_noop!(expiring_order(ALICE, busd, btc, OrderType::Buy, price(1), 30, 5), Error::<Test>::TooManyExpiringOrders);
+
+        // order bị hủy được gỡ khỏi block 5
+        assert_ok!(TradeModule::cancel_limit_order(RuntimeOrigin::signed(ALICE), canceled.hash));
+        assert_eq!(TradeModule::orders_by_expiry(5), vec![filled.hash]);
+
+        // maker khớp hết cũng được gỡ
+        assert_ok!(limit_order(BOB, busd, btc, OrderType::Sell, price(1), 20));
+        assert_eq!(TradeModule::orders(filled.hash).unwrap().status, OrderStatus::Filled);
+        assert!(TradeModule::orders_by_expiry(5).is_empty());
+
+        assert_ok!(expiring_order(ALICE, busd, btc, OrderType::Buy, price(1), 30, 5));
+        assert_ok!(expiring_order(ALICE, busd, btc, OrderType::Buy, price(1), 40, 5));
+        assert_eq!(TradeModule::do_try_state(), Ok(()));
+    });
+}
+
+// stop order được tạo gần nhất, lấy từ event StopOrderCreated
+fn last_stop_order() -> crate::StopOrder<Test> {
+    System::events().into_iter().rev().find_map(|record| match record.event {
+        RuntimeEvent::TradeModule(Event::StopOrderCreated { stop_order, .. }) => Some(stop_order),
+        _ => None,
+    }).unwrap()
+}
+
+#[test]
+fn stop_order_freezes_sell_amount_until_canceled() {
+    new_test_ext().execute_with(|| {
+        let (busd, btc, _) = setup_trade_pair();
+
+        // không đủ số dư thì không tạo được stop order
+        assert_noop!(
+            TradeModule::create_stop_order(RuntimeOrigin::signed(CHARLIE), busd, btc, OrderOpt::Limit, OrderType::Sell, price(1), price(1), INITIAL_TOKENS + 1),
+            pallet_tokens::Error::<Test>::BalanceNotEnough
+        );
+
+        assert_ok!(TradeModule::create_stop_order(RuntimeOrigin::signed(CHARLIE), busd, btc, OrderOpt::Limit, OrderType::Sell, price(1), price(1), 10));
+        let stop_order = last_stop_order();
+        assert_eq!(stop_order.status, StopOrderStatus::Pending);
+        assert_eq!(balances(CHARLIE, btc), (INITIAL_TOKENS, INITIAL_TOKENS - 10, 10));
+
+        assert_ok!(TradeModule::cancel_stop_order(RuntimeOrigin::signed(CHARLIE), stop_order.hash));
+        assert_eq!(TradeModule::stop_orders(stop_order.hash).unwrap().status, StopOrderStatus::Canceled);
+        assert_eq!(balances(CHARLIE, btc), (INITIAL_TOKENS, INITIAL_TOKENS, 0));
+        assert_noop!(TradeModule::cancel_stop_order(RuntimeOrigin::signed(CHARLIE), stop_order.hash), Error::<Test>::StopOrderNotCancelable);
+    });
+}
+
+#[test]
+fn stop_limit_order_is_activated_when_price_is_reached() {
+    new_test_ext().execute_with(|| {
+        let (busd, btc, _) = setup_trade_pair();
+
+        // stop-loss bán 10 BTC khi giá <= 2
+        assert_ok!(TradeModule::create_stop_order(RuntimeOrigin::signed(CHARLIE), busd, btc, OrderOpt::Limit, OrderType::Sell, price(2), price(2), 10));
+        let stop_order = last_stop_order();
+
+        // chưa có giao dịch => chưa trigger
+        run_to_block(2);
+        assert_eq!(TradeModule::stop_orders(stop_order.hash).unwrap().status, StopOrderStatus::Pending);
+
+        // khớp 1 BTC giá 2
+        assert_ok!(limit_order(ALICE, busd, btc, OrderType::Buy, price(2), 2));
+        assert_ok!(limit_order(BOB, busd, btc, OrderType::Sell, price(2), 1));
+
+        run_to_block(3);
+        assert_eq!(TradeModule::stop_orders(stop_order.hash).unwrap().status, StopOrderStatus::Activated);
+        System::assert_has_event(RuntimeEvent::TradeModule(Event::StopOrderActivated { owner: CHARLIE, stop_order_hash: stop_order.hash }));
+
+        // lệnh limit mới nằm trên sổ lệnh, lượng freeze của stop order được chuyển sang lệnh này
+        let order = last_order(CHARLIE);
+        assert_eq!((order.otype, order.price, order.remained_sell_amount), (OrderType::Sell, price(2), 10));
+        assert_eq!(balances(CHARLIE, btc), (INITIAL_TOKENS, INITIAL_TOKENS - 10, 10));
+        assert_eq!(TradeModule::do_try_state(), Ok(()));
+    });
+}
+
+#[test]
+fn stop_market_buy_spends_at_most_sell_amount() {
+    new_test_ext().execute_with(|| {
+        let (busd, btc, _) = setup_trade_pair();
+
+        assert_ok!(limit_order(ALICE, busd, btc, OrderType::Sell, price(2), 10));
+
+        // stop mua khi giá >= 2, chi tối đa 10 BUSD
+        assert_ok!(TradeModule::create_stop_order(RuntimeOrigin::signed(BOB), busd, btc, OrderOpt::Market, OrderType::Buy, price(2), 0, 10));
+        let stop_order = last_stop_order();
+        assert_eq!(balances(BOB, busd), (INITIAL_TOKENS, INITIAL_TOKENS - 10, 10));
+
+        assert_ok!(limit_order(CHARLIE, busd, btc, OrderType::Buy, price(2), 2));
+
+        run_to_block(2);
+        assert_eq!(TradeModule::stop_orders(stop_order.hash).unwrap().status, StopOrderStatus::Activated);
+        assert_eq!(balances(BOB, busd), (INITIAL_TOKENS - 10, INITIAL_TOKENS - 10, 0));
+        assert_eq!(balances(BOB, btc), (INITIAL_TOKENS + 5, INITIAL_TOKENS + 5, 0));
+    });
+}
+
+#[test]
+fn amend_reduces_partially_filled_order_in_place() {
+    new_test_ext().execute_with(|| {
+        let (busd, btc, tp_hash) = setup_trade_pair();
+
+        assert_ok!(limit_order(ALICE, busd, btc, OrderType::Buy, price(1), 100));
+        let order = last_order(ALICE);
+        assert_ok!(limit_order(CHARLIE, busd, btc, OrderType::Buy, price(1), 50));
+        let other = last_order(CHARLIE);
+
+        // khớp 40 => ALICE còn 60
+        assert_ok!(limit_order(BOB, busd, btc, OrderType::Sell, price(1), 40));
+
+        assert_ok!(TradeModule::amend_order(RuntimeOrigin::signed(ALICE), order.hash, price(1), 30));
+        let order = TradeModule::orders(order.hash).unwrap();
+        assert_eq!(order.status, OrderStatus::PartialFilled);
+        assert_eq!((order.sell_amount, order.buy_amount), (70, 70));
+        assert_eq!((order.remained_sell_amount, order.remained_buy_amount), (30, 30));
+        assert_eq!(balances(ALICE, busd), (INITIAL_TOKENS - 40, INITIAL_TOKENS - 70, 30));
+
+        // giữ nguyên vị trí ưu tiên, PriceItem chỉ giảm đúng phần bị giảm
+        let item = TradeModule::linked_item(tp_hash, Some(price(1))).unwrap();
+        assert_eq!(item.orders, vec![order.hash, other.hash]);
+        assert_eq!((item.sell_amount, item.buy_amount), (80, 80));
+        assert_eq!(TradeModule::do_try_state(), Ok(()));
+    });
+}
+
+#[test]
+fn amend_rejects_higher_buy_amount_at_same_price() {
+    new_test_ext().execute_with(|| {
+        let (busd, btc, _) = setup_trade_pair();
+
+        // bán 8 BTC giá 1.25 => nhận 10 BUSD
+        assert_ok!(limit_order(ALICE, busd, btc, OrderType::Sell, 125_000_000, 8));
+        let order = last_order(ALICE);
+        // mỗi lần mua 1 BTC phải trả 2 BUSD do làm tròn => ALICE còn bán 5 BTC để nhận 4 BUSD
+        for _ in 0..3 {
+            assert_ok!(limit_order(BOB, busd, btc, OrderType::Buy, price(2), 2));
+        }
+        let order = TradeModule::orders(order.hash).unwrap();
+        assert_eq!((order.remained_sell_amount, order.remained_buy_amount), (5, 4));
+
+        // 4 BTC giá 1.25 => 5 BUSD > 4 BUSD còn lại
+        assert_noop!(TradeModule::amend_order(RuntimeOrigin::signed(ALICE), order.hash, 125_000_000, 4), Error::<Test>::AmendIncreasesBuyAmount);
+    });
+}
+
+#[test]
+fn amend_to_new_price_rematches_and_keeps_expiry() {
+    new_test_ext().execute_with(|| {
+        let (busd, btc, _) = setup_trade_pair();
+
+        assert_ok!(limit_order(BOB, busd, btc, OrderType::Sell, price(2), 5));
+        assert_ok!(expiring_order(ALICE, busd, btc, OrderType::Buy, price(1), 10, 5));
+        let order = last_order(ALICE);
+
+        // đổi giá lên 2, mua 10 BTC => khớp 5 BTC của BOB, phần còn lại nằm lại trên sổ lệnh
+        assert_ok!(TradeModule::amend_order(RuntimeOrigin::signed(ALICE), order.hash, price(2), 20));
+        let order = TradeModule::orders(order.hash).unwrap();
+        assert_eq!(order.status, OrderStatus::PartialFilled);
+        assert_eq!((order.remained_sell_amount, order.remained_buy_amount), (10, 5));
+        assert_eq!(balances(ALICE, busd), (INITIAL_TOKENS - 10, INITIAL_TOKENS - 20, 10));
+        assert_eq!(TradeModule::orders_by_expiry(5), vec![order.hash]);
+        assert_eq!(TradeModule::do_try_state(), Ok(()));
+    });
+}
+
+// BOB issue ETH, chia cho ALICE, CHARLIE rồi ALICE tạo cặp BUSD/ETH => trả về (eth, tp_hash)
+fn setup_second_trade_pair(busd: H256) -> (H256, H256) {
+    Tokens::issue(RuntimeOrigin::signed(BOB), b"ETH".to_vec(), INITIAL_TOKENS * 3).unwrap();
+    let eth = Tokens::owned_token_index(BOB, 1).unwrap();
+    for account in [ALICE, CHARLIE] {
+        Tokens::transfer(RuntimeOrigin::signed(BOB), account, eth, INITIAL_TOKENS).unwrap();
+    }
+
+    TradeModule::create_trade_pair(RuntimeOrigin::signed(ALICE), busd, eth).unwrap();
+    (eth, TradeModule::trade_pair_hash_by_base_quote((busd, eth)).unwrap())
+}
+
+#[test]
+fn cancel_all_orders_cancels_opened_orders_across_pairs() {
+    new_test_ext().execute_with(|| {
+        let (busd, btc, tp_hash) = setup_trade_pair();
+        let (eth, eth_tp_hash) = setup_second_trade_pair(busd);
+
+        // order khớp hết không còn mở
+        assert_ok!(limit_order(ALICE, busd, btc, OrderType::Buy, price(1), 10));
+        assert_ok!(limit_order(BOB, busd, btc, OrderType::Sell, price(1), 10));
+
+        assert_ok!(limit_order(ALICE, busd, btc, OrderType::Buy, price(1), 20));
+        assert_ok!(limit_order(ALICE, busd, btc, OrderType::Sell, price(3), 5));
+        assert_ok!(limit_order(ALICE, busd, eth, OrderType::Buy, price(1), 30));
+        assert_eq!(TradeModule::owned_opened_trade_pair(ALICE, tp_hash), Some(()));
+        assert_eq!(TradeModule::owned_opened_trade_pair(ALICE, eth_tp_hash), Some(()));
+
+        assert_ok!(TradeModule::cancel_all_orders(RuntimeOrigin::signed(ALICE), None));
+        System::assert_last_event(RuntimeEvent::TradeModule(Event::AllOrdersCanceled { owner: ALICE, tp_hash: None, count: 3 }));
+
+        assert_eq!(balances(ALICE, busd), (INITIAL_TOKENS - 10, INITIAL_TOKENS - 10, 0));
+        assert_eq!(balances(ALICE, btc), (INITIAL_TOKENS + 10, INITIAL_TOKENS + 10, 0));
+        assert_eq!(balances(ALICE, eth), (INITIAL_TOKENS, INITIAL_TOKENS, 0));
+        assert_eq!(TradeModule::owned_opened_trade_pair(ALICE, tp_hash), None);
+        assert_eq!(TradeModule::owned_opened_trade_pair(ALICE, eth_tp_hash), None);
+        assert_eq!(TradeModule::do_try_state(), Ok(()));
+    });
+}
+
+#[test]
+fn cancel_all_orders_skips_paused_pairs() {
+    new_test_ext().execute_with(|| {
+        let (busd, btc, _) = setup_trade_pair();
+        let (eth, eth_tp_hash) = setup_second_trade_pair(busd);
+
+        assert_ok!(limit_order(ALICE, busd, btc, OrderType::Buy, price(1), 20));
+        assert_ok!(limit_order(ALICE, busd, eth, OrderType::Buy, price(1), 30));
+        let paused = last_order(ALICE);
+        assert_ok!(TradeModule::set_trade_pair_status(RuntimeOrigin::root(), eth_tp_hash, TradePairStatus::Paused));
+
+        assert_ok!(TradeModule::cancel_all_orders(RuntimeOrigin::signed(ALICE), None));
+        System::assert_last_event(RuntimeEvent::TradeModule(Event::AllOrdersCanceled { owner: ALICE, tp_hash: None, count: 1 }));
+        assert_eq!(TradeModule::orders(paused.hash).unwrap().status, OrderStatus::Created);
+        assert_eq!(balances(ALICE, busd), (INITIAL_TOKENS, INITIAL_TOKENS - 30, 30));
+
+        // chỉ định cặp đang Paused => lỗi
+        assert_noop!(TradeModule::cancel_all_orders(RuntimeOrigin::signed(ALICE), Some(eth_tp_hash)), Error::<Test>::TradePairPaused);
+    });
+}
+
+// đặt lệnh limit với time-in-force và post-only tùy chọn
+fn limit_order_with(who: AccountId, base: H256, quote: H256, otype: OrderType, price: Price, sell_amount: Balance, tif: TimeInForce, post_only: bool) -> DispatchResultWithPostInfo {
+    TradeModule::create_order(RuntimeOrigin::signed(who), base, quote, OrderOpt::Limit, otype, price, sell_amount, tif, post_only, None, None)
+}
+
+#[test]
+fn ioc_order_cancels_unfilled_remainder() {
+    new_test_ext().execute_with(|| {
+        let (busd, btc, tp_hash) = setup_trade_pair();
+
+        assert_ok!(limit_order(BOB, busd, btc, OrderType::Sell, price(1), 5));
+        assert_ok!(limit_order_with(ALICE, busd, btc, OrderType::Buy, price(1), 10, TimeInForce::ImmediateOrCancel, false));
+        let order = last_order(ALICE);
+
+        // khớp 5 BTC, phần còn lại bị hủy và không nằm trên sổ lệnh
+        assert_eq!(order.status, OrderStatus::Canceled);
+        assert_eq!((order.remained_sell_amount, order.remained_buy_amount), (5, 5));
+        assert_eq!(balances(ALICE, busd), (INITIAL_TOKENS - 5, INITIAL_TOKENS - 5, 0));
+        assert_eq!(balances(ALICE, btc), (INITIAL_TOKENS + 5, INITIAL_TOKENS + 5, 0));
+        assert_eq!(TradeModule::linked_item(tp_hash, Some(price(1))), None);
+        System::assert_last_event(RuntimeEvent::TradeModule(Event::OrderCanceled { owner: ALICE, order_hash: order.hash }));
+    });
+}
+
+#[test]
+fn fok_order_fails_unless_fully_filled() {
+    new_test_ext().execute_with(|| {
+        let (busd, btc, _) = setup_trade_pair();
+
+        assert_ok!(limit_order(BOB, busd, btc, OrderType::Sell, price(1), 5));
+        let maker = last_order(BOB);
+
+        // chỉ có 5 BTC => mua 10 BTC thất bại, không có gì thay đổi
+        assert_noop!(
+            limit_order_with(ALICE, busd, btc, OrderType::Buy, price(1), 10, TimeInForce::FillOrKill, false),
+            Error::<Test>::OrderNotFullyFilled
+        );
+
+        assert_ok!(limit_order_with(ALICE, busd, btc, OrderType::Buy, price(1), 5, TimeInForce::FillOrKill, false));
+        assert_eq!(last_order(ALICE).status, OrderStatus::Filled);
+        assert_eq!(TradeModule::orders(maker.hash).unwrap().status, OrderStatus::Filled);
+        assert_eq!(balances(ALICE, btc), (INITIAL_TOKENS + 5, INITIAL_TOKENS + 5, 0));
+    });
+}
+
+#[test]
+fn post_only_order_rests_or_is_rejected() {
+    new_test_ext().execute_with(|| {
+        let (busd, btc, tp_hash) = setup_trade_pair();
+
+        assert_ok!(limit_order(BOB, busd, btc, OrderType::Sell, price(2), 5));
+
+        // giá mua >= giá bán tốt nhất => sẽ khớp ngay nên bị từ chối
+        assert_noop!(
+            limit_order_with(ALICE, busd, btc, OrderType::Buy, price(2), 10, TimeInForce::GoodTilCanceled, true),
+            Error::<Test>::PostOnlyWouldCross
+        );
+        // post-only chỉ đi với GTC
+        assert_noop!(
+            limit_order_with(ALICE, busd, btc, OrderType::Buy, price(1), 10, TimeInForce::ImmediateOrCancel, true),
+            Error::<Test>::InvalidPostOnlyOrder
+        );
+
+        assert_ok!(limit_order_with(ALICE, busd, btc, OrderType::Buy, price(1), 10, TimeInForce::GoodTilCanceled, true));
+        let order = last_order(ALICE);
+        assert_eq!(order.status, OrderStatus::Created);
+        assert_eq!(TradeModule::linked_item(tp_hash, Some(price(1))).unwrap().orders, vec![order.hash]);
+        assert_eq!(balances(ALICE, busd), (INITIAL_TOKENS, INITIAL_TOKENS - 10, 10));
+    });
+}
+
+// phần tử tạo lệnh limit GTC trong batch_orders
+fn create_spec(base: H256, quote: H256, otype: OrderType, price: Price, sell_amount: Balance) -> OrderSpec<Test> {
+    OrderSpec::Create { base, quote, otype, price, sell_amount, tif: TimeInForce::GoodTilCanceled, post_only: false, expires_at: None }
+}
+
+#[test]
+fn batch_all_or_nothing_rolls_back_on_error() {
+    new_test_ext().execute_with(|| {
+        let (busd, btc, _) = setup_trade_pair();
+
+        // phần tử thứ 2 không đủ số dư => cả batch thất bại
+        let orders = vec![
+            create_spec(busd, btc, OrderType::Buy, price(1), 10),
+            create_spec(busd, btc, OrderType::Buy, price(1), INITIAL_TOKENS),
+        ];
+        assert_noop!(
+            TradeModule::batch_orders(RuntimeOrigin::signed(ALICE), orders.try_into().unwrap(), BatchMode::AllOrNothing),
+            pallet_tokens::Error::<Test>::BalanceNotEnough
+        );
+        assert_eq!(TradeModule::owned_orders_index(ALICE), 0);
+    });
+}
+
+#[test]
+fn batch_best_effort_skips_failed_orders() {
+    new_test_ext().execute_with(|| {
+        let (busd, btc, _) = setup_trade_pair();
+
+        assert_ok!(limit_order(ALICE, busd, btc, OrderType::Buy, price(1), 10));
+        let canceled = last_order(ALICE);
+
+        let orders = vec![
+            OrderSpec::Cancel { order_hash: canceled.hash },
+            create_spec(busd, btc, OrderType::Buy, price(1), INITIAL_TOKENS + 1),
+            create_spec(busd, btc, OrderType::Sell, price(2), 5),
+        ];
+        assert_ok!(TradeModule::batch_orders(RuntimeOrigin::signed(ALICE), orders.try_into().unwrap(), BatchMode::BestEffort));
+        System::assert_last_event(RuntimeEvent::TradeModule(Event::BatchOrdersExecuted {
+            owner: ALICE,
+            results: vec![Ok(()), Err(pallet_tokens::Error::<Test>::BalanceNotEnough.into()), Ok(())],
+        }));
+
+        assert_eq!(TradeModule::orders(canceled.hash).unwrap().status, OrderStatus::Canceled);
+        // lệnh lỗi không được ghi lại => chỉ có 2 order
+        assert_eq!(TradeModule::owned_orders_index(ALICE), 2);
+        let order = last_order(ALICE);
+        assert_eq!((order.otype, order.price, order.sell_amount), (OrderType::Sell, price(2), 5));
+        assert_eq!(balances(ALICE, busd), (INITIAL_TOKENS, INITIAL_TOKENS, 0));
+        assert_eq!(balances(ALICE, btc), (INITIAL_TOKENS, INITIAL_TOKENS - 5, 5));
+
+        // quá MaxBatchOrders phần tử => extrinsic không decode được, giữ nguyên 2 byte index của pallet và call
+        let specs = |n| (0..n).map(|_| create_spec(busd, btc, OrderType::Buy, price(1), 1)).collect::<Vec<_>>();
+        let call = RuntimeCall::TradeModule(crate::Call::batch_orders { orders: specs(20).try_into().unwrap(), mode: BatchMode::BestEffort }).encode();
+        assert!(RuntimeCall::decode(&mut &call[..]).is_ok());
+        let oversized = [&call[..2], &specs(21).encode()[..], &BatchMode::BestEffort.encode()[..]].concat();
+        assert!(RuntimeCall::decode(&mut &oversized[..]).is_err());
+    });
+}
+
+#[test]
+fn self_trade_prevention_cancels_by_mode() {
+    for mode in [SelfTradePrevention::CancelNewest, SelfTradePrevention::CancelOldest, SelfTradePrevention::CancelBoth] {
+        new_test_ext().execute_with(|| {
+            let (busd, btc, _) = setup_trade_pair();
+            assert_ok!(TradeModule::set_self_trade_prevention(RuntimeOrigin::signed(ALICE), mode));
+
+            // maker của ALICE đứng trước maker của BOB cùng mức giá
+            assert_ok!(limit_order(ALICE, busd, btc, OrderType::Sell, price(1), 5));
+            let maker = last_order(ALICE);
+            assert_ok!(limit_order(BOB, busd, btc, OrderType::Sell, price(1), 5));
+            assert_ok!(limit_order(ALICE, busd, btc, OrderType::Buy, price(1), 5));
+            let taker = last_order(ALICE);
+
+            let canceled = match mode {
+                SelfTradePrevention::CancelNewest => vec![taker.hash],
+                SelfTradePrevention::CancelOldest => vec![maker.hash],
+                _ => vec![maker.hash, taker.hash],
+            };
+            System::assert_has_event(RuntimeEvent::TradeModule(Event::SelfTradePrevented {
+                owner: ALICE,
+                taker_order_hash: taker.hash,
+                maker_order_hash: maker.hash,
+                mode,
+                canceled,
+            }));
+
+            let maker_status = TradeModule::orders(maker.hash).unwrap().status;
+            match mode {
+                // taker bị hủy, maker vẫn nằm trên sổ lệnh
+                SelfTradePrevention::CancelNewest => {
+                    assert_eq!((taker.status, maker_status), (OrderStatus::Canceled, OrderStatus::Created));
+                    assert_eq!(balances(ALICE, busd), (INITIAL_TOKENS, INITIAL_TOKENS, 0));
+                },
+                // maker bị hủy, taker khớp tiếp với lệnh của BOB
+                SelfTradePrevention::CancelOldest => {
+                    assert_eq!((taker.status, maker_status), (OrderStatus::Filled, OrderStatus::Canceled));
+                    assert_eq!(balances(ALICE, busd), (INITIAL_TOKENS - 5, INITIAL_TOKENS - 5, 0));
+                },
+                _ => {
+                    assert_eq!((taker.status, maker_status), (OrderStatus::Canceled, OrderStatus::Canceled));
+                    assert_eq!(balances(ALICE, busd), (INITIAL_TOKENS, INITIAL_TOKENS, 0));
+                },
+            }
+            assert_eq!(TradeModule::do_try_state(), Ok(()));
+        });
+    }
+}
+
+#[test]
+fn self_trade_prevention_decrements_both_orders() {
+    new_test_ext().execute_with(|| {
+        let (busd, btc, _) = setup_trade_pair();
+        assert_ok!(TradeModule::set_self_trade_prevention(RuntimeOrigin::signed(ALICE), SelfTradePrevention::DecrementAndCancel));
+
+        assert_ok!(limit_order(ALICE, busd, btc, OrderType::Sell, price(1), 5));
+        let maker = last_order(ALICE);
+        assert_ok!(limit_order(ALICE, busd, btc, OrderType::Buy, price(1), 10));
+        let taker = last_order(ALICE);
+
+        // phần chồng nhau 5 BTC bị bỏ, không tạo trade => maker về 0 bị hủy, taker còn 5 nằm lại trên sổ lệnh
+        assert_eq!(TradeModule::orders(maker.hash).unwrap().status, OrderStatus::Canceled);
+        assert_eq!(taker.status, OrderStatus::Created);
+        assert_eq!((taker.remained_sell_amount, taker.remained_buy_amount), (5, 5));
+        assert_eq!(TradeModule::order_owned_trades_index(taker.hash), 0);
+        assert_eq!(balances(ALICE, btc), (INITIAL_TOKENS, INITIAL_TOKENS, 0));
+        assert_eq!(balances(ALICE, busd), (INITIAL_TOKENS, INITIAL_TOKENS - 5, 5));
+        assert_eq!(TradeModule::do_try_state(), Ok(()));
+    });
+}
+
+// đặt market order, max_slippage tùy chọn
+fn market_order(who: AccountId, base: H256, quote: H256, otype: OrderType, amount: MarketOrderAmount<Balance>, max_slippage: Option<Permill>) -> DispatchResultWithPostInfo {
+    TradeModule::create_market_order(RuntimeOrigin::signed(who), base, quote, otype, amount, max_slippage)
+}
+
+#[test]
+fn market_order_stops_at_max_slippage() {
+    new_test_ext().execute_with(|| {
+        let (busd, btc, _) = setup_trade_pair();
+
+        // sổ lệnh trống
+        assert_noop!(market_order(ALICE, busd, btc, OrderType::Buy, MarketOrderAmount::Quote(10), None), Error::<Test>::NoMarketLiquidity);
+
+        assert_ok!(limit_order(BOB, busd, btc, OrderType::Sell, price(1), 5));
+        assert_ok!(limit_order(BOB, busd, btc, OrderType::Sell, price(2), 5));
+
+        // giá tốt nhất là 1, trượt giá tối đa 50% => chỉ khớp đến giá 1.5
+        assert_ok!(market_order(ALICE, busd, btc, OrderType::Buy, MarketOrderAmount::Quote(10), Some(Permill::from_percent(50))));
+        let order = last_order(ALICE);
+        assert_eq!(order.price, 150_000_000);
+        System::assert_last_event(RuntimeEvent::TradeModule(Event::MarketOrderExecuted {
+            owner: ALICE,
+            base_token: busd,
+            quote_token: btc,
+            order_hash: order.hash,
+            otype: OrderType::Buy,
+            base_amount: 5,
+            quote_amount: 5,
+            avg_price: Some(price(1)),
+            unfilled_amount: 5,
+        }));
+        assert_eq!(balances(ALICE, busd), (INITIAL_TOKENS - 5, INITIAL_TOKENS - 5, 0));
+        assert_eq!(balances(ALICE, btc), (INITIAL_TOKENS + 5, INITIAL_TOKENS + 5, 0));
+    });
+}
+
+#[test]
+fn market_order_stops_at_exactly_max_fills() {
+    new_test_ext().execute_with(|| {
+        MaxMarketOrderFills::set(2);
+        let (busd, btc, _) = setup_trade_pair();
+        for _ in 0..3 {
+            assert_ok!(limit_order(BOB, busd, btc, OrderType::Sell, price(1), 5));
+        }
+
+        // DAVE chỉ đủ BUSD cho đúng 2 lần khớp => ước lượng phải dừng giữa mức giá chứ không lấy cả mức giá
+        assert_ok!(Tokens::transfer(RuntimeOrigin::signed(ALICE), DAVE, busd, 10));
+        assert_ok!(market_order(DAVE, busd, btc, OrderType::Buy, MarketOrderAmount::Quote(15), None));
+        let order = last_order(DAVE);
+        assert_eq!(order.status, OrderStatus::Filled);
+        System::assert_last_event(RuntimeEvent::TradeModule(Event::MarketOrderExecuted {
+            owner: DAVE,
+            base_token: busd,
+            quote_token: btc,
+            order_hash: order.hash,
+            otype: OrderType::Buy,
+            base_amount: 10,
+            quote_amount: 10,
+            avg_price: Some(price(1)),
+            unfilled_amount: 5,
+        }));
+        assert_eq!(balances(DAVE, busd), (0, 0, 0));
+        assert_eq!(balances(DAVE, btc), (10, 10, 0));
+        assert_eq!(TradeModule::do_try_state(), Ok(()));
+    });
+}
+
+#[test]
+fn market_buy_by_base_amount_spends_at_most_base() {
+    new_test_ext().execute_with(|| {
+        let (busd, btc, _) = setup_trade_pair();
+        assert_ok!(limit_order(BOB, busd, btc, OrderType::Sell, price(1), 5));
+        assert_ok!(limit_order(BOB, busd, btc, OrderType::Sell, price(2), 5));
+
+        // chi tối đa 9 BUSD => 5 BTC ở giá 1 và 2 BTC ở giá 2
+        assert_ok!(market_order(ALICE, busd, btc, OrderType::Buy, MarketOrderAmount::Base(9), None));
+        let order = last_order(ALICE);
+        assert_eq!(order.status, OrderStatus::Filled);
+        System::assert_last_event(RuntimeEvent::TradeModule(Event::MarketOrderExecuted {
+            owner: ALICE,
+            base_token: busd,
+            quote_token: btc,
+            order_hash: order.hash,
+            otype: OrderType::Buy,
+            base_amount: 9,
+            quote_amount: 7,
+            avg_price: Some(9 * price(1) / 7),
+            unfilled_amount: 0,
+        }));
+        assert_eq!(balances(ALICE, busd), (INITIAL_TOKENS - 9, INITIAL_TOKENS - 9, 0));
+        assert_eq!(balances(ALICE, btc), (INITIAL_TOKENS + 7, INITIAL_TOKENS + 7, 0));
+        assert_eq!(TradeModule::do_try_state(), Ok(()));
+    });
+}
+
+#[test]
+fn trades_update_candles_per_interval() {
+    new_test_ext().execute_with(|| {
+        let (busd, btc, tp_hash) = setup_trade_pair();
+
+        // block 1: 5 BTC ở giá 1, block 3: 5 BTC ở giá 2 => cùng nến [0, 5) và [0, 10)
+        assert_ok!(limit_order(BOB, busd, btc, OrderType::Sell, price(1), 5));
+        assert_ok!(limit_order(ALICE, busd, btc, OrderType::Buy, price(1), 5));
+        run_to_block(3);
+        assert_ok!(limit_order(BOB, busd, btc, OrderType::Sell, price(2), 5));
+        assert_ok!(limit_order(ALICE, busd, btc, OrderType::Buy, price(2), 10));
+        // block 6: 3 BTC ở giá 1 => mở nến [5, 10) của interval 5, vẫn cộng vào nến [0, 10)
+        run_to_block(6);
+        assert_ok!(limit_order(BOB, busd, btc, OrderType::Sell, price(1), 3));
+        assert_ok!(limit_order(ALICE, busd, btc, OrderType::Buy, price(1), 3));
+
+        let candle = |start, open, high, low, close, base_volume, quote_volume, trades| Candle { start, open, high, low, close, base_volume, quote_volume, trades };
+        assert_eq!(
+            TradeModule::candles(tp_hash, 5, 10),
+            vec![candle(0, price(1), price(2), price(1), price(2), 15, 10, 2), candle(5, price(1), price(1), price(1), price(1), 3, 3, 1)]
+        );
+        assert_eq!(TradeModule::candles(tp_hash, 10, 10), vec![candle(0, price(1), price(2), price(1), price(1), 18, 13, 3)]);
+        // limit lấy các nến mới nhất
+        assert_eq!(TradeModule::candles(tp_hash, 5, 1), vec![candle(5, price(1), price(1), price(1), price(1), 3, 3, 1)]);
+        // interval không được cấu hình thì không có nến
+        assert!(TradeModule::candles(tp_hash, 7, 10).is_empty());
+    });
+}
+
+#[test]
+fn candles_are_pruned_by_retention() {
+    new_test_ext().execute_with(|| {
+        let (busd, btc, tp_hash) = setup_trade_pair();
+
+        // CandleRetention = 10, tạo 11 nến interval 5 => nến [0, 5) bị xóa
+        for i in 0..11u64 {
+            run_to_block(1 + 5 * i);
+            assert_ok!(limit_order(BOB, busd, btc, OrderType::Sell, price(1), 1));
+            assert_ok!(limit_order(ALICE, busd, btc, OrderType::Buy, price(1), 1));
+        }
+
+        let starts: Vec<u64> = TradeModule::candles(tp_hash, 5, 20).iter().map(|candle| candle.start).collect();
+        assert_eq!(starts, (1..11).map(|i| 5 * i).collect::<Vec<u64>>());
+        assert_eq!(TradeModule::candles_of((tp_hash, 5), 0), None);
+        assert_eq!(TradeModule::candle_starts((tp_hash, 5)).len(), 10);
+    });
+}
+
+#[test]
+fn twap_averages_prices_over_blocks() {
+    new_test_ext().execute_with(|| {
+        let (busd, btc, tp_hash) = setup_trade_pair();
+        assert_eq!(TradeModule::twap(tp_hash, 5), None);
+
+        // block 1: giá 1, block 11: giá 3
+        assert_ok!(limit_order(BOB, busd, btc, OrderType::Sell, price(1), 5));
+        assert_ok!(limit_order(ALICE, busd, btc, OrderType::Buy, price(1), 5));
+        run_to_block(11);
+        assert_ok!(limit_order(BOB, busd, btc, OrderType::Sell, price(3), 5));
+        assert_ok!(limit_order(ALICE, busd, btc, OrderType::Buy, price(3), 15));
+        run_to_block(21);
+
+        // window nằm trọn sau trade cuối => giá gần nhất
+        assert_eq!(TradeModule::twap(tp_hash, 5), Some(price(3)));
+        // 10 block giá 1 và 10 block giá 3
+        assert_eq!(TradeModule::twap(tp_hash, 20), Some(price(2)));
+        // không có observation tại block 6 => dùng observation block 1, cửa sổ thực tế là 20 block
+        assert_eq!(TradeModule::twap(tp_hash, 15), Some(price(2)));
+        // lịch sử chưa đủ dài
+        assert_eq!(TradeModule::twap(tp_hash, 21), None);
+        assert_eq!(TradeModule::twap(tp_hash, 0), None);
+    });
+}
+
+#[test]
+fn market_order_stops_at_end_of_book_after_self_trade_cancel() {
+    new_test_ext().execute_with(|| {
+        let (busd, btc, _) = setup_trade_pair();
+        assert_ok!(TradeModule::set_self_trade_prevention(RuntimeOrigin::signed(ALICE), SelfTradePrevention::CancelOldest));
+        assert_ok!(limit_order(ALICE, busd, btc, OrderType::Sell, price(1), 5));
+        assert_ok!(limit_order(BOB, busd, btc, OrderType::Sell, price(2), 5));
+
+        // ước lượng tính cả order của ALICE, order đó bị hủy khi khớp => taker còn dư khi đã hết sổ lệnh bán
+        assert_ok!(market_order(ALICE, busd, btc, OrderType::Buy, MarketOrderAmount::Quote(10), None));
+        let order = last_order(ALICE);
+        assert_eq!(order.status, OrderStatus::Canceled);
+        System::assert_last_event(RuntimeEvent::TradeModule(Event::MarketOrderExecuted {
+            owner: ALICE,
+            base_token: busd,
+            quote_token: btc,
+            order_hash: order.hash,
+            otype: OrderType::Buy,
+            base_amount: 10,
+            quote_amount: 5,
+            avg_price: Some(price(2)),
+            unfilled_amount: 5,
+        }));
+        assert_eq!(balances(ALICE, busd), (INITIAL_TOKENS - 10, INITIAL_TOKENS - 10, 0));
+        assert_eq!(balances(ALICE, btc), (INITIAL_TOKENS + 5, INITIAL_TOKENS + 5, 0));
+        assert_eq!(TradeModule::do_try_state(), Ok(()));
+    });
+}
+
+#[test]
+fn limit_order_stops_at_max_fills_and_cancels_crossing_remainder() {
+    new_test_ext().execute_with(|| {
+        MaxMarketOrderFills::set(2);
+        let (busd, btc, tp_hash) = setup_trade_pair();
+        for _ in 0..3 {
+            assert_ok!(limit_order(BOB, busd, btc, OrderType::Sell, price(1), 5));
+        }
+
+        // khớp 2 order rồi dừng, phần còn dư vẫn khớp được với order thứ 3 => bị hủy thay vì nằm trên sổ lệnh
+        assert_ok!(limit_order(ALICE, busd, btc, OrderType::Buy, price(1), 15));
+        let order = last_order(ALICE);
+        assert_eq!((order.status, order.remained_buy_amount), (OrderStatus::Canceled, 5));
+        System::assert_last_event(RuntimeEvent::TradeModule(Event::OrderCanceled { owner: ALICE, order_hash: order.hash }));
+        assert_eq!(balances(ALICE, busd), (INITIAL_TOKENS - 10, INITIAL_TOKENS - 10, 0));
+        assert_eq!(LinkedItemList::<Test>::get(tp_hash, Some(price(1))).unwrap().sell_amount, 5);
+        assert_eq!(TradeModule::do_try_state(), Ok(()));
+
+        // khớp hết order thứ 3 thì sổ lệnh bán trống => phần còn dư được đưa lên sổ lệnh
+        assert_ok!(limit_order(ALICE, busd, btc, OrderType::Buy, price(1), 15));
+        let order = last_order(ALICE);
+        assert_eq!((order.status, order.remained_buy_amount), (OrderStatus::PartialFilled, 10));
+        assert_eq!(LinkedItemList::<Test>::get(tp_hash, Some(price(1))).unwrap().buy_amount, 10);
+        assert_eq!(TradeModule::do_try_state(), Ok(()));
+    });
+}
+
+#[test]
+fn amended_order_stops_at_max_fills_and_cancels_crossing_remainder() {
+    new_test_ext().execute_with(|| {
+        MaxMarketOrderFills::set(2);
+        let (busd, btc, _) = setup_trade_pair();
+        for _ in 0..3 {
+            assert_ok!(limit_order(BOB, busd, btc, OrderType::Sell, price(2), 5));
+        }
+        assert_ok!(limit_order(ALICE, busd, btc, OrderType::Buy, price(1), 15));
+        let order_hash = last_order(ALICE).hash;
+
+        // đổi giá lên 2 => khớp 2 order của BOB rồi dừng, phần còn dư vẫn khớp được => bị hủy
+        assert_ok!(TradeModule::amend_order(RuntimeOrigin::signed(ALICE), order_hash, price(2), 30));
+        let order = TradeModule::orders(order_hash).unwrap();
+        assert_eq!((order.status, order.remained_buy_amount), (OrderStatus::Canceled, 5));
+        System::assert_last_event(RuntimeEvent::TradeModule(Event::OrderCanceled { owner: ALICE, order_hash }));
+        assert_eq!(balances(ALICE, busd), (INITIAL_TOKENS - 20, INITIAL_TOKENS - 20, 0));
+        assert_eq!(balances(ALICE, btc), (INITIAL_TOKENS + 10, INITIAL_TOKENS + 10, 0));
+        assert_eq!(TradeModule::do_try_state(), Ok(()));
+    });
+}
@@ -0,0 +1,255 @@
+//! Weights for pallet_trade
+//!
+//! CHƯA CÓ KẾT QUẢ BENCHMARK: các weight dưới đây được viết tay theo số lần đọc/ghi storage của từng extrinsic,
+//! không phải output của `benchmark pallet`
+//! => chạy `scripts/benchmark.sh` trên máy chuẩn để sinh lại toàn bộ file này từ `benchmarking.rs` trước khi lên production
+
+#![cfg_attr(rustfmt, rustfmt_skip)]
+#![allow(unused_parens)]
+#![allow(unused_imports)]
+
+use frame_support::{traits::Get, weights::{Weight, constants::RocksDbWeight}};
+use frame_support::sp_std::marker::PhantomData;
+
+/// Weight functions needed for pallet_trade.
+pub trait WeightInfo {
+	fn create_trade_pair() -> Weight;
+	fn create_order(l: u32, f: u32, ) -> Weight;
+	fn cancel_limit_order() -> Weight;
+	fn expire_order() -> Weight;
+	fn sweep_delisted_order() -> Weight;
+	fn update_trade_pair_rules() -> Weight;
+	fn set_trade_pair_status() -> Weight;
+	fn set_self_trade_prevention() -> Weight;
+	fn create_stop_order() -> Weight;
+	fn cancel_stop_order() -> Weight;
+	fn update_candle() -> Weight;
+}
+
+/// Weights viết tay cho pallet_trade, chưa đo trên máy chuẩn.
+pub struct SubstrateWeight<T>(PhantomData<T>);
+impl<T: frame_system::Config> WeightInfo for SubstrateWeight<T> {
+	// ước lượng: Tokens::Owners đọc 2, ghi 0
+	// ước lượng: Trade::TradePairsHashByBaseQuote đọc 2, ghi 1
+	// ước lượng: Trade::Nonce đọc 1, ghi 1
+	// ước lượng: RandomnessCollectiveFlip::RandomMaterial đọc 1, ghi 0
+	// ước lượng: System::Account đọc 1, ghi 1
+	// ước lượng: Trade::TradePairsIndex đọc 1, ghi 1
+	// ước lượng: Trade::LinkedItemList đọc 0, ghi 3
+	// ước lượng: Trade::TradePairDeposits đọc 0, ghi 1
+	// ước lượng: Trade::TradePairs đọc 0, ghi 1
+	// ước lượng: Trade::TradePairsHashByIndex đọc 0, ghi 1
+	fn create_trade_pair() -> Weight {
+		Weight::from_ref_time(52_000_000 as u64)
+			.saturating_add(T::DbWeight::get().reads(8 as u64))
+			.saturating_add(T::DbWeight::get().writes(11 as u64))
+	}
+	// ước lượng: Trade::TradePairsHashByBaseQuote đọc 1, ghi 0
+	// ước lượng: Trade::TradePairs đọc 1, ghi 1
+	// ước lượng: Trade::LinkedItemList đọc 3, ghi 3
+	// ước lượng: Tokens::Tokens đọc 1, ghi 0
+	// ước lượng: Tokens::FreeBalanceOf đọc 1, ghi 1
+	// ước lượng: Tokens::FreezedBalanceOf đọc 1, ghi 1
+	// ước lượng: Trade::Nonce đọc 1, ghi 1
+	// ước lượng: Trade::OwnedTPOpenedOrders đọc 1, ghi 1
+	// ước lượng: Trade::OwnedOrdersIndex đọc 1, ghi 1
+	// ước lượng: Trade::TradePairOwnedOrdersIndex đọc 1, ghi 1
+	// ước lượng: Trade::Orders đọc 0, ghi 1
+	// ước lượng: Trade::OwnedOrders đọc 0, ghi 1
+	// ước lượng: Trade::TradePairOwnedOrders đọc 0, ghi 1
+	// ước lượng: Tokens::BalanceOf đọc 4, ghi 4
+	// ước lượng: Trade::TPTradeDataBucket đọc 1, ghi 1
+	// ước lượng: Trade::TPHighPrices đọc 1, ghi 1
+	// ước lượng: Trade::TPHighPrices tối đa 2_490 byte (key + MaxBuckets phần tử)
+	// ước lượng: Trade::TPLowPrices đọc 1, ghi 1
+	// ước lượng: Trade::TPLowPrices tối đa 2_490 byte (key + MaxBuckets phần tử)
+	// ước lượng: Trade::TPBucketHighPrices đọc 1, ghi 1
+	// ước lượng: Trade::TPBucketHighPrices tối đa 2_462 byte (key + BucketLen phần tử)
+	// ước lượng: Trade::TPBucketLowPrices đọc 1, ghi 1
+	// ước lượng: Trade::TPBucketLowPrices tối đa 2_462 byte (key + BucketLen phần tử)
+	// ước lượng: Trade::OwnedTPClosedOrders đọc 2, ghi 2
+	// ước lượng: Trade::OrdersByExpiry đọc 1, ghi 1
+	// ước lượng: Trade::Trades đọc 0, ghi 1
+	// ước lượng: Trade::OrderOwnedTradesIndex đọc 2, ghi 2
+	// ước lượng: Trade::OwnedTradesIndex đọc 2, ghi 2
+	// ước lượng: Trade::OwnedTPTradesIndex đọc 2, ghi 2
+	// ước lượng: Trade::TradePairOwnedTradesIndex đọc 1, ghi 1
+	/// The range of component `l` is `[1, 50]`.
+	/// The range of component `f` is `[1, 50]`.
+	fn create_order(l: u32, f: u32, ) -> Weight {
+		Weight::from_ref_time(61_000_000 as u64)
+			.saturating_add(Weight::from_ref_time(6_400_000 as u64).saturating_mul(l as u64))
+			.saturating_add(Weight::from_ref_time(118_000_000 as u64).saturating_mul(f as u64))
+			.saturating_add(T::DbWeight::get().reads(13 as u64))
+			.saturating_add(T::DbWeight::get().reads((2 as u64).saturating_mul(l as u64)))
+			.saturating_add(T::DbWeight::get().reads((31 as u64).saturating_mul(f as u64)))
+			.saturating_add(T::DbWeight::get().writes(12 as u64))
+			.saturating_add(T::DbWeight::get().writes((2 as u64).saturating_mul(l as u64)))
+			.saturating_add(T::DbWeight::get().writes((37 as u64).saturating_mul(f as u64)))
+	}
+	// ước lượng: Trade::Orders đọc 1, ghi 1
+	// ước lượng: Trade::TradePairsHashByBaseQuote đọc 1, ghi 0
+	// ước lượng: Trade::TradePairs đọc 1, ghi 0
+	// ước lượng: Trade::LinkedItemList đọc 3, ghi 3
+	// ước lượng: Trade::OwnedTPOpenedOrders đọc 1, ghi 1
+	// ước lượng: Trade::OwnedTPClosedOrders đọc 1, ghi 1
+	// ước lượng: Trade::OrdersByExpiry đọc 1, ghi 1
+	// ước lượng: Tokens::Tokens đọc 1, ghi 0
+	// ước lượng: Tokens::FreeBalanceOf đọc 1, ghi 1
+	// ước lượng: Tokens::FreezedBalanceOf đọc 1, ghi 1
+	fn cancel_limit_order() -> Weight {
+		Weight::from_ref_time(58_000_000 as u64)
+			.saturating_add(T::DbWeight::get().reads(12 as u64))
+			.saturating_add(T::DbWeight::get().writes(9 as u64))
+	}
+	// ước lượng: Trade::Orders đọc 1, ghi 1
+	// ước lượng: Trade::TradePairsHashByBaseQuote đọc 1, ghi 0
+	// ước lượng: Trade::LinkedItemList đọc 3, ghi 3
+	// ước lượng: Trade::OwnedTPOpenedOrders đọc 1, ghi 1
+	// ước lượng: Trade::OwnedOpenedTradePairs đọc 0, ghi 1
+	// ước lượng: Trade::OwnedTPClosedOrders đọc 1, ghi 1
+	// ước lượng: Trade::OrdersByExpiry đọc 1, ghi 1
+	// ước lượng: Tokens::Tokens đọc 1, ghi 0
+	// ước lượng: Tokens::FreeBalanceOf đọc 1, ghi 1
+	// ước lượng: Tokens::FreezedBalanceOf đọc 1, ghi 1
+	fn expire_order() -> Weight {
+		Weight::from_ref_time(50_000_000 as u64)
+			.saturating_add(T::DbWeight::get().reads(11 as u64))
+			.saturating_add(T::DbWeight::get().writes(10 as u64))
+	}
+	// ước lượng: Trade::OrderList đọc 1, ghi 0
+	// ước lượng: Trade::Orders đọc 1, ghi 1
+	// ước lượng: Trade::TradePairsHashByBaseQuote đọc 1, ghi 0
+	// ước lượng: Trade::LinkedItemList đọc 4, ghi 3
+	// ước lượng: Trade::OwnedTPOpenedOrders đọc 1, ghi 1
+	// ước lượng: Trade::OwnedOpenedTradePairs đọc 0, ghi 1
+	// ước lượng: Trade::OwnedTPClosedOrders đọc 1, ghi 1
+	// ước lượng: Trade::OrdersByExpiry đọc 1, ghi 1
+	// ước lượng: Tokens::Tokens đọc 1, ghi 0
+	// ước lượng: Tokens::FreeBalanceOf đọc 1, ghi 1
+	// ước lượng: Tokens::FreezedBalanceOf đọc 1, ghi 1
+	fn sweep_delisted_order() -> Weight {
+		Weight::from_ref_time(55_000_000 as u64)
+			.saturating_add(T::DbWeight::get().reads(13 as u64))
+			.saturating_add(T::DbWeight::get().writes(10 as u64))
+	}
+	// ước lượng: Trade::TradePairs đọc 1, ghi 1
+	fn update_trade_pair_rules() -> Weight {
+		Weight::from_ref_time(20_000_000 as u64)
+			.saturating_add(T::DbWeight::get().reads(1 as u64))
+			.saturating_add(T::DbWeight::get().writes(1 as u64))
+	}
+	// ước lượng: Trade::TradePairs đọc 1, ghi 1
+	// ước lượng: Trade::DelistingTradePairs đọc 0, ghi 1
+	// ước lượng: Trade::TradePairDeposits đọc 1, ghi 1
+	// ước lượng: System::Account đọc 1, ghi 1
+	fn set_trade_pair_status() -> Weight {
+		Weight::from_ref_time(30_000_000 as u64)
+			.saturating_add(T::DbWeight::get().reads(3 as u64))
+			.saturating_add(T::DbWeight::get().writes(4 as u64))
+	}
+	// ước lượng: Trade::SelfTradePreventionOf đọc 0, ghi 1
+	fn set_self_trade_prevention() -> Weight {
+		Weight::from_ref_time(15_000_000 as u64)
+			.saturating_add(T::DbWeight::get().writes(1 as u64))
+	}
+	// ước lượng: Trade::TradePairsHashByBaseQuote đọc 1, ghi 0
+	// ước lượng: Trade::TradePairs đọc 1, ghi 0
+	// ước lượng: Trade::Nonce đọc 1, ghi 1
+	// ước lượng: RandomnessCollectiveFlip::RandomMaterial đọc 1, ghi 0
+	// ước lượng: Tokens::Tokens đọc 1, ghi 0
+	// ước lượng: Tokens::FreeBalanceOf đọc 1, ghi 1
+	// ước lượng: Tokens::FreezedBalanceOf đọc 1, ghi 1
+	// ước lượng: Trade::StopOrders đọc 0, ghi 1
+	// ước lượng: Trade::TPStopOrders đọc 1, ghi 1
+	// ước lượng: Trade::TPStopTriggerPrices đọc 1, ghi 1
+	// ước lượng: Trade::StopCheckPairs đọc 0, ghi 1
+	fn create_stop_order() -> Weight {
+		Weight::from_ref_time(45_000_000 as u64)
+			.saturating_add(T::DbWeight::get().reads(9 as u64))
+			.saturating_add(T::DbWeight::get().writes(8 as u64))
+	}
+	// ước lượng: Trade::StopOrders đọc 1, ghi 1
+	// ước lượng: Trade::TradePairsHashByBaseQuote đọc 1, ghi 0
+	// ước lượng: Trade::TPStopOrders đọc 1, ghi 1
+	// ước lượng: Trade::TPStopTriggerPrices đọc 1, ghi 1
+	// ước lượng: Tokens::Tokens đọc 1, ghi 0
+	// ước lượng: Tokens::FreeBalanceOf đọc 1, ghi 1
+	// ước lượng: Tokens::FreezedBalanceOf đọc 1, ghi 1
+	fn cancel_stop_order() -> Weight {
+		Weight::from_ref_time(35_000_000 as u64)
+			.saturating_add(T::DbWeight::get().reads(7 as u64))
+			.saturating_add(T::DbWeight::get().writes(5 as u64))
+	}
+	// ước lượng: Trade::Candles đọc 1, ghi 2
+	// ước lượng: Trade::CandleStarts đọc 1, ghi 1
+	fn update_candle() -> Weight {
+		Weight::from_ref_time(18_000_000 as u64)
+			.saturating_add(T::DbWeight::get().reads(2 as u64))
+			.saturating_add(T::DbWeight::get().writes(3 as u64))
+	}
+}
+
+// For backwards compatibility and tests
+impl WeightInfo for () {
+	fn create_trade_pair() -> Weight {
+		Weight::from_ref_time(52_000_000 as u64)
+			.saturating_add(RocksDbWeight::get().reads(8 as u64))
+			.saturating_add(RocksDbWeight::get().writes(11 as u64))
+	}
+	fn create_order(l: u32, f: u32, ) -> Weight {
+		Weight::from_ref_time(61_000_000 as u64)
+			.saturating_add(Weight::from_ref_time(6_400_000 as u64).saturating_mul(l as u64))
+			.saturating_add(Weight::from_ref_time(118_000_000 as u64).saturating_mul(f as u64))
+			.saturating_add(RocksDbWeight::get().reads(13 as u64))
+			.saturating_add(RocksDbWeight::get().reads((2 as u64).saturating_mul(l as u64)))
+			.saturating_add(RocksDbWeight::get().reads((31 as u64).saturating_mul(f as u64)))
+			.saturating_add(RocksDbWeight::get().writes(12 as u64))
+			.saturating_add(RocksDbWeight::get().writes((2 as u64).saturating_mul(l as u64)))
+			.saturating_add(RocksDbWeight::get().writes((37 as u64).saturating_mul(f as u64)))
+	}
+	fn cancel_limit_order() -> Weight {
+		Weight::from_ref_time(58_000_000 as u64)
+			.saturating_add(RocksDbWeight::get().reads(12 as u64))
+			.saturating_add(RocksDbWeight::get().writes(9 as u64))
+	}
+	fn expire_order() -> Weight {
+		Weight::from_ref_time(50_000_000 as u64)
+			.saturating_add(RocksDbWeight::get().reads(11 as u64))
+			.saturating_add(RocksDbWeight::get().writes(10 as u64))
+	}
+	fn sweep_delisted_order() -> Weight {
+		Weight::from_ref_time(55_000_000 as u64)
+			.saturating_add(RocksDbWeight::get().reads(13 as u64))
+			.saturating_add(RocksDbWeight::get().writes(10 as u64))
+	}
+	fn update_trade_pair_rules() -> Weight {
+		Weight::from_ref_time(20_000_000 as u64)
+			.saturating_add(RocksDbWeight::get().reads(1 as u64))
+			.saturating_add(RocksDbWeight::get().writes(1 as u64))
+	}
+	fn set_trade_pair_status() -> Weight {
+		Weight::from_ref_time(30_000_000 as u64)
+			.saturating_add(RocksDbWeight::get().reads(3 as u64))
+			.saturating_add(RocksDbWeight::get().writes(4 as u64))
+	}
+	fn set_self_trade_prevention() -> Weight {
+		Weight::from_ref_time(15_000_000 as u64)
+			.saturating_add(RocksDbWeight::get().writes(1 as u64))
+	}
+	fn create_stop_order() -> Weight {
+		Weight::from_ref_time(45_000_000 as u64)
+			.saturating_add(RocksDbWeight::get().reads(9 as u64))
+			.saturating_add(RocksDbWeight::get().writes(8 as u64))
+	}
+	fn cancel_stop_order() -> Weight {
+		Weight::from_ref_time(35_000_000 as u64)
+			.saturating_add(RocksDbWeight::get().reads(7 as u64))
+			.saturating_add(RocksDbWeight::get().writes(5 as u64))
+	}
+	fn update_candle() -> Weight {
+		Weight::from_ref_time(18_000_000 as u64)
+			.saturating_add(RocksDbWeight::get().reads(2 as u64))
+			.saturating_add(RocksDbWeight::get().writes(3 as u64))
+	}
+}
@@ -13,7 +13,8 @@ use sp_core::{crypto::KeyTypeId, OpaqueMetadata};
 use sp_runtime::{
 	create_runtime_str, generic, impl_opaque_keys,
 	traits::{
-		AccountIdLookup, BlakeTwo256, Block as BlockT, IdentifyAccount, NumberFor, One, Verify,
+		AccountIdConversion, AccountIdLookup, BlakeTwo256, Block as BlockT, IdentifyAccount,
+		NumberFor, One, Verify,
 	},
 	transaction_validity::{TransactionSource, TransactionValidity},
 	ApplyExtrinsicResult, MultiSignature,
@@ -34,7 +35,9 @@ pub use frame_support::{
 	},
 	StorageValue,
 };
+use frame_support::{bounded_vec, BoundedVec, PalletId};
 pub use frame_system::Call as SystemCall;
+use frame_system::EnsureRoot;
 pub use pallet_balances::Call as BalancesCall;
 pub use pallet_timestamp::Call as TimestampCall;
 use pallet_transaction_payment::{ConstFeeMultiplier, CurrencyAdapter, Multiplier};
@@ -53,6 +56,8 @@ pub type AccountId = <<Signature as Verify>::Signer as IdentifyAccount>::Account
 
 pub type Balance = u128;
 
+pub type Price = u128;
+
 pub type Index = u32;
 
 pub type Hash = sp_core::H256;
@@ -78,7 +83,7 @@ pub const VERSION: RuntimeVersion = RuntimeVersion {
 	spec_name: create_runtime_str!("node-template"),
 	impl_name: create_runtime_str!("node-template"),
 	authoring_version: 1,
-	spec_version: 100,
+	spec_version: 101,
 	impl_version: 1,
 	apis: RUNTIME_API_VERSIONS,
 	transaction_version: 1,
@@ -210,22 +215,67 @@ impl pallet_tokens::Config for Runtime {
 	type RuntimeEvent = RuntimeEvent;
 	type Currency = Balances;
 	type TokenRandom = RandomnessCollectiveFlip;
+	type AdminOrigin = EnsureRoot<AccountId>;
+	type MaxSymbolLength = ConstU32<16>;
+	type WeightInfo = pallet_tokens::weights::SubstrateWeight<Runtime>;
 }
 
 parameter_types! {
 	pub const PriceFactor: u128 = 100_000_000;
     pub const BlocksPerDay: u32 = 6 * 60 * 24;
-    pub const OpenedOrdersArrayCap: u8 = 20;
-    pub const ClosedOrdersArrayCap: u8 = 100;
+    pub const OpenedOrdersArrayCap: u32 = 100;
+    pub const ClosedOrdersArrayCap: u32 = 100;
+	pub const MaxOrdersPerPriceLevel: u32 = 200;
+	pub const MakerFee: Permill = Permill::from_parts(1_000);	// 0.1%
+	pub const TakerFee: Permill = Permill::from_parts(2_000);	// 0.2%
+	pub const TradePalletId: PalletId = PalletId(*b"py/trade");
+	pub TradeFeeCollector: AccountId = TradePalletId::get().into_account_truncating();
+	pub const MaxExpiringOrdersPerBlock: u32 = 100;
+	pub const MaxStopOrdersPerBlock: u32 = 50;
+	pub const MaxCancelAllOrders: u32 = 50;
+	pub const MaxBatchOrders: u32 = 20;
+	pub const MaxDelistOrdersPerBlock: u32 = 50;
+	pub const TradePairDeposit: Balance = 1_000_000_000_000;
+	pub const OwnerOnlyTradePairCreation: bool = false;
+	pub const MaxMarketOrderFills: u32 = 50;
+	pub const MaxCandleIntervals: u32 = 4;
+	pub CandleIntervals: BoundedVec<u32, MaxCandleIntervals> = bounded_vec![MINUTES, HOURS, DAYS];
+	pub const CandleRetention: u32 = 500;
+	pub const PriceObservationPeriod: u32 = MINUTES;
+	pub const MaxPriceObservations: u32 = 24 * 60;	// 24h với mỗi observation cách nhau 1 phút
+	pub const MaxStopOrdersPerPrice: u32 = 100;
+	pub const MaxStopTriggerPrices: u32 = 200;
 }
 impl pallet_trade::Config for Runtime {
 	type RuntimeEvent = RuntimeEvent;
 	type TradeRandom = RandomnessCollectiveFlip;
-	type Price = u128;
+	type Price = Price;
 	type PriceFactor = PriceFactor;
 	type BlocksPerDay = BlocksPerDay;
 	type OpenedOrdersArrayCap = OpenedOrdersArrayCap;
 	type ClosedOrdersArrayCap = ClosedOrdersArrayCap;
+	type MaxOrdersPerPriceLevel = MaxOrdersPerPriceLevel;
+	type MakerFee = MakerFee;
+	type TakerFee = TakerFee;
+	type FeeCollector = TradeFeeCollector;
+	type MaxExpiringOrdersPerBlock = MaxExpiringOrdersPerBlock;
+	type MaxStopOrdersPerBlock = MaxStopOrdersPerBlock;
+	type MaxCancelAllOrders = MaxCancelAllOrders;
+	type MaxBatchOrders = MaxBatchOrders;
+	type MaxDelistOrdersPerBlock = MaxDelistOrdersPerBlock;
+	type AdminOrigin = EnsureRoot<AccountId>;
+	type DepositCurrency = Balances;
+	type TradePairDeposit = TradePairDeposit;
+	type OwnerOnlyTradePairCreation = OwnerOnlyTradePairCreation;
+	type MaxMarketOrderFills = MaxMarketOrderFills;
+	type MaxCandleIntervals = MaxCandleIntervals;
+	type CandleIntervals = CandleIntervals;
+	type CandleRetention = CandleRetention;
+	type PriceObservationPeriod = PriceObservationPeriod;
+	type MaxPriceObservations = MaxPriceObservations;
+	type MaxStopOrdersPerPrice = MaxStopOrdersPerPrice;
+	type MaxStopTriggerPrices = MaxStopTriggerPrices;
+	type WeightInfo = pallet_trade::weights::SubstrateWeight<Runtime>;
 }
 
 construct_runtime!(
@@ -265,12 +315,18 @@ pub type SignedExtra = (
 pub type UncheckedExtrinsic =
 	generic::UncheckedExtrinsic<Address, RuntimeCall, Signature, SignedExtra>;
 pub type SignedPayload = generic::SignedPayload<RuntimeCall, SignedExtra>;
+// migration storage của các pallet, chạy 1 lần khi nâng spec_version, tokens phải chạy trước trade
+pub type Migrations = (
+	pallet_tokens::migrations::v1::MigrateToV1<Runtime>,
+	pallet_trade::migrations::v1::MigrateToV1<Runtime>,
+);
 pub type Executive = frame_executive::Executive<
 	Runtime,
 	Block,
 	frame_system::ChainContext<Runtime>,
 	Runtime,
 	AllPalletsWithSystem,
+	Migrations,
 >;
 
 #[cfg(feature = "runtime-benchmarks")]
@@ -284,7 +340,8 @@ mod benches {
 		[frame_system, SystemBench::<Runtime>]
 		[pallet_balances, Balances]
 		[pallet_timestamp, Timestamp]
-		[pallet_template, TemplateModule]
+		[pallet_tokens, Tokens]
+		[pallet_trade, Trade]
 	);
 }
 
@@ -433,6 +490,20 @@ impl_runtime_apis! {
 		}
 	}
 
+	impl pallet_trade::runtime_api::TradeApi<Block, Hash, BlockNumber, Price, Balance> for Runtime {
+		fn order_book(tp_hash: Hash, depth: u32) -> pallet_trade::runtime_api::OrderBook<Price, Balance> {
+			Trade::order_book(tp_hash, depth)
+		}
+
+		fn candles(tp_hash: Hash, interval: u32, limit: u32) -> Vec<pallet_trade::runtime_api::Candle<BlockNumber, Price, Balance>> {
+			Trade::candles(tp_hash, interval, limit)
+		}
+
+		fn twap(tp_hash: Hash, window: BlockNumber) -> Option<Price> {
+			Trade::twap(tp_hash, window)
+		}
+	}
+
 	#[cfg(feature = "runtime-benchmarks")]
 	impl frame_benchmarking::Benchmark<Block> for Runtime {
 		fn benchmark_metadata(extra: bool) -> (
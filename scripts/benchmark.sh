@@ -0,0 +1,30 @@
+#!/usr/bin/env bash
+# This script is meant to be run on Unix/Linux based systems
+# Sinh lại pallets/*/src/weights.rs từ benchmarking.rs, chạy trên máy chuẩn
+set -e
+
+cd $(dirname ${BASH_SOURCE[0]})/..
+
+# template sinh trait WeightInfo riêng của pallet, lấy từ bản substrate mà cargo đã checkout
+TEMPLATE=${TEMPLATE:-$(find ${CARGO_HOME:-$HOME/.cargo}/git/checkouts -path '*substrate*/.maintain/frame-weight-template.hbs' | head -n 1)}
+if [ -z "$TEMPLATE" ] ; then
+   echo "*** frame-weight-template.hbs not found, set TEMPLATE=<path>"
+   exit 1
+fi
+
+echo "*** Building node with runtime-benchmarks"
+cargo build --release --features runtime-benchmarks
+
+for pallet in tokens trade ; do
+   echo "*** Benchmarking pallet_$pallet"
+   ./target/release/node-template benchmark pallet \
+      --chain=dev \
+      --execution=wasm \
+      --wasm-execution=compiled \
+      --pallet=pallet_$pallet \
+      --extrinsic='*' \
+      --steps=50 \
+      --repeat=20 \
+      --template=$TEMPLATE \
+      --output=pallets/$pallet/src/weights.rs
+done